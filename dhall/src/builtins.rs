@@ -1,5 +1,4 @@
 use std::collections::{BTreeMap, HashMap};
-use std::convert::TryInto;
 
 use crate::operations::{BinOp, OpKind};
 use crate::semantics::{
@@ -284,7 +283,7 @@ macro_rules! make_closure {
         rc(ExprKind::Op(OpKind::BinOp(
             BinOp::NaturalPlus,
             make_closure!($($v)*),
-            rc(ExprKind::Num(NumKind::Natural(1)))
+            rc(ExprKind::Num(NumKind::Natural(1u64.into())))
         )))
     };
     ([ $($head:tt)* ] # $($tail:tt)*) => {{
@@ -328,19 +327,19 @@ fn apply_builtin(b: Builtin, args: Vec<Nir>, env: NzEnv) -> NirKind {
             Ret::NirKind(EmptyOptionalLit(t.clone()))
         }
         (Builtin::NaturalIsZero, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Bool(*n == 0))),
+            Num(Natural(n)) => Ret::NirKind(Num(Bool(n.is_zero()))),
             _ => Ret::DoneAsIs,
         },
         (Builtin::NaturalEven, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Bool(*n % 2 == 0))),
+            Num(Natural(n)) => Ret::NirKind(Num(Bool(n.is_even()))),
             _ => Ret::DoneAsIs,
         },
         (Builtin::NaturalOdd, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Bool(*n % 2 != 0))),
+            Num(Natural(n)) => Ret::NirKind(Num(Bool(!n.is_even()))),
             _ => Ret::DoneAsIs,
         },
         (Builtin::NaturalToInteger, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Integer(*n as isize))),
+            Num(Natural(n)) => Ret::NirKind(Num(Integer(n.clone().into()))),
             _ => Ret::DoneAsIs,
         },
         (Builtin::NaturalShow, [n]) => match &*n.kind() {
@@ -349,16 +348,18 @@ fn apply_builtin(b: Builtin, args: Vec<Nir>, env: NzEnv) -> NirKind {
         },
         (Builtin::NaturalSubtract, [a, b]) => match (&*a.kind(), &*b.kind()) {
             (Num(Natural(a)), Num(Natural(b))) => {
-                Ret::NirKind(Num(Natural(if b > a { b - a } else { 0 })))
+                Ret::NirKind(Num(Natural(b.saturating_sub(a))))
             }
-            (Num(Natural(0)), _) => Ret::Nir(b.clone()),
-            (_, Num(Natural(0))) => Ret::NirKind(Num(Natural(0))),
-            _ if a == b => Ret::NirKind(Num(Natural(0))),
+            (Num(Natural(a)), _) if a.is_zero() => Ret::Nir(b.clone()),
+            (_, Num(Natural(b))) if b.is_zero() => {
+                Ret::NirKind(Num(Natural(0u64.into())))
+            }
+            _ if a == b => Ret::NirKind(Num(Natural(0u64.into()))),
             _ => Ret::DoneAsIs,
         },
         (Builtin::IntegerShow, [n]) => match &*n.kind() {
             Num(Integer(n)) => {
-                let s = if *n < 0 {
+                let s = if n.is_negative() {
                     n.to_string()
                 } else {
                     format!("+{}", n)
@@ -369,7 +370,7 @@ fn apply_builtin(b: Builtin, args: Vec<Nir>, env: NzEnv) -> NirKind {
         },
         (Builtin::IntegerToDouble, [n]) => match &*n.kind() {
             Num(Integer(n)) => {
-                Ret::NirKind(Num(Double(NaiveDouble::from(*n as f64))))
+                Ret::NirKind(Num(Double(NaiveDouble::from(n.to_f64()))))
             }
             _ => Ret::DoneAsIs,
         },
@@ -378,9 +379,7 @@ fn apply_builtin(b: Builtin, args: Vec<Nir>, env: NzEnv) -> NirKind {
             _ => Ret::DoneAsIs,
         },
         (Builtin::IntegerClamp, [n]) => match &*n.kind() {
-            Num(Integer(n)) => {
-                Ret::NirKind(Num(Natural((*n).try_into().unwrap_or(0))))
-            }
+            Num(Integer(n)) => Ret::NirKind(Num(Natural(n.clamp_to_natural()))),
             _ => Ret::DoneAsIs,
         },
         (Builtin::DoubleShow, [n]) => match &*n.kind() {
@@ -402,8 +401,8 @@ fn apply_builtin(b: Builtin, args: Vec<Nir>, env: NzEnv) -> NirKind {
             _ => Ret::DoneAsIs,
         },
         (Builtin::ListLength, [_, l]) => match &*l.kind() {
-            EmptyListLit(_) => Ret::NirKind(Num(Natural(0))),
-            NEListLit(xs) => Ret::NirKind(Num(Natural(xs.len()))),
+            EmptyListLit(_) => Ret::NirKind(Num(Natural(0u64.into()))),
+            NEListLit(xs) => Ret::NirKind(Num(Natural(xs.len().into()))),
             _ => Ret::DoneAsIs,
         },
         (Builtin::ListHead, [_, l]) => match &*l.kind() {
@@ -449,7 +448,7 @@ fn apply_builtin(b: Builtin, args: Vec<Nir>, env: NzEnv) -> NirKind {
                                     let mut kvs = HashMap::new();
                                     kvs.insert(
                                         "index".into(),
-                                        Nir::from_kind(Num(Natural(i))),
+                                        Nir::from_kind(Num(Natural(i.into()))),
                                     );
                                     kvs.insert("value".into(), e.clone());
                                     Nir::from_kind(RecordLit(kvs))
@@ -496,14 +495,16 @@ fn apply_builtin(b: Builtin, args: Vec<Nir>, env: NzEnv) -> NirKind {
                     λ(x : Natural) ->
                     1 + var(x)
                 )))
-                .app(Num(Natural(0)).into_nir()),
+                .app(Num(Natural(0u64.into())).into_nir()),
         ),
 
         (Builtin::NaturalFold, [n, t, succ, zero]) => match &*n.kind() {
-            Num(Natural(0)) => Ret::Nir(zero.clone()),
+            Num(Natural(n)) if n.is_zero() => Ret::Nir(zero.clone()),
             Num(Natural(n)) => {
                 let fold = Nir::from_builtin(Builtin::NaturalFold)
-                    .app(Num(Natural(n - 1)).into_nir())
+                    .app(
+                        Num(Natural(n.saturating_sub(&1u64.into()))).into_nir(),
+                    )
                     .app(t.clone())
                     .app(succ.clone())
                     .app(zero.clone());
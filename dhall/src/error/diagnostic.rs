@@ -0,0 +1,365 @@
+use annotate_snippets::{
+    display_list::DisplayList,
+    snippet::{Annotation, AnnotationType, Slice, Snippet, SourceAnnotation},
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::Serialize;
+use serde_json::json;
+use std::path::{Component, Path};
+use url::Url;
+
+use crate::semantics::resolve::ImportLocation;
+use crate::syntax::ParsedSpan;
+
+/// A structured error message, as displayed by `ErrorBuilder`.
+///
+/// It can be serialized (e.g. to JSON) for consumption by other tools, or displayed as text with
+/// source snippets.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The source locations the message refers to.
+    pub spans: Vec<DiagnosticSpan>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+/// A labelled range of source text.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticSpan {
+    /// The file the span points into, or `None` if the text was not read from a file.
+    pub file: Option<String>,
+    pub start: Position,
+    /// The position just after the end of the span.
+    pub end: Position,
+    pub label: String,
+    /// Whether this is the location of the error, as opposed to some related location.
+    pub primary: bool,
+    /// Used to display the source text.
+    #[serde(skip)]
+    span: Option<ParsedSpan>,
+}
+
+/// A position in a file. Both the line and the column start at 1; columns count characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl ToString) -> Self {
+        Diagnostic {
+            severity,
+            message: message.to_string(),
+            spans: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+    pub fn error(message: impl ToString) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    /// The file the first primary span points into, if any.
+    pub fn file(&self) -> Option<&str> {
+        self.spans
+            .iter()
+            .find(|span| span.primary)
+            .and_then(|span| span.file.as_deref())
+    }
+}
+
+impl DiagnosticSpan {
+    pub fn new(span: ParsedSpan, label: impl ToString, primary: bool) -> Self {
+        let file = match span.location() {
+            ImportLocation::Missing => None,
+            location => Some(location.to_string()),
+        };
+        let ((start_line, start_col), (end_line, end_col)) = span.line_col();
+        DiagnosticSpan {
+            file,
+            start: Position {
+                line: start_line,
+                column: start_col,
+            },
+            end: Position {
+                line: end_line,
+                column: end_col,
+            },
+            label: label.to_string(),
+            primary,
+            span: Some(span),
+        }
+    }
+
+    /// A span for which we only know the position, e.g. from the parser.
+    pub(crate) fn from_position(
+        file: Option<String>,
+        start: Position,
+        end: Position,
+        label: impl ToString,
+    ) -> Self {
+        DiagnosticSpan {
+            file,
+            start,
+            end,
+            label: label.to_string(),
+            primary: true,
+            span: None,
+        }
+    }
+}
+
+/// The characters that must be escaped in the path of a URI.
+#[rustfmt::skip]
+const PATH: &AsciiSet = &CONTROLS
+    .add(b' ').add(b'"').add(b'#').add(b'%').add(b'<')
+    .add(b'>').add(b'?').add(b'`').add(b'{').add(b'}');
+
+/// The SARIF `artifactLocation` of a file, whose `uri` must be a URI. Absolute paths become `file:`
+/// URIs, and relative paths become references relative to the `%SRCROOT%` base. Environment
+/// variables have no location.
+fn artifact_location(file: &str) -> Option<serde_json::Value> {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        let url = Url::from_file_path(path).ok()?;
+        return Some(json!({ "uri": url.as_str() }));
+    }
+    match Url::parse(file) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+            Some(json!({ "uri": url.as_str() }))
+        }
+        Ok(_) => None,
+        Err(_) => {
+            let segments: Vec<String> = path
+                .components()
+                .filter(|c| *c != Component::CurDir)
+                .map(|c| {
+                    let segment = c.as_os_str().to_string_lossy();
+                    utf8_percent_encode(&segment, PATH).to_string()
+                })
+                .collect();
+            Some(json!({
+                "uri": segments.join("/"),
+                "uriBaseId": "%SRCROOT%",
+            }))
+        }
+    }
+}
+
+/// A SARIF 2.1.0 log with a result for each diagnostic.
+pub fn to_sarif(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    let location = |span: &DiagnosticSpan| {
+        let mut physical = json!({
+            "region": {
+                "startLine": span.start.line,
+                "startColumn": span.start.column,
+                "endLine": span.end.line,
+                "endColumn": span.end.column,
+            }
+        });
+        if let Some(artifact) = span.file.as_deref().and_then(artifact_location)
+        {
+            physical["artifactLocation"] = artifact;
+        }
+        json!({
+            "physicalLocation": physical,
+            "message": { "text": span.label },
+        })
+    };
+    let result = |diagnostic: &Diagnostic| {
+        let (primary, related): (Vec<_>, Vec<_>) =
+            diagnostic.spans.iter().partition(|span| span.primary);
+        let level = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note | Severity::Help => "note",
+        };
+        let text = Some(&diagnostic.message)
+            .into_iter()
+            .chain(&diagnostic.notes)
+            .chain(&diagnostic.help)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+        json!({
+            "level": level,
+            "message": { "text": text },
+            "locations": primary.into_iter().map(location).collect::<Vec<_>>(),
+            "relatedLocations": related.into_iter().map(location).collect::<Vec<_>>(),
+        })
+    };
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "dhall",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/Nadrieril/dhall-rust",
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": diagnostics.iter().map(result).collect::<Vec<_>>(),
+        }],
+    })
+}
+
+fn annotation_type(severity: Severity) -> AnnotationType {
+    match severity {
+        Severity::Error => AnnotationType::Error,
+        Severity::Warning => AnnotationType::Warning,
+        Severity::Note => AnnotationType::Note,
+        Severity::Help => AnnotationType::Help,
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // Group annotations by the file they point into, keeping the order in which files first
+        // appear.
+        let mut files: Vec<(&ParsedSpan, Vec<SourceAnnotation>)> = Vec::new();
+        for annot in &self.spans {
+            let span = match &annot.span {
+                Some(span) => span,
+                None => continue,
+            };
+            let annotation = SourceAnnotation {
+                label: annot.label.clone(),
+                annotation_type: if annot.primary {
+                    AnnotationType::Error
+                } else {
+                    AnnotationType::Help
+                },
+                range: span.as_char_range(),
+            };
+            match files.iter_mut().find(|(sp, _)| sp.same_file(span)) {
+                Some((_, annots)) => annots.push(annotation),
+                None => files.push((span, vec![annotation])),
+            }
+        }
+        let slices = files
+            .into_iter()
+            .map(|(span, annotations)| {
+                let origin = match span.location() {
+                    ImportLocation::Missing => "<current file>".to_string(),
+                    location => location.to_string(),
+                };
+                Slice {
+                    source: span.to_input(),
+                    line_start: 1,
+                    origin: Some(origin),
+                    fold: true,
+                    annotations,
+                }
+            })
+            .collect();
+        let footer = self
+            .notes
+            .iter()
+            .map(|note| (Severity::Note, note))
+            .chain(self.help.iter().map(|help| (Severity::Help, help)))
+            .map(|(severity, message)| Annotation {
+                label: Some(message.clone()),
+                id: None,
+                annotation_type: annotation_type(severity),
+            })
+            .collect();
+
+        let snippet = Snippet {
+            title: Some(Annotation {
+                label: Some(self.message.clone()),
+                id: None,
+                annotation_type: annotation_type(self.severity),
+            }),
+            slices,
+            footer,
+            opt: Default::default(),
+        };
+        write!(f, "{}", DisplayList::from(snippet))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sarif_should_have_a_result_per_diagnostic() {
+        let position = |line, column| Position { line, column };
+        let mut error = Diagnostic::error("type error");
+        error.spans.push(DiagnosticSpan::from_position(
+            Some("/dir/a.dhall".to_owned()),
+            position(1, 5),
+            position(1, 9),
+            "this is not a function",
+        ));
+        let mut related = DiagnosticSpan::from_position(
+            None,
+            position(2, 1),
+            position(2, 3),
+            "defined here",
+        );
+        related.primary = false;
+        error.spans.push(related);
+        error.help.push("remove the argument".to_owned());
+        let warning = Diagnostic::new(Severity::Warning, "unused let");
+
+        let sarif = to_sarif(&[error, warning]);
+        let results = &sarif["runs"][0]["results"];
+        assert_eq!(results.as_array().unwrap().len(), 2);
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(
+            results[0]["message"]["text"],
+            "type error\nremove the argument"
+        );
+        assert_eq!(
+            results[0]["locations"],
+            json!([{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": "file:///dir/a.dhall" },
+                    "region": {
+                        "startLine": 1,
+                        "startColumn": 5,
+                        "endLine": 1,
+                        "endColumn": 9,
+                    },
+                },
+                "message": { "text": "this is not a function" },
+            }])
+        );
+        let related = &results[0]["relatedLocations"][0]["physicalLocation"];
+        assert!(related.get("artifactLocation").is_none());
+        assert_eq!(related["region"]["startLine"], 2);
+        assert_eq!(results[1]["level"], "warning");
+        assert_eq!(results[1]["locations"], json!([]));
+    }
+
+    #[test]
+    fn sarif_locations_should_be_uris() {
+        let uri =
+            |file: &str| artifact_location(file).map(|a| a["uri"].clone());
+        assert_eq!(uri("/dir/a b.dhall").unwrap(), "file:///dir/a%20b.dhall");
+        assert_eq!(
+            uri("https://example.com/a.dhall").unwrap(),
+            "https://example.com/a.dhall"
+        );
+        assert_eq!(uri("env:HOME"), None);
+        assert_eq!(
+            artifact_location("./dir/b#1.dhall").unwrap(),
+            json!({ "uri": "dir/b%231.dhall", "uriBaseId": "%SRCROOT%" })
+        );
+    }
+}
@@ -1,16 +1,34 @@
+use itertools::Itertools;
 use std::io::Error as IOError;
 
 use crate::semantics::resolve::{ImportLocation, ImportStack};
-use crate::syntax::{Import, ParseError};
+use crate::syntax::{Hash, Import, ParseError, Span};
 
 mod builder;
+mod diagnostic;
+mod type_error;
 pub use builder::*;
+pub use diagnostic::*;
+pub use type_error::*;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub struct Error {
     kind: ErrorKind,
+    /// The imports that were being resolved when the error happened, innermost first.
+    imports: Vec<ImportFrame>,
+}
+
+/// One step of the chain of imports that led to an error.
+#[derive(Debug, Clone)]
+pub struct ImportFrame {
+    /// The location that contains the import.
+    pub parent: ImportLocation,
+    /// The location the import points to, if it could be computed.
+    pub location: Option<ImportLocation>,
+    /// The span of the import in `parent`.
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -28,10 +46,18 @@ pub enum ErrorKind {
 #[derive(Debug)]
 pub enum ImportError {
     Missing,
-    MissingEnvVar,
+    /// The environment variable with this name is not set.
+    MissingEnvVar(String),
     SanityCheck,
     UnexpectedImport(Import<()>),
     ImportCycle(ImportStack, ImportLocation),
+    /// The import policy does not allow reading from this location.
+    Forbidden(ImportLocation, String),
+    /// The imported expression does not have the expected hash.
+    HashMismatch {
+        expected: Hash,
+        actual: Hash,
+    },
     Url(url::ParseError),
 }
 
@@ -46,18 +72,6 @@ pub enum EncodeError {
     CBORError(serde_cbor::error::Error),
 }
 
-/// A structured type error
-#[derive(Debug)]
-pub struct TypeError {
-    message: TypeMessage,
-}
-
-/// The specific type error
-#[derive(Debug)]
-pub enum TypeMessage {
-    Custom(String),
-}
-
 #[derive(Debug)]
 pub enum CacheError {
     MissingConfiguration,
@@ -67,30 +81,190 @@ pub enum CacheError {
 
 impl Error {
     pub fn new(kind: ErrorKind) -> Self {
-        Error { kind }
+        Error {
+            kind,
+            imports: Vec::new(),
+        }
     }
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
     }
-}
+    /// The chain of imports that led to this error, innermost first. Empty if the error did not
+    /// happen inside an import.
+    pub fn import_chain(&self) -> &[ImportFrame] {
+        &self.imports
+    }
 
-impl TypeError {
-    pub fn new(message: TypeMessage) -> Self {
-        TypeError { message }
+    /// Record that this error happened while resolving the import at `span` in `parent`.
+    pub(crate) fn in_import(
+        mut self,
+        parent: &ImportLocation,
+        location: Option<&ImportLocation>,
+        span: &Span,
+    ) -> Self {
+        self.imports.push(ImportFrame {
+            parent: parent.clone(),
+            location: location.cloned(),
+            span: span.clone(),
+        });
+        self
     }
-}
 
-impl std::fmt::Display for TypeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        use TypeMessage::*;
-        let msg = match &self.message {
-            Custom(s) => format!("Type error: {}", s),
+    /// The locations of the import chain, outermost first, including the location that failed
+    /// if it is known.
+    fn chain_locations(&self) -> Vec<&ImportLocation> {
+        let mut chain: Vec<_> = self
+            .imports
+            .iter()
+            .rev()
+            .map(|frame| &frame.parent)
+            .collect();
+        if let Some(location) = self
+            .imports
+            .first()
+            .and_then(|frame| frame.location.as_ref())
+        {
+            chain.push(location);
+        }
+        if chain.first() == Some(&&ImportLocation::Missing) {
+            chain.remove(0);
+        }
+        chain
+    }
+
+    fn chain_note(&self) -> Option<String> {
+        let chain = self.chain_locations();
+        if chain.is_empty() {
+            return None;
+        }
+        Some(format!("while resolving {}", chain.iter().join(" → ")))
+    }
+
+    /// Describe an import error, pointing at the offending import if we know where it is.
+    fn import_diagnostic(&self, err: &ImportError) -> Diagnostic {
+        let (title, label) = match err {
+            ImportError::Missing => (
+                "cannot resolve `missing`".to_string(),
+                "`missing` never resolves".to_string(),
+            ),
+            ImportError::MissingEnvVar(name) => (
+                format!("environment variable `{}` is not set", name),
+                "this variable is not set".to_string(),
+            ),
+            ImportError::SanityCheck => (
+                "remote imports cannot import local files or environment \
+                 variables"
+                    .to_string(),
+                "imported from a remote file".to_string(),
+            ),
+            ImportError::UnexpectedImport(_) => (
+                "unexpected import".to_string(),
+                "imports are not allowed here".to_string(),
+            ),
+            ImportError::ImportCycle(..) => (
+                "import cycle detected".to_string(),
+                "this import refers back to a file that imports it".to_string(),
+            ),
+            ImportError::Forbidden(location, reason) => (
+                format!("import of `{}` is not allowed: {}", location, reason),
+                "forbidden by the import policy".to_string(),
+            ),
+            ImportError::HashMismatch { .. } => {
+                ("hash mismatch".to_string(), "hash mismatch".to_string())
+            }
+            ImportError::Url(err) => {
+                (format!("invalid url: {}", err), "invalid url".to_string())
+            }
         };
-        write!(f, "{}", msg)
+        let mut builder = ErrorBuilder::new(title);
+        if let Some(frame) = self.imports.first() {
+            builder.span_err(frame.span.clone(), label);
+        }
+        match err {
+            ImportError::ImportCycle(stack, location) => {
+                let start =
+                    stack.iter().position(|loc| loc == location).unwrap_or(0);
+                let mut cycle = stack[start..].iter().chain(Some(location));
+                builder.note(format!("the cycle is {}", cycle.join(" → ")));
+            }
+            ImportError::HashMismatch { expected, actual } => {
+                builder.note(format!("Expected {}", expected));
+                builder.note(format!("Found    {}", actual));
+            }
+            _ => {}
+        }
+        // The cycle note already lists the imports involved.
+        if !matches!(err, ImportError::ImportCycle(..)) {
+            if let Some(note) = self.chain_note() {
+                builder.note(note);
+            }
+        }
+        builder.diagnostic()
+    }
+
+    /// Describe a parse error, pointing into the imported file if the error happened in an
+    /// import.
+    fn parse_diagnostic(&self, err: &ParseError) -> Diagnostic {
+        use pest::error::LineColLocation;
+        let file = self
+            .imports
+            .first()
+            .and_then(|frame| frame.location.as_ref())
+            .map(|location| location.to_string());
+        let ((start_line, start_col), (end_line, end_col)) = match err.line_col
+        {
+            LineColLocation::Pos(pos) => (pos, pos),
+            LineColLocation::Span(start, end) => (start, end),
+        };
+        let mut diagnostic = Diagnostic::error("parse error");
+        diagnostic.spans.push(DiagnosticSpan::from_position(
+            file,
+            Position {
+                line: start_line,
+                column: start_col,
+            },
+            Position {
+                line: end_line,
+                column: end_col,
+            },
+            err.variant.message(),
+        ));
+        diagnostic
     }
-}
 
-impl std::error::Error for TypeError {}
+    /// The error as a structured message, e.g. to report it to an editor or to a CI system.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = match &self.kind {
+            ErrorKind::Resolve(err) => return self.import_diagnostic(err),
+            ErrorKind::IO(err) => {
+                match self.imports.first().and_then(|fr| fr.location.as_ref()) {
+                    Some(location) => {
+                        let mut builder = ErrorBuilder::new(format!(
+                            "cannot read `{}`: {}",
+                            location, err
+                        ));
+                        let frame = &self.imports[0];
+                        builder.span_err(frame.span.clone(), "imported here");
+                        if let Some(note) = self.chain_note() {
+                            builder.note(note);
+                        }
+                        return builder.diagnostic();
+                    }
+                    None => Diagnostic::error(err),
+                }
+            }
+            ErrorKind::Parse(err) => self.parse_diagnostic(err),
+            ErrorKind::Typecheck(err) => err.diagnostic().clone(),
+            ErrorKind::Decode(err) => Diagnostic::error(err),
+            ErrorKind::Encode(err) => Diagnostic::error(err),
+            ErrorKind::Cache(err) => Diagnostic::error(err),
+        };
+        if let Some(note) = self.chain_note() {
+            diagnostic.notes.push(note);
+        }
+        diagnostic
+    }
+}
 
 impl std::fmt::Display for EncodeError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -103,17 +277,63 @@ impl std::fmt::Display for EncodeError {
 
 impl std::error::Error for EncodeError {}
 
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DecodeError::CBORError(e) => write!(f, "Decode error: {}", e),
+            DecodeError::WrongFormatError(e) => {
+                write!(f, "Decode error: wrong format: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CacheError::MissingConfiguration => {
+                write!(f, "Cache error: could not find a cache directory")
+            }
+            CacheError::InitialisationError { cause } => write!(
+                f,
+                "Cache error: could not create the cache directory: {}",
+                cause
+            ),
+            CacheError::CacheHashInvalid => write!(
+                f,
+                "Cache error: the cached expression does not match its hash"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match &self.kind {
-            ErrorKind::IO(err) => write!(f, "{}", err),
-            ErrorKind::Parse(err) => write!(f, "{}", err),
-            ErrorKind::Decode(err) => write!(f, "{:?}", err),
-            ErrorKind::Encode(err) => write!(f, "{:?}", err),
-            ErrorKind::Resolve(err) => write!(f, "{:?}", err),
-            ErrorKind::Typecheck(err) => write!(f, "{}", err),
-            ErrorKind::Cache(err) => write!(f, "{:?}", err),
+        let msg = match &self.kind {
+            ErrorKind::Resolve(_) => {
+                return write!(f, "{}", self.to_diagnostic())
+            }
+            ErrorKind::IO(err) => match self.imports.first() {
+                Some(ImportFrame {
+                    location: Some(_), ..
+                }) => return write!(f, "{}", self.to_diagnostic()),
+                _ => err.to_string(),
+            },
+            ErrorKind::Parse(err) => err.to_string(),
+            ErrorKind::Decode(err) => err.to_string(),
+            ErrorKind::Encode(err) => err.to_string(),
+            ErrorKind::Typecheck(err) => err.to_string(),
+            ErrorKind::Cache(err) => err.to_string(),
+        };
+        write!(f, "{}", msg)?;
+        if let Some(note) = self.chain_note() {
+            write!(f, "\n  = note: {}", note)?;
         }
+        Ok(())
     }
 }
 
@@ -163,3 +383,105 @@ impl From<CacheError> for Error {
         ErrorKind::Cache(err).into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::parse::{parse_str, parse_str_at};
+    use crate::semantics::InMemoryFetcher;
+    use std::path::Path;
+
+    fn fetcher() -> InMemoryFetcher {
+        InMemoryFetcher::new()
+            .file("/dir/a.dhall", "{ x = ./b.dhall }")
+            .file("/dir/b.dhall", "1 + env:UNSET")
+            .file("/dir/c.dhall", "{ x = 1 }\n  .y")
+            .file("/dir/cycle.dhall", "./loop.dhall")
+            .file("/dir/loop.dhall", "./cycle.dhall")
+    }
+
+    fn resolve_error(s: &str) -> Error {
+        parse_str(s)
+            .unwrap()
+            .resolve_with_fetcher(&fetcher())
+            .unwrap_err()
+    }
+
+    #[test]
+    fn import_errors_should_show_the_import_chain() {
+        let err = resolve_error("/dir/a.dhall");
+        match err.kind() {
+            ErrorKind::Resolve(ImportError::MissingEnvVar(name)) => {
+                assert_eq!(name, "UNSET")
+            }
+            _ => panic!("{:?}", err),
+        }
+        let chain: Vec<_> = err
+            .import_chain()
+            .iter()
+            .map(|frame| frame.parent.to_string())
+            .collect();
+        assert_eq!(chain, vec!["/dir/b.dhall", "/dir/a.dhall", "missing"]);
+        assert!(matches!(err.import_chain()[0].span, Span::Parsed(_)));
+        let msg = err.to_string();
+        assert!(msg.contains("environment variable `UNSET` is not set"));
+        assert!(msg.contains("--> /dir/b.dhall:1:5"));
+        assert!(msg.contains(
+            "while resolving /dir/a.dhall → /dir/b.dhall → env:UNSET"
+        ));
+
+        let msg = resolve_error("/dir/none.dhall").to_string();
+        assert!(msg.contains("cannot read `/dir/none.dhall`"));
+
+        let msg = resolve_error("/dir/cycle.dhall").to_string();
+        assert!(msg.contains("import cycle detected"));
+        assert!(msg.contains(
+            "the cycle is /dir/cycle.dhall → /dir/loop.dhall → /dir/cycle.dhall"
+        ));
+        assert!(!msg.contains("while resolving"));
+    }
+
+    #[test]
+    fn root_locations_should_be_normalized() {
+        let root =
+            ImportLocation::local(Path::new("/dir/../dir/./cycle.dhall"));
+        assert_eq!(root.to_string(), "/dir/cycle.dhall");
+        let relative = ImportLocation::local(Path::new("dhall/../dir/a.dhall"));
+        assert_eq!(relative.to_string(), "./dir/a.dhall");
+
+        let msg = parse_str_at("./loop.dhall", root)
+            .unwrap()
+            .resolve_with_fetcher(&fetcher())
+            .unwrap_err()
+            .to_string();
+        assert!(msg.contains("--> /dir/cycle.dhall:1:1"));
+        assert!(msg.contains(
+            "the cycle is /dir/loop.dhall → /dir/cycle.dhall → /dir/loop.dhall"
+        ));
+    }
+
+    #[test]
+    fn errors_should_point_into_the_imported_file() {
+        let err = resolve_error("{ a = /dir/c.dhall }");
+        assert!(matches!(err.kind(), ErrorKind::Typecheck(_)));
+        let msg = err.to_string();
+        assert!(msg.contains("--> /dir/c.dhall:1:1"));
+        assert!(msg.contains("note: while resolving /dir/c.dhall"));
+    }
+
+    #[test]
+    fn errors_should_convert_to_diagnostics() {
+        let diagnostic = resolve_error("{ a = /dir/c.dhall }").to_diagnostic();
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.message, "MissingRecordField");
+        assert_eq!(diagnostic.file(), Some("/dir/c.dhall"));
+        let span = &diagnostic.spans[0];
+        assert_eq!(span.start, Position { line: 1, column: 1 });
+        assert_eq!(span.end, Position { line: 2, column: 5 });
+        assert_eq!(diagnostic.notes, vec!["while resolving /dir/c.dhall"]);
+
+        let diagnostic = resolve_error("/dir/none.dhall").to_diagnostic();
+        assert_eq!(diagnostic.file(), None);
+        assert_eq!(diagnostic.spans[0].label, "imported here");
+    }
+}
@@ -0,0 +1,709 @@
+use crate::error::{Diagnostic, ErrorBuilder};
+use crate::operations::BinOp;
+use crate::semantics::{TyEnv, Type};
+use crate::syntax::{Expr, Label, Span, V};
+
+/// A structured type error
+#[derive(Debug)]
+pub struct TypeError {
+    message: Box<TypeMessage>,
+    span: Span,
+    /// The message with source snippets. Printing types requires the environment they live in, so
+    /// this is computed when the error is created.
+    diagnostic: Box<Diagnostic>,
+}
+
+/// The specific type error.
+///
+/// Where the Dhall standard names the error, the variant uses that name. The types are those of
+/// the offending subexpressions, printed when the error is built so that the variables bound in
+/// the surrounding expression keep their names.
+/// The parameter is only used by the typechecker, whose types need that environment to be printed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TypeMessage<T = Expr> {
+    /// A variable is not in scope.
+    UnboundVariable(V),
+    /// An expression has type `actual` but its annotation requires `expected`.
+    TypeMismatch {
+        expected: T,
+        actual: T,
+    },
+    /// A function was applied to an argument of the wrong type.
+    WrongArgumentType {
+        /// The span of the function.
+        function: Span,
+        expected: T,
+        actual: T,
+    },
+    /// Something that is not a function was applied to an argument.
+    NotAFunction {
+        actual: T,
+    },
+    /// A term was found where a type was expected.
+    NotAType {
+        expr: Expr,
+        actual: T,
+    },
+    /// `Sort` was used in a position that requires it to have a type.
+    Untyped,
+    InvalidOutputType {
+        actual: T,
+    },
+    InvalidTextInterpolation {
+        actual: T,
+    },
+    InvalidOptionalType {
+        actual: T,
+    },
+    InvalidListType {
+        actual: T,
+    },
+    InvalidListElement {
+        expected: T,
+        actual: T,
+    },
+    InvalidFieldType {
+        actual: T,
+    },
+    InvalidVariantType {
+        actual: T,
+    },
+    AssertMismatch {
+        assertion: T,
+    },
+    AssertMustTakeEquivalence {
+        actual: T,
+    },
+    MustCombineRecord {
+        actual: T,
+    },
+    RecordTypeMergeRequiresRecordType {
+        actual: T,
+    },
+    BinOpTypeMismatch {
+        op: BinOp,
+        actual: T,
+    },
+    EquivalenceTypeMismatch {
+        left: T,
+        right: T,
+    },
+    EquivalenceArgumentsMustBeTerms {
+        actual: T,
+    },
+    InvalidPredicate {
+        actual: T,
+    },
+    IfBranchMustBeTerm {
+        actual: T,
+    },
+    IfBranchMismatch {
+        left: T,
+        right: T,
+    },
+    Merge1ArgMustBeRecord {
+        actual: T,
+    },
+    Merge2ArgMustBeUnionOrOptional {
+        actual: T,
+    },
+    /// The handler for `variant` expects an input of type `actual` instead of `expected`.
+    MergeHandlerWrongInputType {
+        /// The span of the record of handlers.
+        handlers: Span,
+        /// The span of the union.
+        union: Span,
+        variant: Label,
+        expected: T,
+        actual: T,
+    },
+    /// The handler for `variant` must be a function but has type `actual`.
+    MergeHandlerNotAFunction {
+        /// The span of the record of handlers.
+        handlers: Span,
+        /// The span of the union.
+        union: Span,
+        variant: Label,
+        variant_type: T,
+        actual: T,
+    },
+    MergeReturnTypeIsDependent {
+        variant: Label,
+    },
+    /// There is a handler for `variant` but the union has no such alternative.
+    MergeHandlerMissingVariant {
+        variant: Label,
+    },
+    /// The union has an alternative `variant` that has no handler.
+    MergeHandlerMissing {
+        variant: Label,
+    },
+    MergeHandlerTypeMismatch {
+        expected: T,
+        actual: T,
+    },
+    MergeAnnotMismatch {
+        expected: T,
+        actual: T,
+    },
+    MergeEmptyNeedsAnnotation,
+    InvalidToMapRecordKind {
+        actual: T,
+    },
+    ToMapNotARecord {
+        actual: T,
+    },
+    ToMapEmptyNeedsAnnotation,
+    InvalidToMapType {
+        actual: T,
+    },
+    HeterogenousRecordToMap {
+        actual: T,
+    },
+    ToMapAnnotMismatch {
+        expected: T,
+        actual: T,
+    },
+    MissingRecordField {
+        field: Label,
+        record: T,
+    },
+    MissingUnionField {
+        field: Label,
+        union: T,
+    },
+    NotARecord {
+        actual: T,
+    },
+    ProjectionMustBeRecord {
+        actual: T,
+    },
+    ProjectionMissingEntry {
+        field: Label,
+        record: T,
+    },
+    ProjectionByExprTakesRecordType {
+        actual: T,
+    },
+    ProjectionWrongType {
+        field: Label,
+        expected: T,
+        actual: T,
+    },
+    /// The `using` clause of an import does not have type
+    /// `List { mapKey : Text, mapValue : Text }`.
+    InvalidImportHeaders {
+        expected: T,
+        actual: T,
+    },
+}
+
+impl TypeMessage<Type> {
+    /// Print the types, which live in `env`.
+    fn render(self, env: &TyEnv) -> TypeMessage {
+        use TypeMessage::*;
+        let ty = |t: Type| t.to_expr_tyenv(env);
+        match self {
+            UnboundVariable(x) => UnboundVariable(x),
+            TypeMismatch { expected, actual } => TypeMismatch {
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+            WrongArgumentType {
+                function,
+                expected,
+                actual,
+            } => WrongArgumentType {
+                function,
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+            NotAFunction { actual } => NotAFunction { actual: ty(actual) },
+            NotAType { expr, actual } => NotAType {
+                expr,
+                actual: ty(actual),
+            },
+            Untyped => Untyped,
+            InvalidOutputType { actual } => {
+                InvalidOutputType { actual: ty(actual) }
+            }
+            InvalidTextInterpolation { actual } => {
+                InvalidTextInterpolation { actual: ty(actual) }
+            }
+            InvalidOptionalType { actual } => {
+                InvalidOptionalType { actual: ty(actual) }
+            }
+            InvalidListType { actual } => {
+                InvalidListType { actual: ty(actual) }
+            }
+            InvalidListElement { expected, actual } => InvalidListElement {
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+            InvalidFieldType { actual } => {
+                InvalidFieldType { actual: ty(actual) }
+            }
+            InvalidVariantType { actual } => {
+                InvalidVariantType { actual: ty(actual) }
+            }
+            AssertMismatch { assertion } => AssertMismatch {
+                assertion: ty(assertion),
+            },
+            AssertMustTakeEquivalence { actual } => {
+                AssertMustTakeEquivalence { actual: ty(actual) }
+            }
+            MustCombineRecord { actual } => {
+                MustCombineRecord { actual: ty(actual) }
+            }
+            RecordTypeMergeRequiresRecordType { actual } => {
+                RecordTypeMergeRequiresRecordType { actual: ty(actual) }
+            }
+            BinOpTypeMismatch { op, actual } => BinOpTypeMismatch {
+                op,
+                actual: ty(actual),
+            },
+            EquivalenceTypeMismatch { left, right } => {
+                EquivalenceTypeMismatch {
+                    left: ty(left),
+                    right: ty(right),
+                }
+            }
+            EquivalenceArgumentsMustBeTerms { actual } => {
+                EquivalenceArgumentsMustBeTerms { actual: ty(actual) }
+            }
+            InvalidPredicate { actual } => {
+                InvalidPredicate { actual: ty(actual) }
+            }
+            IfBranchMustBeTerm { actual } => {
+                IfBranchMustBeTerm { actual: ty(actual) }
+            }
+            IfBranchMismatch { left, right } => IfBranchMismatch {
+                left: ty(left),
+                right: ty(right),
+            },
+            Merge1ArgMustBeRecord { actual } => {
+                Merge1ArgMustBeRecord { actual: ty(actual) }
+            }
+            Merge2ArgMustBeUnionOrOptional { actual } => {
+                Merge2ArgMustBeUnionOrOptional { actual: ty(actual) }
+            }
+            MergeHandlerWrongInputType {
+                handlers,
+                union,
+                variant,
+                expected,
+                actual,
+            } => MergeHandlerWrongInputType {
+                handlers,
+                union,
+                variant,
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+            MergeHandlerNotAFunction {
+                handlers,
+                union,
+                variant,
+                variant_type,
+                actual,
+            } => MergeHandlerNotAFunction {
+                handlers,
+                union,
+                variant,
+                variant_type: ty(variant_type),
+                actual: ty(actual),
+            },
+            MergeReturnTypeIsDependent { variant } => {
+                MergeReturnTypeIsDependent { variant }
+            }
+            MergeHandlerMissingVariant { variant } => {
+                MergeHandlerMissingVariant { variant }
+            }
+            MergeHandlerMissing { variant } => MergeHandlerMissing { variant },
+            MergeHandlerTypeMismatch { expected, actual } => {
+                MergeHandlerTypeMismatch {
+                    expected: ty(expected),
+                    actual: ty(actual),
+                }
+            }
+            MergeAnnotMismatch { expected, actual } => MergeAnnotMismatch {
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+            MergeEmptyNeedsAnnotation => MergeEmptyNeedsAnnotation,
+            InvalidToMapRecordKind { actual } => {
+                InvalidToMapRecordKind { actual: ty(actual) }
+            }
+            ToMapNotARecord { actual } => {
+                ToMapNotARecord { actual: ty(actual) }
+            }
+            ToMapEmptyNeedsAnnotation => ToMapEmptyNeedsAnnotation,
+            InvalidToMapType { actual } => {
+                InvalidToMapType { actual: ty(actual) }
+            }
+            HeterogenousRecordToMap { actual } => {
+                HeterogenousRecordToMap { actual: ty(actual) }
+            }
+            ToMapAnnotMismatch { expected, actual } => ToMapAnnotMismatch {
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+            MissingRecordField { field, record } => MissingRecordField {
+                field,
+                record: ty(record),
+            },
+            MissingUnionField { field, union } => MissingUnionField {
+                field,
+                union: ty(union),
+            },
+            NotARecord { actual } => NotARecord { actual: ty(actual) },
+            ProjectionMustBeRecord { actual } => {
+                ProjectionMustBeRecord { actual: ty(actual) }
+            }
+            ProjectionMissingEntry { field, record } => {
+                ProjectionMissingEntry {
+                    field,
+                    record: ty(record),
+                }
+            }
+            ProjectionByExprTakesRecordType { actual } => {
+                ProjectionByExprTakesRecordType { actual: ty(actual) }
+            }
+            ProjectionWrongType {
+                field,
+                expected,
+                actual,
+            } => ProjectionWrongType {
+                field,
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+            InvalidImportHeaders { expected, actual } => InvalidImportHeaders {
+                expected: ty(expected),
+                actual: ty(actual),
+            },
+        }
+    }
+}
+
+impl TypeError {
+    /// Build an error for the expression at `span`, whose types live in `env`.
+    pub(crate) fn new(
+        env: &TyEnv,
+        span: Span,
+        message: TypeMessage<Type>,
+    ) -> Self {
+        let message = message.render(env);
+        let diagnostic = Box::new(message.diagnostic(&span));
+        TypeError {
+            message: Box::new(message),
+            span,
+            diagnostic,
+        }
+    }
+
+    pub fn message(&self) -> &TypeMessage {
+        &self.message
+    }
+    /// The span of the offending expression.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+    /// The structured error message.
+    pub fn diagnostic(&self) -> &Diagnostic {
+        &self.diagnostic
+    }
+}
+
+impl TypeMessage {
+    fn diagnostic(&self, span: &Span) -> Diagnostic {
+        use TypeMessage::*;
+        fn ty(t: &Expr) -> &Expr {
+            t
+        }
+        let span = span.clone();
+        let msg = match self {
+            UnboundVariable(var) => {
+                return ErrorBuilder::new(format!("unbound variable `{}`", var))
+                    .span_err(span, "not found in this scope")
+                    .diagnostic()
+            }
+            TypeMismatch { expected, actual } => {
+                format!("annot mismatch: {} != {}", ty(actual), ty(expected))
+            }
+            WrongArgumentType {
+                function,
+                expected,
+                actual,
+            } => {
+                return ErrorBuilder::new("wrong type of function argument")
+                    .span_err(
+                        function.clone(),
+                        format!(
+                            "this expects an argument of type: {}",
+                            ty(expected),
+                        ),
+                    )
+                    .span_err(
+                        span,
+                        format!("but this has type: {}", ty(actual)),
+                    )
+                    .note(format!(
+                        "expected type `{}`\n   found type `{}`",
+                        ty(expected),
+                        ty(actual),
+                    ))
+                    .diagnostic()
+            }
+            NotAFunction { actual } => {
+                return ErrorBuilder::new(format!(
+                    "expected function, found `{}`",
+                    ty(actual)
+                ))
+                .span_err(span, "function application requires a function")
+                .diagnostic()
+            }
+            NotAType { expr, actual } => {
+                return ErrorBuilder::new(format!(
+                    "Expected a type, found: `{}`",
+                    expr
+                ))
+                .span_err(span, format!("this has type: `{}`", ty(actual)))
+                .help(
+                    "An expression in type position must have type `Type`, \
+                     `Kind` or `Sort`",
+                )
+                .diagnostic()
+            }
+            MergeHandlerWrongInputType {
+                handlers,
+                union,
+                variant,
+                expected,
+                actual,
+            } => {
+                return ErrorBuilder::new("Wrong handler input type")
+                    .span_err(span, "in this merge expression")
+                    .span_err(
+                        handlers.clone(),
+                        format!(
+                            "the handler for `{}` expects a value of type: \
+                             `{}`",
+                            variant,
+                            ty(actual)
+                        ),
+                    )
+                    .span_err(
+                        union.clone(),
+                        format!(
+                            "but the corresponding variant has type: `{}`",
+                            ty(expected)
+                        ),
+                    )
+                    .diagnostic()
+            }
+            MergeHandlerNotAFunction {
+                handlers,
+                union,
+                variant,
+                variant_type,
+                actual,
+            } => {
+                return ErrorBuilder::new("merge handler is not a function")
+                    .span_err(span, "in this merge expression")
+                    .span_err(
+                        handlers.clone(),
+                        format!(
+                            "the handler for `{}` has type: `{}`",
+                            variant,
+                            ty(actual)
+                        ),
+                    )
+                    .span_help(
+                        union.clone(),
+                        format!(
+                            "the corresponding variant has type: `{}`",
+                            ty(variant_type)
+                        ),
+                    )
+                    .help(format!(
+                        "a handler for this variant must be a function that \
+                         takes an input of type: `{}`",
+                        ty(variant_type)
+                    ))
+                    .diagnostic()
+            }
+            InvalidImportHeaders { expected, actual } => {
+                return ErrorBuilder::new("invalid import headers")
+                    .span_err(
+                        span,
+                        format!(
+                            "expected `{}`, found `{}`",
+                            ty(expected),
+                            ty(actual),
+                        ),
+                    )
+                    .diagnostic()
+            }
+            Untyped => "Sort does not have a type".to_owned(),
+            InvalidOutputType { .. } => "Invalid output type".to_owned(),
+            InvalidToMapRecordKind { .. } => {
+                "`toMap` only accepts records of type `Type`".to_owned()
+            }
+            ToMapNotARecord { .. } => {
+                "The argument to `toMap` must be a record".to_owned()
+            }
+            ToMapEmptyNeedsAnnotation => "`toMap` applied to an empty record \
+                                          requires a type annotation"
+                .to_owned(),
+            InvalidToMapType { .. } => "The type of `toMap x` must be of the \
+                                        form `List { mapKey : Text, mapValue \
+                                        : T }`"
+                .to_owned(),
+            HeterogenousRecordToMap { .. } => {
+                "Every field of the record must have the same type".to_owned()
+            }
+            ToMapAnnotMismatch { .. } => "Annotation mismatch".to_owned(),
+            // The remaining errors are described by their name in the standard.
+            InvalidTextInterpolation { .. } => {
+                "InvalidTextInterpolation".to_owned()
+            }
+            InvalidOptionalType { .. } => "InvalidOptionalType".to_owned(),
+            InvalidListType { .. } => "InvalidListType".to_owned(),
+            InvalidListElement { .. } => "InvalidListElement".to_owned(),
+            InvalidFieldType { .. } => "InvalidFieldType".to_owned(),
+            InvalidVariantType { .. } => "InvalidVariantType".to_owned(),
+            AssertMismatch { .. } => "AssertMismatch".to_owned(),
+            AssertMustTakeEquivalence { .. } => {
+                "AssertMustTakeEquivalence".to_owned()
+            }
+            MustCombineRecord { .. } => "MustCombineRecord".to_owned(),
+            RecordTypeMergeRequiresRecordType { .. } => {
+                "RecordTypeMergeRequiresRecordType".to_owned()
+            }
+            BinOpTypeMismatch { .. } => "BinOpTypeMismatch".to_owned(),
+            EquivalenceTypeMismatch { .. } => {
+                "EquivalenceTypeMismatch".to_owned()
+            }
+            EquivalenceArgumentsMustBeTerms { .. } => {
+                "EquivalenceArgumentsMustBeTerms".to_owned()
+            }
+            InvalidPredicate { .. } => "InvalidPredicate".to_owned(),
+            IfBranchMustBeTerm { .. } => "IfBranchMustBeTerm".to_owned(),
+            IfBranchMismatch { .. } => "IfBranchMismatch".to_owned(),
+            Merge1ArgMustBeRecord { .. } => "Merge1ArgMustBeRecord".to_owned(),
+            Merge2ArgMustBeUnionOrOptional { .. } => {
+                "Merge2ArgMustBeUnionOrOptional".to_owned()
+            }
+            MergeReturnTypeIsDependent { .. } => {
+                "MergeReturnTypeIsDependent".to_owned()
+            }
+            MergeHandlerMissingVariant { .. } => {
+                "MergeHandlerMissingVariant".to_owned()
+            }
+            MergeHandlerMissing { .. } => {
+                "MergeVariantMissingHandler".to_owned()
+            }
+            MergeHandlerTypeMismatch { .. } => {
+                "MergeHandlerTypeMismatch".to_owned()
+            }
+            MergeAnnotMismatch { .. } => "MergeAnnotMismatch".to_owned(),
+            MergeEmptyNeedsAnnotation => "MergeEmptyNeedsAnnotation".to_owned(),
+            MissingRecordField { .. } => "MissingRecordField".to_owned(),
+            MissingUnionField { .. } => "MissingUnionField".to_owned(),
+            NotARecord { .. } => "NotARecord".to_owned(),
+            ProjectionMustBeRecord { .. } => {
+                "ProjectionMustBeRecord".to_owned()
+            }
+            ProjectionMissingEntry { .. } => {
+                "ProjectionMissingEntry".to_owned()
+            }
+            ProjectionByExprTakesRecordType { .. } => {
+                "ProjectionByExprTakesRecordType".to_owned()
+            }
+            ProjectionWrongType { .. } => "ProjectionWrongType".to_owned(),
+        };
+        ErrorBuilder::new(&msg).span_err(span, &msg).diagnostic()
+    }
+}
+
+impl std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Type error: {}", self.diagnostic)
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::semantics::parse::parse_str;
+
+    fn type_error(s: &str) -> TypeError {
+        let err = parse_str(s)
+            .and_then(|p| Ok(p.resolve()?.typecheck()?))
+            .unwrap_err();
+        match err.kind {
+            ErrorKind::Typecheck(err) => err,
+            _ => panic!("not a type error: {}", err),
+        }
+    }
+
+    #[test]
+    fn type_errors_should_be_structured() {
+        match type_error("λ(x : Bool) → y").message() {
+            TypeMessage::UnboundVariable(var) => {
+                assert_eq!(var.to_string(), "y")
+            }
+            msg => panic!("{:?}", msg),
+        }
+        match type_error("(1 : Bool)").message() {
+            TypeMessage::TypeMismatch { expected, actual } => {
+                assert_eq!(expected.to_string(), "Bool");
+                assert_eq!(actual.to_string(), "Natural");
+            }
+            msg => panic!("{:?}", msg),
+        }
+        match type_error("True 1").message() {
+            TypeMessage::NotAFunction { actual } => {
+                assert_eq!(actual.to_string(), "Bool")
+            }
+            msg => panic!("{:?}", msg),
+        }
+        // The type refers to a variable bound by the enclosing lambda.
+        match type_error("λ(a : Type) → λ(x : a) → x 1").message() {
+            TypeMessage::NotAFunction { actual } => {
+                assert_eq!(actual.to_string(), "a")
+            }
+            msg => panic!("{:?}", msg),
+        }
+        match type_error("{ x = 1 }.y").message() {
+            TypeMessage::MissingRecordField { field, record } => {
+                assert_eq!(field.to_string(), "y");
+                assert_eq!(record.to_string(), "{ x : Natural }");
+            }
+            msg => panic!("{:?}", msg),
+        }
+        match type_error("merge {=} (< A >.A)").message() {
+            TypeMessage::MergeHandlerMissing { variant } => {
+                assert_eq!(variant.to_string(), "A")
+            }
+            msg => panic!("{:?}", msg),
+        }
+
+        let err = type_error("let f = λ(x : Natural) → x in f True");
+        match err.message() {
+            TypeMessage::WrongArgumentType { .. } => {}
+            msg => panic!("{:?}", msg),
+        }
+        match err.span() {
+            Span::Parsed(span) => {
+                assert_eq!(span.as_char_range(), (32, 36))
+            }
+            span => panic!("{:?}", span),
+        }
+    }
+}
@@ -19,11 +19,16 @@ use std::path::Path;
 use url::Url;
 
 use crate::error::{Error, TypeError};
+use crate::semantics::inspect::{self, Completion, Definition};
+use crate::semantics::lint::{self, Lint};
 use crate::semantics::parse;
 use crate::semantics::resolve;
-use crate::semantics::resolve::ImportLocation;
-use crate::semantics::{typecheck, typecheck_with, Hir, Nir, Tir, Type};
-use crate::syntax::Expr;
+use crate::semantics::resolve::{
+    Cache, DefaultFetcher, Fetcher, FreezeOptions, ImportGraph, ImportLocation,
+    ImportPolicy, Session,
+};
+use crate::semantics::{typecheck, typecheck_with, Hir, Nir, Tir, TyEnv, Type};
+use crate::syntax::{Expr, ExprKind, Span};
 
 #[derive(Debug, Clone)]
 pub struct Parsed(Expr, ImportLocation);
@@ -32,7 +37,7 @@ pub struct Parsed(Expr, ImportLocation);
 ///
 /// Invariant: there must be no `Import` nodes or `ImportAlt` operations left.
 #[derive(Debug, Clone)]
-pub struct Resolved(Hir);
+pub struct Resolved(Hir, ImportGraph);
 
 /// A typed expression
 #[derive(Debug, Clone)]
@@ -59,11 +64,19 @@ impl Parsed {
         parse::parse_file(f)
     }
     pub fn parse_remote(url: Url) -> Result<Parsed, Error> {
-        parse::parse_remote(url)
+        parse::parse_remote(url, Vec::new())
     }
     pub fn parse_str(s: &str) -> Result<Parsed, Error> {
         parse::parse_str(s)
     }
+    /// Parse text that was read from `location`, e.g. the contents of an open editor buffer.
+    /// Relative imports are resolved against `location`.
+    pub fn parse_str_at(
+        s: &str,
+        location: ImportLocation,
+    ) -> Result<Parsed, Error> {
+        parse::parse_str_at(s, location)
+    }
     pub fn parse_binary_file(f: &Path) -> Result<Parsed, Error> {
         parse::parse_binary_file(f)
     }
@@ -72,17 +85,91 @@ impl Parsed {
         parse::parse_binary(data)
     }
 
+    /// Binds `name` to `value` in the expression, like an enclosing `let name : T = value`.
+    /// `value` must be closed; its type is inferred. Each call adds a `let` around the ones
+    /// added before.
+    ///
+    /// Variables are looked up when resolving imports, and an unbound variable is an error then,
+    /// so bindings must be added before resolution: a `Resolved` expression has no free
+    /// variables left for a binding to provide.
+    pub fn with_binding(
+        self,
+        name: &str,
+        value: &Hir,
+    ) -> Result<Parsed, TypeError> {
+        let ty = typecheck(value)?.ty().to_expr_tyenv(&TyEnv::new());
+        let Parsed(expr, root) = self;
+        let kind =
+            ExprKind::Let(name.into(), Some(ty), value.to_expr_noopts(), expr);
+        Ok(Parsed(Expr::new(kind, Span::Artificial), root))
+    }
+
     pub fn resolve(self) -> Result<Resolved, Error> {
         resolve::resolve(self)
     }
+    /// Resolve imports, reading their contents through the given `Fetcher`.
+    pub fn resolve_with_fetcher(
+        self,
+        fetcher: &dyn Fetcher,
+    ) -> Result<Resolved, Error> {
+        resolve::resolve_with_fetcher(self, fetcher)
+    }
+    /// Resolve imports, reading their contents through the given `Fetcher` and rejecting the
+    /// ones that `policy` does not allow.
+    pub fn resolve_with(
+        self,
+        fetcher: &dyn Fetcher,
+        policy: &ImportPolicy,
+    ) -> Result<Resolved, Error> {
+        resolve::resolve_with(self, fetcher, policy)
+    }
+    /// Like `resolve_with`, but uses `cache` for frozen imports instead of the on-disk cache in
+    /// the default location. Use `Cache::disabled()` to not touch the disk cache at all.
+    pub fn resolve_with_cache(
+        self,
+        fetcher: &dyn Fetcher,
+        policy: &ImportPolicy,
+        cache: &Cache,
+    ) -> Result<Resolved, Error> {
+        resolve::resolve_with_cache(self, fetcher, policy, cache)
+    }
+    /// Resolve imports, reusing those already resolved in `session` and storing the new ones in
+    /// it.
+    pub fn resolve_in(self, session: &Session) -> Result<Resolved, Error> {
+        self.resolve_in_with(session, &DefaultFetcher, &ImportPolicy::new())
+    }
+    pub fn resolve_in_with(
+        self,
+        session: &Session,
+        fetcher: &dyn Fetcher,
+        policy: &ImportPolicy,
+    ) -> Result<Resolved, Error> {
+        resolve::resolve_in(self, session, fetcher, policy)
+    }
     pub fn skip_resolve(self) -> Result<Resolved, Error> {
         resolve::skip_resolve(self)
     }
+    /// Add `sha256:` integrity checks to the imports of the expression.
+    pub fn freeze(self, options: FreezeOptions) -> Result<Expr, Error> {
+        resolve::freeze(self, &DefaultFetcher, options)
+    }
 
     /// Converts a value back to the corresponding AST expression.
     pub fn to_expr(&self) -> Expr {
         self.0.clone()
     }
+    /// What the variable or import at byte offset `offset` refers to.
+    pub fn definition_at(&self, offset: usize) -> Option<Definition> {
+        inspect::definition_at(&self.0, &self.1, offset)
+    }
+    /// Unused bindings, redundant annotations and other problems in the expression.
+    pub fn lint(&self) -> Vec<Lint> {
+        lint::lint(&self.0)
+    }
+    /// The expression with the automatic fixes for `lint` applied.
+    pub fn fix_lints(&self) -> Expr {
+        lint::fix(&self.0)
+    }
 }
 
 impl Resolved {
@@ -96,6 +183,18 @@ impl Resolved {
     pub fn to_expr(&self) -> Expr {
         self.0.to_expr_noopts()
     }
+    /// The files, urls and environment variables that were imported during resolution.
+    pub fn import_graph(&self) -> &ImportGraph {
+        &self.1
+    }
+    /// The type of the smallest subexpression around byte offset `offset`, with its span.
+    pub fn type_at(&self, offset: usize) -> Option<(Span, Expr)> {
+        inspect::type_at(&self.0, offset)
+    }
+    /// The record fields or union alternatives that could follow a `.` at byte offset `offset`.
+    pub fn completions_at(&self, offset: usize) -> Vec<Completion> {
+        inspect::completions_at(&self.0, offset)
+    }
 }
 
 impl Typed {
@@ -33,15 +33,15 @@ fn normalize_binop(o: BinOp, x: &Nir, y: &Nir) -> Ret {
         (BoolNE, Num(Bool(x)), Num(Bool(y))) => ret_kind(Num(Bool(x != y))),
         (BoolNE, _, _) if x == y => ret_kind(Num(Bool(false))),
 
-        (NaturalPlus, Num(Natural(0)), _) => ret_ref(y),
-        (NaturalPlus, _, Num(Natural(0))) => ret_ref(x),
+        (NaturalPlus, Num(Natural(n)), _) if n.is_zero() => ret_ref(y),
+        (NaturalPlus, _, Num(Natural(n))) if n.is_zero() => ret_ref(x),
         (NaturalPlus, Num(Natural(x)), Num(Natural(y))) => {
             ret_kind(Num(Natural(x + y)))
         }
-        (NaturalTimes, Num(Natural(0)), _) => ret_kind(Num(Natural(0))),
-        (NaturalTimes, _, Num(Natural(0))) => ret_kind(Num(Natural(0))),
-        (NaturalTimes, Num(Natural(1)), _) => ret_ref(y),
-        (NaturalTimes, _, Num(Natural(1))) => ret_ref(x),
+        (NaturalTimes, Num(Natural(n)), _) if n.is_zero() => ret_ref(x),
+        (NaturalTimes, _, Num(Natural(n))) if n.is_zero() => ret_ref(y),
+        (NaturalTimes, Num(Natural(n)), _) if n.is_one() => ret_ref(y),
+        (NaturalTimes, _, Num(Natural(n))) if n.is_one() => ret_ref(x),
         (NaturalTimes, Num(Natural(x)), Num(Natural(y))) => {
             ret_kind(Num(Natural(x * y)))
         }
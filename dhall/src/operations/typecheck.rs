@@ -3,11 +3,11 @@ use std::cmp::max;
 use std::collections::HashMap;
 
 use crate::builtins::Builtin;
-use crate::error::{ErrorBuilder, TypeError};
+use crate::error::{TypeError, TypeMessage};
 use crate::operations::{BinOp, OpKind};
 use crate::semantics::{
-    merge_maps, mk_span_err, mkerr, Binder, Closure, Hir, HirKind, Nir,
-    NirKind, Tir, TyEnv, Type,
+    merge_maps, mkerr, Binder, Closure, Hir, HirKind, Nir, NirKind, Tir, TyEnv,
+    Type,
 };
 use crate::syntax::{Const, ExprKind, Span};
 
@@ -17,23 +17,22 @@ fn check_rectymerge(
     x: Nir,
     y: Nir,
 ) -> Result<(), TypeError> {
+    let not_record = |x: &Nir| {
+        mkerr(
+            env,
+            span.clone(),
+            TypeMessage::RecordTypeMergeRequiresRecordType {
+                actual: Type::new_infer_universe(env, x.clone())?,
+            },
+        )
+    };
     let kts_x = match x.kind() {
         NirKind::RecordType(kts) => kts,
-        _ => {
-            return mk_span_err(
-                span.clone(),
-                "RecordTypeMergeRequiresRecordType",
-            )
-        }
+        _ => return not_record(&x),
     };
     let kts_y = match y.kind() {
         NirKind::RecordType(kts) => kts,
-        _ => {
-            return mk_span_err(
-                span.clone(),
-                "RecordTypeMergeRequiresRecordType",
-            )
-        }
+        _ => return not_record(&y),
     };
     for (k, tx) in kts_x {
         if let Some(ty) = kts_y.get(k) {
@@ -51,7 +50,7 @@ fn typecheck_binop(
     l: &Tir<'_>,
     r: &Tir<'_>,
 ) -> Result<Type, TypeError> {
-    let span_err = |msg: &str| mk_span_err(span.clone(), msg);
+    let span_err = |msg| mkerr(env, span.clone(), msg);
     use BinOp::*;
     use NirKind::{ListType, RecordType};
 
@@ -63,12 +62,20 @@ fn typecheck_binop(
             // Extract the LHS record type
             let kts_x = match x_type.kind() {
                 RecordType(kts) => kts,
-                _ => return span_err("MustCombineRecord"),
+                _ => {
+                    return span_err(TypeMessage::MustCombineRecord {
+                        actual: x_type.clone(),
+                    })
+                }
             };
             // Extract the RHS record type
             let kts_y = match y_type.kind() {
                 RecordType(kts) => kts,
-                _ => return span_err("MustCombineRecord"),
+                _ => {
+                    return span_err(TypeMessage::MustCombineRecord {
+                        actual: y_type.clone(),
+                    })
+                }
             };
 
             // Union the two records, prefering
@@ -104,21 +111,36 @@ fn typecheck_binop(
         ListAppend => {
             match l.ty().kind() {
                 ListType(..) => {}
-                _ => return span_err("BinOpTypeMismatch"),
+                _ => {
+                    return span_err(TypeMessage::BinOpTypeMismatch {
+                        op,
+                        actual: l.ty().clone(),
+                    })
+                }
             }
 
             if l.ty() != r.ty() {
-                return span_err("BinOpTypeMismatch");
+                return span_err(TypeMessage::BinOpTypeMismatch {
+                    op,
+                    actual: r.ty().clone(),
+                });
             }
 
             l.ty().clone()
         }
         Equivalence => {
             if l.ty() != r.ty() {
-                return span_err("EquivalenceTypeMismatch");
+                return span_err(TypeMessage::EquivalenceTypeMismatch {
+                    left: l.ty().clone(),
+                    right: r.ty().clone(),
+                });
             }
             if l.ty().ty().as_const() != Some(Const::Type) {
-                return span_err("EquivalenceArgumentsMustBeTerms");
+                return span_err(
+                    TypeMessage::EquivalenceArgumentsMustBeTerms {
+                        actual: l.ty().clone(),
+                    },
+                );
             }
 
             Type::from_const(Const::Type)
@@ -137,11 +159,17 @@ fn typecheck_binop(
             });
 
             if *l.ty() != t {
-                return span_err("BinOpTypeMismatch");
+                return span_err(TypeMessage::BinOpTypeMismatch {
+                    op,
+                    actual: l.ty().clone(),
+                });
             }
 
             if *r.ty() != t {
-                return span_err("BinOpTypeMismatch");
+                return span_err(TypeMessage::BinOpTypeMismatch {
+                    op,
+                    actual: r.ty().clone(),
+                });
             }
 
             t
@@ -156,13 +184,17 @@ fn typecheck_merge(
     scrut: &Tir<'_>,
     type_annot: Option<&Tir<'_>>,
 ) -> Result<Type, TypeError> {
-    let span_err = |msg: &str| mk_span_err(span.clone(), msg);
+    let span_err = |msg| mkerr(env, span.clone(), msg);
     use NirKind::{OptionalType, PiClosure, RecordType, UnionType};
 
     let record_type = record.ty();
     let handlers = match record_type.kind() {
         RecordType(kts) => kts,
-        _ => return span_err("Merge1ArgMustBeRecord"),
+        _ => {
+            return span_err(TypeMessage::Merge1ArgMustBeRecord {
+                actual: record_type.clone(),
+            })
+        }
     };
 
     let scrut_type = scrut.ty();
@@ -174,7 +206,11 @@ fn typecheck_merge(
             kts.insert("Some".into(), Some(ty.clone()));
             Cow::Owned(kts)
         }
-        _ => return span_err("Merge2ArgMustBeUnionOrOptional"),
+        _ => {
+            return span_err(TypeMessage::Merge2ArgMustBeUnionOrOptional {
+                actual: scrut_type.clone(),
+            })
+        }
     };
 
     let mut inferred_type = None;
@@ -184,32 +220,20 @@ fn typecheck_merge(
             Some(Some(variant_type)) => match handler_type.kind() {
                 PiClosure { closure, annot, .. } => {
                     if variant_type != annot {
-                        return mkerr(
-                            ErrorBuilder::new(format!(
-                                "Wrong handler input type"
-                            ))
-                            .span_err(
-                                span,
-                                format!("in this merge expression",),
-                            )
-                            .span_err(
-                                record.span(),
-                                format!(
-                                    "the handler for `{}` expects a value of \
-                                     type: `{}`",
-                                    x,
-                                    annot.to_expr_tyenv(env)
-                                ),
-                            )
-                            .span_err(
-                                scrut.span(),
-                                format!(
-                                    "but the corresponding variant has type: \
-                                     `{}`",
-                                    variant_type.to_expr_tyenv(env)
-                                ),
-                            )
-                            .format(),
+                        return span_err(
+                            TypeMessage::MergeHandlerWrongInputType {
+                                handlers: record.span(),
+                                union: scrut.span(),
+                                variant: x.clone(),
+                                expected: Type::new_infer_universe(
+                                    env,
+                                    variant_type.clone(),
+                                )?,
+                                actual: Type::new_infer_universe(
+                                    env,
+                                    annot.clone(),
+                                )?,
+                            },
                         );
                     }
 
@@ -217,56 +241,55 @@ fn typecheck_merge(
                     match closure.remove_binder() {
                         Ok(v) => Type::new_infer_universe(env, v.clone())?,
                         Err(()) => {
-                            return span_err("MergeReturnTypeIsDependent")
+                            return span_err(
+                                TypeMessage::MergeReturnTypeIsDependent {
+                                    variant: x.clone(),
+                                },
+                            )
                         }
                     }
                 }
                 _ => {
-                    return mkerr(
-                        ErrorBuilder::new(format!(
-                            "merge handler is not a function"
-                        ))
-                        .span_err(span, format!("in this merge expression"))
-                        .span_err(
-                            record.span(),
-                            format!(
-                                "the handler for `{}` has type: `{}`",
-                                x,
-                                handler_type.to_expr_tyenv(env)
-                            ),
-                        )
-                        .span_help(
-                            scrut.span(),
-                            format!(
-                                "the corresponding variant has type: `{}`",
-                                variant_type.to_expr_tyenv(env)
-                            ),
-                        )
-                        .help(format!(
-                            "a handler for this variant must be a function \
-                             that takes an input of type: `{}`",
-                            variant_type.to_expr_tyenv(env)
-                        ))
-                        .format(),
-                    )
+                    return span_err(TypeMessage::MergeHandlerNotAFunction {
+                        handlers: record.span(),
+                        union: scrut.span(),
+                        variant: x.clone(),
+                        variant_type: Type::new_infer_universe(
+                            env,
+                            variant_type.clone(),
+                        )?,
+                        actual: Type::new_infer_universe(
+                            env,
+                            handler_type.clone(),
+                        )?,
+                    })
                 }
             },
             // Union alternative without type
             Some(None) => Type::new_infer_universe(env, handler_type.clone())?,
-            None => return span_err("MergeHandlerMissingVariant"),
+            None => {
+                return span_err(TypeMessage::MergeHandlerMissingVariant {
+                    variant: x.clone(),
+                })
+            }
         };
         match &inferred_type {
             None => inferred_type = Some(handler_return_type),
             Some(t) => {
                 if t != &handler_return_type {
-                    return span_err("MergeHandlerTypeMismatch");
+                    return span_err(TypeMessage::MergeHandlerTypeMismatch {
+                        expected: t.clone(),
+                        actual: handler_return_type,
+                    });
                 }
             }
         }
     }
     for x in variants.keys() {
         if !handlers.contains_key(x) {
-            return span_err("MergeVariantMissingHandler");
+            return span_err(TypeMessage::MergeHandlerMissing {
+                variant: x.clone(),
+            });
         }
     }
 
@@ -277,13 +300,18 @@ fn typecheck_merge(
     Ok(match (inferred_type, type_annot) {
         (Some(t1), Some(t2)) => {
             if t1 != t2 {
-                return span_err("MergeAnnotMismatch");
+                return span_err(TypeMessage::MergeAnnotMismatch {
+                    expected: t2,
+                    actual: t1,
+                });
             }
             t1
         }
         (Some(t), None) => t,
         (None, Some(t)) => t,
-        (None, None) => return span_err("MergeEmptyNeedsAnnotation"),
+        (None, None) => {
+            return span_err(TypeMessage::MergeEmptyNeedsAnnotation)
+        }
     })
 }
 
@@ -292,7 +320,7 @@ pub fn typecheck_operation(
     span: Span,
     opkind: &OpKind<Tir<'_>>,
 ) -> Result<Type, TypeError> {
-    let span_err = |msg: &str| mk_span_err(span.clone(), msg);
+    let span_err = |msg| mkerr(env, span.clone(), msg);
     use NirKind::{ListType, PiClosure, RecordType, UnionType};
     use OpKind::*;
 
@@ -303,58 +331,50 @@ pub fn typecheck_operation(
                 PiClosure { annot, closure, .. } => {
                     if arg.ty().as_nir() != annot {
                         return mkerr(
-                            ErrorBuilder::new(format!(
-                                "wrong type of function argument"
-                            ))
-                            .span_err(
-                                f.span(),
-                                format!(
-                                    "this expects an argument of type: {}",
-                                    annot.to_expr_tyenv(env),
-                                ),
-                            )
-                            .span_err(
-                                arg.span(),
-                                format!(
-                                    "but this has type: {}",
-                                    arg.ty().to_expr_tyenv(env),
-                                ),
-                            )
-                            .note(format!(
-                                "expected type `{}`\n   found type `{}`",
-                                annot.to_expr_tyenv(env),
-                                arg.ty().to_expr_tyenv(env),
-                            ))
-                            .format(),
+                            env,
+                            arg.span(),
+                            TypeMessage::WrongArgumentType {
+                                function: f.span(),
+                                expected: Type::new_infer_universe(
+                                    env,
+                                    annot.clone(),
+                                )?,
+                                actual: arg.ty().clone(),
+                            },
                         );
                     }
 
                     let arg_nf = arg.eval(env);
                     Type::new_infer_universe(env, closure.apply(arg_nf))?
                 }
-                _ => return mkerr(
-                    ErrorBuilder::new(format!(
-                        "expected function, found `{}`",
-                        f.ty().to_expr_tyenv(env)
-                    ))
-                    .span_err(
+                _ => {
+                    return mkerr(
+                        env,
                         f.span(),
-                        format!("function application requires a function",),
+                        TypeMessage::NotAFunction {
+                            actual: f.ty().clone(),
+                        },
                     )
-                    .format(),
-                ),
+                }
             }
         }
         BinOp(o, l, r) => typecheck_binop(env, span, *o, l, r)?,
         BoolIf(x, y, z) => {
             if *x.ty().kind() != NirKind::from_builtin(Builtin::Bool) {
-                return span_err("InvalidPredicate");
+                return span_err(TypeMessage::InvalidPredicate {
+                    actual: x.ty().clone(),
+                });
             }
             if y.ty().ty().as_const() != Some(Const::Type) {
-                return span_err("IfBranchMustBeTerm");
+                return span_err(TypeMessage::IfBranchMustBeTerm {
+                    actual: y.ty().clone(),
+                });
             }
             if y.ty() != z.ty() {
-                return span_err("IfBranchMismatch");
+                return span_err(TypeMessage::IfBranchMismatch {
+                    left: y.ty().clone(),
+                    right: z.ty().clone(),
+                });
             }
 
             y.ty().clone()
@@ -364,13 +384,17 @@ pub fn typecheck_operation(
         }
         ToMap(record, annot) => {
             if record.ty().ty().as_const() != Some(Const::Type) {
-                return span_err("`toMap` only accepts records of type `Type`");
+                return span_err(TypeMessage::InvalidToMapRecordKind {
+                    actual: record.ty().clone(),
+                });
             }
             let record_t = record.ty();
             let kts = match record_t.kind() {
                 RecordType(kts) => kts,
                 _ => {
-                    return span_err("The argument to `toMap` must be a record")
+                    return span_err(TypeMessage::ToMapNotARecord {
+                        actual: record_t.clone(),
+                    })
                 }
             };
 
@@ -378,33 +402,31 @@ pub fn typecheck_operation(
                 let annot = if let Some(annot) = annot {
                     annot
                 } else {
-                    return span_err(
-                        "`toMap` applied to an empty record requires a type \
-                         annotation",
-                    );
+                    return span_err(TypeMessage::ToMapEmptyNeedsAnnotation);
                 };
                 let annot_val = annot.eval_to_type(env)?;
 
-                let err_msg = "The type of `toMap x` must be of the form \
-                               `List { mapKey : Text, mapValue : T }`";
+                let err_msg = || TypeMessage::InvalidToMapType {
+                    actual: annot_val.clone(),
+                };
                 let arg = match annot_val.kind() {
                     ListType(t) => t,
-                    _ => return span_err(err_msg),
+                    _ => return span_err(err_msg()),
                 };
                 let kts = match arg.kind() {
                     RecordType(kts) => kts,
-                    _ => return span_err(err_msg),
+                    _ => return span_err(err_msg()),
                 };
                 if kts.len() != 2 {
-                    return span_err(err_msg);
+                    return span_err(err_msg());
                 }
                 match kts.get("mapKey") {
                     Some(t) if *t == Nir::from_builtin(Builtin::Text) => {}
-                    _ => return span_err(err_msg),
+                    _ => return span_err(err_msg()),
                 }
                 match kts.get("mapValue") {
                     Some(_) => {}
-                    None => return span_err(err_msg),
+                    None => return span_err(err_msg()),
                 }
                 annot_val
             } else {
@@ -412,7 +434,9 @@ pub fn typecheck_operation(
                 for (_, t) in kts.iter() {
                     if *t != entry_type {
                         return span_err(
-                            "Every field of the record must have the same type",
+                            TypeMessage::HeterogenousRecordToMap {
+                                actual: record_t.clone(),
+                            },
                         );
                     }
                 }
@@ -426,7 +450,10 @@ pub fn typecheck_operation(
                 if let Some(annot) = annot {
                     let annot_val = annot.eval_to_type(env)?;
                     if output_type != annot_val {
-                        return span_err("Annotation mismatch");
+                        return span_err(TypeMessage::ToMapAnnotMismatch {
+                            expected: annot_val,
+                            actual: output_type,
+                        });
                     }
                 }
                 output_type
@@ -436,7 +463,12 @@ pub fn typecheck_operation(
             match scrut.ty().kind() {
                 RecordType(kts) => match kts.get(x) {
                     Some(val) => Type::new_infer_universe(env, val.clone())?,
-                    None => return span_err("MissingRecordField"),
+                    None => {
+                        return span_err(TypeMessage::MissingRecordField {
+                            field: x.clone(),
+                            record: scrut.ty().clone(),
+                        })
+                    }
                 },
                 NirKind::Const(_) => {
                     let scrut = scrut.eval_to_type(env)?;
@@ -450,25 +482,49 @@ pub fn typecheck_operation(
                             })
                             .to_type(scrut.ty()),
                             Some(None) => scrut,
-                            None => return span_err("MissingUnionField"),
+                            None => {
+                                return span_err(
+                                    TypeMessage::MissingUnionField {
+                                        field: x.clone(),
+                                        union: scrut.clone(),
+                                    },
+                                )
+                            }
                         },
-                        _ => return span_err("NotARecord"),
+                        _ => {
+                            return span_err(TypeMessage::NotARecord {
+                                actual: scrut.clone(),
+                            })
+                        }
                     }
                 }
-                _ => return span_err("NotARecord"),
+                _ => {
+                    return span_err(TypeMessage::NotARecord {
+                        actual: scrut.ty().clone(),
+                    })
+                }
             }
         }
         Projection(record, labels) => {
             let record_type = record.ty();
             let kts = match record_type.kind() {
                 RecordType(kts) => kts,
-                _ => return span_err("ProjectionMustBeRecord"),
+                _ => {
+                    return span_err(TypeMessage::ProjectionMustBeRecord {
+                        actual: record_type.clone(),
+                    })
+                }
             };
 
             let mut new_kts = HashMap::new();
             for l in labels {
                 match kts.get(l) {
-                    None => return span_err("ProjectionMissingEntry"),
+                    None => {
+                        return span_err(TypeMessage::ProjectionMissingEntry {
+                            field: l.clone(),
+                            record: record_type.clone(),
+                        })
+                    }
                     Some(t) => {
                         new_kts.insert(l.clone(), t.clone());
                     }
@@ -481,23 +537,50 @@ pub fn typecheck_operation(
             let record_type = record.ty();
             let rec_kts = match record_type.kind() {
                 RecordType(kts) => kts,
-                _ => return span_err("ProjectionMustBeRecord"),
+                _ => {
+                    return span_err(TypeMessage::ProjectionMustBeRecord {
+                        actual: record_type.clone(),
+                    })
+                }
             };
 
             let selection_val = selection.eval_to_type(env)?;
             let sel_kts = match selection_val.kind() {
                 RecordType(kts) => kts,
-                _ => return span_err("ProjectionByExprTakesRecordType"),
+                _ => {
+                    return span_err(
+                        TypeMessage::ProjectionByExprTakesRecordType {
+                            actual: selection_val.clone(),
+                        },
+                    )
+                }
             };
 
             for (l, sel_ty) in sel_kts {
                 match rec_kts.get(l) {
                     Some(rec_ty) => {
                         if rec_ty != sel_ty {
-                            return span_err("ProjectionWrongType");
+                            return span_err(
+                                TypeMessage::ProjectionWrongType {
+                                    field: l.clone(),
+                                    expected: Type::new_infer_universe(
+                                        env,
+                                        sel_ty.clone(),
+                                    )?,
+                                    actual: Type::new_infer_universe(
+                                        env,
+                                        rec_ty.clone(),
+                                    )?,
+                                },
+                            );
                         }
                     }
-                    None => return span_err("ProjectionMissingEntry"),
+                    None => {
+                        return span_err(TypeMessage::ProjectionMissingEntry {
+                            field: l.clone(),
+                            record: record_type.clone(),
+                        })
+                    }
                 }
             }
 
@@ -0,0 +1,317 @@
+//! Queries about the subexpressions of a file, for editor tooling.
+use crate::semantics::{
+    type_with, Hir, HirKind, ImportLocation, ImportPolicy, NirKind, TyEnv,
+};
+use crate::syntax::text::cst::lex;
+use crate::syntax::{CstElement, Expr, ExprKind, Label, ParsedSpan, Span, V};
+
+/// What a variable or import refers to.
+#[derive(Debug, Clone)]
+pub enum Definition {
+    /// The variable is bound by a `let` binding; this is the span of its name.
+    Let(Span),
+    /// The import points to this location.
+    Import(ImportLocation),
+}
+
+/// A field of a record or an alternative of a union, as suggested by `completions_at`.
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub label: Label,
+    /// The type of the field, or of the contents of the alternative.
+    pub ty: Option<Expr>,
+    /// Whether this is an alternative of a union rather than a field of a record.
+    pub alternative: bool,
+}
+
+fn as_parsed(span: &Span) -> Option<&ParsedSpan> {
+    match span {
+        Span::Parsed(span) => Some(span),
+        _ => None,
+    }
+}
+
+fn contains(span: &Span, offset: usize) -> bool {
+    match as_parsed(span) {
+        Some(span) => span.start() <= offset && offset <= span.end(),
+        None => false,
+    }
+}
+
+fn children(hir: &Hir) -> Vec<&Hir> {
+    let mut children = Vec::new();
+    if let HirKind::Expr(e) = hir.kind() {
+        e.map_ref(|e| children.push(e));
+    }
+    children
+}
+
+/// Calls `f` on each subexpression of `hir` in its typing environment, parents before children.
+/// Stops as soon as `f` returns `Some`, and skips subexpressions under a binder whose type cannot
+/// be computed.
+fn find_with_env<'a, T>(
+    env: &TyEnv,
+    hir: &'a Hir,
+    f: &mut impl FnMut(&TyEnv, &'a Hir) -> Option<T>,
+) -> Option<T> {
+    if let Some(x) = f(env, hir) {
+        return Some(x);
+    }
+    let extend = |binder: &Label, annot: &Hir| -> Option<TyEnv> {
+        let annot = type_with(env, annot, None).ok()?;
+        Some(env.insert_type(binder, annot.eval_to_type(env).ok()?))
+    };
+    match hir.kind() {
+        HirKind::Expr(ExprKind::Lam(binder, annot, body))
+        | HirKind::Expr(ExprKind::Pi(binder, annot, body)) => {
+            find_with_env(env, annot, f)
+                .or_else(|| find_with_env(&extend(binder, annot)?, body, f))
+        }
+        HirKind::Expr(ExprKind::Let(binder, annot, val, body)) => {
+            let annot = annot.as_ref().and_then(|t| find_with_env(env, t, f));
+            annot.or_else(|| find_with_env(env, val, f)).or_else(|| {
+                let val = type_with(env, val, None).ok()?;
+                let body_env =
+                    env.insert_value(binder, val.eval(env), val.ty().clone());
+                find_with_env(&body_env, body, f)
+            })
+        }
+        // The contents of an import come from another file.
+        HirKind::Import(..) => None,
+        _ => children(hir)
+            .into_iter()
+            .find_map(|child| find_with_env(env, child, f)),
+    }
+}
+
+/// The smallest subexpression that satisfies `pred`, with its environment.
+fn smallest<'a>(
+    hir: &'a Hir,
+    pred: impl Fn(&ParsedSpan) -> bool,
+) -> Option<(TyEnv, &'a Hir)> {
+    let mut found: Option<(TyEnv, &'a Hir, usize)> = None;
+    find_with_env(&TyEnv::new(), hir, &mut |env, hir| {
+        if let Some(span) = as_parsed(&hir.span()) {
+            let len = span.end() - span.start();
+            let smaller = match &found {
+                Some((_, _, found_len)) => len <= *found_len,
+                None => true,
+            };
+            if pred(span) && smaller {
+                found = Some((env.clone(), hir, len));
+            }
+        }
+        None::<()>
+    });
+    found.map(|(env, hir, _)| (env, hir))
+}
+
+/// The type of the smallest subexpression around the byte offset `offset`, with its span.
+pub fn type_at(hir: &Hir, offset: usize) -> Option<(Span, Expr)> {
+    let (env, hir) =
+        smallest(hir, |span| span.start() <= offset && offset <= span.end())?;
+    // On a `let` binding, show the type of the bound value.
+    let value = match hir.kind() {
+        HirKind::Expr(ExprKind::Let(_, _, val, _)) => val,
+        _ => hir,
+    };
+    let tir = type_with(&env, value, None).ok()?;
+    Some((hir.span(), tir.ty().to_expr_tyenv(&env)))
+}
+
+/// The fields or alternatives that may follow a `.` placed at byte offset `offset`, i.e. just
+/// after the end of a subexpression. That subexpression may be a record, a record of types, or a
+/// union type.
+pub fn completions_at(hir: &Hir, offset: usize) -> Vec<Completion> {
+    let (env, hir) = match smallest(hir, |span| span.end() == offset) {
+        Some(x) => x,
+        None => return Vec::new(),
+    };
+    let tir = match type_with(&env, hir, None) {
+        Ok(tir) => tir,
+        Err(_) => return Vec::new(),
+    };
+    let mut completions: Vec<_> = match tir.ty().kind() {
+        NirKind::RecordType(kts) => kts
+            .iter()
+            .map(|(label, ty)| Completion {
+                label: label.clone(),
+                ty: Some(ty.to_expr_tyenv(&env)),
+                alternative: false,
+            })
+            .collect(),
+        _ => match tir.eval(&env).kind() {
+            NirKind::UnionType(kts) => kts
+                .iter()
+                .map(|(label, ty)| Completion {
+                    label: label.clone(),
+                    ty: ty.as_ref().map(|ty| ty.to_expr_tyenv(&env)),
+                    alternative: true,
+                })
+                .collect(),
+            _ => Vec::new(),
+        },
+    };
+    completions.sort_by(|x, y| x.label.cmp(&y.label));
+    completions
+}
+
+/// What the variable or import at byte offset `offset` refers to. `location` is where `expr` was
+/// read from; relative imports are resolved against it.
+pub fn definition_at(
+    expr: &Expr,
+    location: &ImportLocation,
+    offset: usize,
+) -> Option<Definition> {
+    let mut scope = Vec::new();
+    find_definition(expr, location, offset, &mut scope)
+}
+
+/// The span of the name bound by a `let` expression, which is the first token after `let`.
+fn let_binder_span(expr: &Expr) -> Option<Span> {
+    let next = match expr.kind() {
+        ExprKind::Let(_, annot, val, _) => annot.as_ref().unwrap_or(val),
+        _ => return None,
+    };
+    let span = expr.span();
+    let next_span = next.span();
+    let head = as_parsed(&span)?.up_to(as_parsed(&next_span)?.start());
+    let mut start = head.start();
+    let mut tokens = 0;
+    for element in lex(head.text()) {
+        let len = element.to_string().len();
+        if let CstElement::Token(_) = element {
+            if tokens == 1 {
+                return Some(Span::Parsed(head.between(start, start + len)));
+            }
+            tokens += 1;
+        }
+        start += len;
+    }
+    None
+}
+
+fn find_definition<'a>(
+    expr: &'a Expr,
+    location: &ImportLocation,
+    offset: usize,
+    // The enclosing binders, with the span of the name if it is bound by a `let`.
+    scope: &mut Vec<(&'a Label, Option<Span>)>,
+) -> Option<Definition> {
+    if as_parsed(&expr.span()).is_some() && !contains(&expr.span(), offset) {
+        return None;
+    }
+    match expr.kind() {
+        ExprKind::Var(V(name, idx)) => {
+            let (_, span) = scope
+                .iter()
+                .rev()
+                .filter(|(label, _)| *label == name)
+                .nth(*idx)?;
+            return span.clone().map(Definition::Let);
+        }
+        ExprKind::Import(import) => {
+            let target = location
+                .chain(import, None, &ImportPolicy::default())
+                .ok()?;
+            return Some(Definition::Import(target));
+        }
+        _ => {}
+    }
+    let binding_span = match expr.kind() {
+        ExprKind::Let(..) => let_binder_span(expr),
+        _ => None,
+    };
+    let result = expr.kind().traverse_ref_maybe_binder(|binder, e| {
+        if let Some(binder) = binder {
+            scope.push((binder, binding_span.clone()));
+        }
+        let found = find_definition(e, location, offset, scope);
+        if binder.is_some() {
+            scope.pop();
+        }
+        match found {
+            Some(def) => Err(def),
+            None => Ok(()),
+        }
+    });
+    result.err()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::parse::parse_str;
+    use std::path::PathBuf;
+
+    fn resolve(s: &str) -> crate::Resolved {
+        parse_str(s).unwrap().skip_resolve().unwrap()
+    }
+
+    #[test]
+    fn hover_should_show_the_type_of_the_innermost_subexpression() {
+        let s = "let x = { a = 1, b = True } in x.a";
+        let (span, ty) = resolve(s).type_at(s.rfind('x').unwrap()).unwrap();
+        assert_eq!(ty.to_string(), "{ a : Natural, b : Bool }");
+        let span = as_parsed(&span).unwrap();
+        assert_eq!((span.start(), span.end()), (31, 32));
+        let (_, ty) = resolve(s).type_at(s.len()).unwrap();
+        assert_eq!(ty.to_string(), "Natural");
+
+        let s = "λ(n : Natural) → n";
+        let (_, ty) = resolve(s).type_at(s.len()).unwrap();
+        assert_eq!(ty.to_string(), "Natural");
+    }
+
+    #[test]
+    fn completions_should_list_fields_and_alternatives() {
+        let labels = |s: &str| -> Vec<(String, bool)> {
+            resolve(s)
+                .completions_at(s.len())
+                .into_iter()
+                .map(|c| (String::from(&c.label), c.alternative))
+                .collect()
+        };
+        assert_eq!(
+            labels("let x = { b = 1, a = True } in x"),
+            vec![("a".to_owned(), false), ("b".to_owned(), false)]
+        );
+        assert_eq!(
+            labels("let C = < Red | Green : Natural > in C"),
+            vec![("Green".to_owned(), true), ("Red".to_owned(), true)]
+        );
+        assert!(labels("let x = 1 in x").is_empty());
+    }
+
+    #[test]
+    fn definitions_should_point_to_bindings_and_imports() {
+        let s = "let x = 1 let y = 2 in λ(x : Natural) → x@1 + y";
+        let parsed = parse_str(s).unwrap();
+        match parsed.definition_at(s.rfind('x').unwrap()) {
+            Some(Definition::Let(span)) => {
+                let span = as_parsed(&span).unwrap();
+                assert_eq!((span.start(), span.end()), (4, 5))
+            }
+            def => panic!("{:?}", def),
+        }
+        assert!(parsed.definition_at(s.rfind("x@").unwrap()).is_some());
+        assert!(parsed.definition_at(s.find("x :").unwrap()).is_none());
+        let s = "let {- the name -} `y z` : Natural = 1 in `y z`";
+        match parse_str(s).unwrap().definition_at(s.len() - 1) {
+            Some(Definition::Let(span)) => {
+                assert_eq!(as_parsed(&span).unwrap().text(), "`y z`")
+            }
+            def => panic!("{:?}", def),
+        }
+
+        let location = ImportLocation::Local(PathBuf::from("/dir/a.dhall"));
+        let parsed =
+            crate::Parsed::parse_str_at("./b.dhall", location).unwrap();
+        match parsed.definition_at(3) {
+            Some(Definition::Import(ImportLocation::Local(path))) => {
+                assert_eq!(path, PathBuf::from("/dir/b.dhall"))
+            }
+            def => panic!("{:?}", def),
+        }
+    }
+}
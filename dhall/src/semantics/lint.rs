@@ -0,0 +1,712 @@
+//! Checks for common mistakes and outdated idioms, with automatic fixes.
+use std::fmt;
+
+use crate::builtins::Builtin;
+use crate::error::{Diagnostic, DiagnosticSpan, Severity};
+use crate::operations::OpKind;
+use crate::semantics::resolve::ImportLocation;
+use crate::syntax::{
+    Cst, CstElement, CstNode, Expr, ExprKind, Label, NumKind, ParseResult,
+    Span, Trivia, V,
+};
+
+/// Replace the text from `start` to `end` with the string.
+type Edit = (usize, usize, String);
+
+/// The kinds of problems reported by `lint`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// A `let` binding whose variable is never used.
+    UnusedLet(Label),
+    /// A type annotation that does not change anything, e.g. `1 : Natural`.
+    RedundantAnnotation,
+    /// A variable with the name of a builtin, e.g. `` λ(`Natural` : Type) → ... ``.
+    ShadowedBuiltin(Label),
+    /// A use of `Optional/fold` or `Optional/build`, which were removed from the language.
+    DeprecatedBuiltin(Label),
+    /// A type annotation on a `merge` over an `Optional`. It used to be required, but the type
+    /// can now be inferred from the handlers.
+    OptionalMergeAnnotation,
+}
+
+/// A problem found by `lint`.
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub kind: LintKind,
+    /// Where the problem is.
+    pub span: Span,
+    /// The `let`, annotation, etc. where the problem was found, rewritten to fix it, if it can be
+    /// done automatically. Use `fix` to apply all the fixes to an expression.
+    pub fix: Option<Expr>,
+}
+
+impl LintKind {
+    /// A short name for the kind of lint, e.g. `unused-let`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintKind::UnusedLet(_) => "unused-let",
+            LintKind::RedundantAnnotation => "redundant-annotation",
+            LintKind::ShadowedBuiltin(_) => "shadowed-builtin",
+            LintKind::DeprecatedBuiltin(_) => "deprecated-builtin",
+            LintKind::OptionalMergeAnnotation => "optional-merge-annotation",
+        }
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LintKind::UnusedLet(x) => {
+                write!(f, "unused `let` binding `{}`", x.as_ref())
+            }
+            LintKind::RedundantAnnotation => {
+                write!(f, "redundant type annotation")
+            }
+            LintKind::ShadowedBuiltin(x) => {
+                write!(f, "`{}` shadows a builtin", x.as_ref())
+            }
+            LintKind::DeprecatedBuiltin(x) => {
+                write!(f, "`{}` was removed from the language", x.as_ref())
+            }
+            LintKind::OptionalMergeAnnotation => write!(
+                f,
+                "`merge` on an `Optional` does not need a type annotation"
+            ),
+        }
+    }
+}
+
+impl Lint {
+    fn new(kind: LintKind, span: Span, fix: Option<Expr>) -> Self {
+        Lint { kind, span, fix }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(Severity::Warning, &self.kind);
+        if let Span::Parsed(span) = &self.span {
+            diagnostic.spans.push(DiagnosticSpan::new(
+                span.clone(),
+                self.kind.code(),
+                true,
+            ));
+        }
+        let help = match &self.kind {
+            LintKind::UnusedLet(_) => Some("remove the binding".to_owned()),
+            LintKind::RedundantAnnotation
+            | LintKind::OptionalMergeAnnotation => {
+                Some("remove the annotation".to_owned())
+            }
+            LintKind::ShadowedBuiltin(_) => {
+                Some("rename the variable".to_owned())
+            }
+            LintKind::DeprecatedBuiltin(_) => self
+                .fix
+                .as_ref()
+                .map(|fix| format!("use `{}` instead", fix)),
+        };
+        diagnostic.help.extend(help);
+        diagnostic
+    }
+}
+
+/// The problems in `expr`, in the order they appear in the source.
+pub fn lint(expr: &Expr) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    collect(expr, &mut Vec::new(), &mut lints);
+    let range = |lint: &Lint| match &lint.span {
+        Span::Parsed(span) => Some((span.start(), span.end())),
+        _ => None,
+    };
+    lints.sort_by_key(range);
+    // Desugaring can duplicate a subexpression, e.g. with `with`.
+    lints.dedup_by(|x, y| {
+        range(x).is_some() && range(x) == range(y) && x.kind == y.kind
+    });
+    lints
+}
+
+/// Applies all the automatic fixes to `expr`. Subexpressions that are not changed keep their span.
+pub fn fix(expr: &Expr) -> Expr {
+    fix_with(expr, &mut Vec::new())
+}
+
+/// Applies all the automatic fixes to the source text of an expression. Unlike `fix`, only the
+/// text of the fixed expressions changes, so comments and layout are kept.
+pub fn fix_text(text: &str, location: ImportLocation) -> ParseResult<String> {
+    let mut text = text.to_owned();
+    loop {
+        let cst = Cst::parse_at(&text, location.clone())?;
+        // Fixes that overlap an earlier one, e.g. in the value of an unused `let`, are left for
+        // the next round.
+        let mut edits: Vec<Edit> = Vec::new();
+        for lint in lint(cst.expr()) {
+            let mut lint_edits = fix_edits(cst.root(), &lint);
+            // Desugaring can duplicate a variable, e.g. with `with`.
+            lint_edits.sort();
+            lint_edits.dedup();
+            let overlaps = lint_edits.iter().any(|(start, end, _)| {
+                edits.iter().any(|(s, e, _)| start < e && s < end)
+            });
+            if !overlaps {
+                edits.extend(lint_edits);
+            }
+        }
+        if edits.is_empty() {
+            return Ok(text);
+        }
+        edits.sort_by_key(|(start, _, _)| *start);
+        for (start, end, replacement) in edits.into_iter().rev() {
+            text.replace_range(start..end, &replacement);
+        }
+    }
+}
+
+fn collect<'a>(
+    expr: &'a Expr,
+    scope: &mut Vec<&'a Label>,
+    lints: &mut Vec<Lint>,
+) {
+    // Only report the outermost application of a removed builtin.
+    if let Some((lint, args)) = deprecated_call(expr, scope) {
+        lints.push(lint);
+        for arg in args {
+            collect(arg, scope, lints);
+        }
+        return;
+    }
+    lints.extend(check(expr));
+    expr.kind().map_ref_maybe_binder(|binder, e| {
+        scope.extend(binder);
+        collect(e, scope, lints);
+        if binder.is_some() {
+            scope.pop();
+        }
+    });
+}
+
+fn fix_with<'a>(expr: &'a Expr, scope: &mut Vec<&'a Label>) -> Expr {
+    let kind = expr.kind().map_ref_maybe_binder(|binder, e| {
+        scope.extend(binder);
+        let e = fix_with(e, scope);
+        if binder.is_some() {
+            scope.pop();
+        }
+        e
+    });
+    let mut expr = Expr::new(kind, expr.span());
+    loop {
+        let fixed = deprecated_call(&expr, scope)
+            .map(|(lint, _)| lint)
+            .into_iter()
+            .chain(check(&expr))
+            .find_map(|lint| lint.fix);
+        match fixed {
+            // Keep the span of the original expression, so that the formatter can place comments.
+            Some(fixed) if matches!(fixed.span(), Span::Artificial) => {
+                expr = Expr::new(fixed.kind().clone(), expr.span())
+            }
+            Some(fixed) => expr = fixed,
+            None => return expr,
+        }
+    }
+}
+
+/// The problems with `expr` itself, not counting its subexpressions.
+fn check(expr: &Expr) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    match expr.kind() {
+        ExprKind::Lam(x, t, _) | ExprKind::Pi(x, t, _)
+            if is_builtin_name(x) =>
+        {
+            lints.push(Lint::new(
+                LintKind::ShadowedBuiltin(x.clone()),
+                span_up_to(expr, t),
+                None,
+            ));
+        }
+        ExprKind::Let(x, _, v, _) if is_builtin_name(x) => {
+            lints.push(Lint::new(
+                LintKind::ShadowedBuiltin(x.clone()),
+                span_up_to(expr, v),
+                None,
+            ));
+        }
+        _ => {}
+    }
+    match expr.kind() {
+        ExprKind::Let(x, t, v, body) => {
+            let is_test = matches!(v.kind(), ExprKind::Assert(_));
+            if !is_test && !is_used(x, 0, body) {
+                lints.push(Lint::new(
+                    LintKind::UnusedLet(x.clone()),
+                    span_up_to(expr, v),
+                    Some(shift(-1, x, 0, body)),
+                ));
+            }
+            // Don't report the same annotation twice.
+            if let (Some(t), ExprKind::Annot(v2, t2)) = (t, v.kind()) {
+                if t == t2 && !is_redundant_annotation(v2, t2) {
+                    let fix = ExprKind::Let(
+                        x.clone(),
+                        Some(t.clone()),
+                        v2.clone(),
+                        body.clone(),
+                    );
+                    lints.push(Lint::new(
+                        LintKind::RedundantAnnotation,
+                        v.span(),
+                        Some(Expr::new(fix, expr.span())),
+                    ));
+                }
+            }
+        }
+        ExprKind::Annot(x, t) if is_redundant_annotation(x, t) => {
+            lints.push(Lint::new(
+                LintKind::RedundantAnnotation,
+                expr.span(),
+                Some(x.clone()),
+            ));
+        }
+        ExprKind::Op(OpKind::Merge(handlers, x, Some(_))) => {
+            let is_optional = match handlers.kind() {
+                ExprKind::RecordLit(kvs) => {
+                    kvs.len() == 2
+                        && kvs.contains_key("None")
+                        && kvs.contains_key("Some")
+                }
+                _ => false,
+            };
+            if is_optional {
+                let fix = ExprKind::Op(OpKind::Merge(
+                    handlers.clone(),
+                    x.clone(),
+                    None,
+                ));
+                lints.push(Lint::new(
+                    LintKind::OptionalMergeAnnotation,
+                    expr.span(),
+                    Some(Expr::new(fix, expr.span())),
+                ));
+            }
+        }
+        _ => {}
+    }
+    lints
+}
+
+/// The span of `expr` up to the end of its subexpression `sub`, e.g. a `let` binding without its
+/// body.
+fn span_up_to(expr: &Expr, sub: &Expr) -> Span {
+    match (expr.span(), sub.span()) {
+        (Span::Parsed(span), Span::Parsed(sub)) if span.same_file(&sub) => {
+            Span::Parsed(span.up_to(sub.end()))
+        }
+        (span, _) => span,
+    }
+}
+
+/// The text edits that apply the fix of `lint`, if it has one.
+fn fix_edits(root: &CstNode, lint: &Lint) -> Vec<Edit> {
+    let (span, fix) = match (&lint.span, &lint.fix) {
+        (Span::Parsed(span), Some(fix)) => (span, fix),
+        _ => return Vec::new(),
+    };
+    // The lint spans the whole expression, except for an unused `let` which stops at the value.
+    let node = find_node(root, span.start(), &|node| match &lint.kind {
+        LintKind::UnusedLet(_) => {
+            matches!(node.expr().kind(), ExprKind::Let(..))
+        }
+        _ => node.end() == span.end(),
+    });
+    let node = match node {
+        Some(node) => node,
+        None => return Vec::new(),
+    };
+    match (&lint.kind, node.expr().kind()) {
+        (LintKind::UnusedLet(x), ExprKind::Let(_, _, _, body)) => {
+            // Remove everything up to the body, including the `in` and the whitespace after it.
+            let elements = positioned(node);
+            let body_start = match elements.iter().position(
+                |(_, e)| matches!(e, CstElement::Token(t) if t == "in"),
+            ) {
+                Some(i) => elements[i + 1..]
+                    .iter()
+                    .find(|(_, e)| {
+                        !matches!(e, CstElement::Trivia(Trivia::Whitespace(_)))
+                    })
+                    .map(|(pos, _)| *pos)
+                    .unwrap_or_else(|| node.end()),
+                // In `let x = 1 let y = 2 in y`, the body is the second `let`.
+                None => node.children().last().unwrap().start(),
+            };
+            let mut edits =
+                vec![remove_keeping_comments(node, node.start(), body_start)];
+            unshift_edits(x, 0, body, &mut edits);
+            edits
+        }
+        (LintKind::RedundantAnnotation, _)
+        | (LintKind::OptionalMergeAnnotation, _) => {
+            // Remove the `: T` at the end, along with the whitespace before it.
+            let elements = positioned(node);
+            let colon = elements.iter().position(
+                |(_, e)| matches!(e, CstElement::Token(t) if t == ":"),
+            );
+            let start = colon.and_then(|colon| {
+                elements[..colon]
+                    .iter()
+                    .rev()
+                    .find(|(_, e)| !matches!(e, CstElement::Trivia(_)))
+                    .map(|(pos, e)| pos + element_len(e))
+            });
+            match start {
+                Some(start) => {
+                    vec![remove_keeping_comments(node, start, node.end())]
+                }
+                None => Vec::new(),
+            }
+        }
+        _ => vec![(node.start(), node.end(), fix.to_string())],
+    }
+}
+
+/// The innermost node starting at `start` for which `pred` holds.
+fn find_node<'a>(
+    node: &'a CstNode,
+    start: usize,
+    pred: &dyn Fn(&CstNode) -> bool,
+) -> Option<&'a CstNode> {
+    node.children()
+        .filter(|child| child.start() <= start && start < child.end())
+        .find_map(|child| find_node(child, start, pred))
+        .or_else(|| {
+            Some(node).filter(|node| node.start() == start && pred(node))
+        })
+}
+
+/// The direct elements of `node`, with their offset in the source.
+fn positioned(node: &CstNode) -> Vec<(usize, &CstElement)> {
+    let mut pos = node.start();
+    node.elements()
+        .iter()
+        .map(|element| {
+            let start = pos;
+            pos += element_len(element);
+            (start, element)
+        })
+        .collect()
+}
+
+fn element_len(element: &CstElement) -> usize {
+    match element {
+        CstElement::Node(node) => node.end() - node.start(),
+        _ => element.to_string().len(),
+    }
+}
+
+/// An edit that removes the text of `node` from `start` to `end`, except for the comments that
+/// are directly in it and the whitespace around them.
+fn remove_keeping_comments(node: &CstNode, start: usize, end: usize) -> Edit {
+    let elements = positioned(node);
+    let is_comment = |i: usize| matches!(elements.get(i), Some((_, CstElement::Trivia(t))) if t.is_comment());
+    let mut kept = String::new();
+    let mut ends_with_line_comment = false;
+    for (i, (pos, element)) in elements.iter().enumerate() {
+        if *pos < start || pos + element_len(element) > end {
+            continue;
+        }
+        match element {
+            CstElement::Trivia(Trivia::Whitespace(s))
+                if (i > 0 && is_comment(i - 1)) || is_comment(i + 1) =>
+            {
+                kept.push_str(s)
+            }
+            CstElement::Trivia(trivia) if trivia.is_comment() => {
+                kept.push_str(trivia.text());
+                ends_with_line_comment =
+                    matches!(trivia, Trivia::LineComment(_));
+            }
+            _ => {}
+        }
+    }
+    if start == node.start() {
+        kept = kept.trim_start().to_owned();
+    }
+    if end == node.end() {
+        kept.truncate(kept.trim_end().len());
+        if ends_with_line_comment {
+            kept.push('\n');
+        }
+    }
+    (start, end, kept)
+}
+
+/// The edits that decrement the indices of the variables `x@n` in `expr` with `n >= min`, like
+/// `shift(-1, ...)`.
+fn unshift_edits(x: &Label, min: usize, expr: &Expr, edits: &mut Vec<Edit>) {
+    match (expr.kind(), expr.span()) {
+        (ExprKind::Var(V(y, n)), Span::Parsed(span)) if x == y && *n >= min => {
+            edits.push((
+                span.start(),
+                span.end(),
+                V(y.clone(), n - 1).to_string(),
+            ));
+        }
+        (kind, _) => {
+            kind.map_ref_maybe_binder(|binder, e| {
+                let min = if binder == Some(x) { min + 1 } else { min };
+                unshift_edits(x, min, e, edits)
+            });
+        }
+    }
+}
+
+/// Whether `x : t` can be replaced with `x`.
+fn is_redundant_annotation(x: &Expr, t: &Expr) -> bool {
+    match x.kind() {
+        ExprKind::Annot(_, t2) => t == t2,
+        _ => match (literal_type(x), t.kind()) {
+            (Some(b), ExprKind::Builtin(b2)) => b == *b2,
+            _ => false,
+        },
+    }
+}
+
+/// Whether `x` is the name of a builtin, and can thus only be bound using backticks.
+fn is_builtin_name(x: &Label) -> bool {
+    let name = x.as_ref();
+    Builtin::parse(name).is_some()
+        || matches!(name, "Type" | "Kind" | "Sort" | "True" | "False")
+}
+
+/// The type of a literal, if it is obvious.
+fn literal_type(expr: &Expr) -> Option<Builtin> {
+    Some(match expr.kind() {
+        ExprKind::Num(NumKind::Bool(_)) => Builtin::Bool,
+        ExprKind::Num(NumKind::Natural(_)) => Builtin::Natural,
+        ExprKind::Num(NumKind::Integer(_)) => Builtin::Integer,
+        ExprKind::Num(NumKind::Double(_)) => Builtin::Double,
+        ExprKind::TextLit(_) => Builtin::Text,
+        _ => return None,
+    })
+}
+
+/// If `expr` applies `Optional/fold` or `Optional/build`, a lint for it and the arguments.
+fn deprecated_call<'a>(
+    expr: &'a Expr,
+    scope: &[&Label],
+) -> Option<(Lint, Vec<&'a Expr>)> {
+    let mut head = expr;
+    let mut args = Vec::new();
+    while let ExprKind::Op(OpKind::App(f, a)) = head.kind() {
+        args.push(a);
+        head = f;
+    }
+    args.reverse();
+    let name = match head.kind() {
+        ExprKind::Var(V(name, idx))
+            if scope.iter().filter(|x| **x == name).count() <= *idx =>
+        {
+            name
+        }
+        _ => return None,
+    };
+    let app = |f: Expr, a: &Expr| {
+        Expr::new(ExprKind::Op(OpKind::App(f, a.clone())), Span::Artificial)
+    };
+    let artificial = |kind| Expr::new(kind, Span::Artificial);
+    let (arity, fixed) = match name.as_ref() {
+        // `Optional/fold a o optional some none`
+        "Optional/fold" => (
+            5,
+            args.get(4).map(|_| {
+                let mut handlers = std::collections::BTreeMap::new();
+                handlers.insert(Label::from("None"), args[4].clone());
+                handlers.insert(Label::from("Some"), args[3].clone());
+                artificial(ExprKind::Op(OpKind::Merge(
+                    artificial(ExprKind::RecordLit(handlers)),
+                    args[1].clone(),
+                    None,
+                )))
+            }),
+        ),
+        // `Optional/build a g` becomes `g (Optional a) (λ(a : a) → Some a) (None a)`
+        "Optional/build" => (
+            2,
+            args.get(1).map(|_| {
+                let ty = args[0];
+                let builtin = |b| artificial(ExprKind::Builtin(b));
+                let x = Label::from("a");
+                let some = artificial(ExprKind::Lam(
+                    x.clone(),
+                    ty.clone(),
+                    artificial(ExprKind::SomeLit(artificial(ExprKind::Var(
+                        V(x, 0),
+                    )))),
+                ));
+                let f =
+                    app(args[1].clone(), &app(builtin(Builtin::Optional), ty));
+                let f = app(f, &some);
+                app(f, &app(builtin(Builtin::OptionalNone), ty))
+            }),
+        ),
+        _ => return None,
+    };
+    let fix =
+        fixed.map(|fixed| args[arity..].iter().fold(fixed, |f, a| app(f, a)));
+    let lint =
+        Lint::new(LintKind::DeprecatedBuiltin(name.clone()), expr.span(), fix);
+    Some((lint, args))
+}
+
+/// Whether `expr` mentions the variable `x@idx`.
+fn is_used(x: &Label, idx: usize, expr: &Expr) -> bool {
+    match expr.kind() {
+        ExprKind::Var(V(y, n)) => x == y && idx == *n,
+        kind => kind
+            .traverse_ref_maybe_binder(|binder, e| {
+                let idx = if binder == Some(x) { idx + 1 } else { idx };
+                if is_used(x, idx, e) {
+                    Err(())
+                } else {
+                    Ok(())
+                }
+            })
+            .is_err(),
+    }
+}
+
+/// Adds `delta` to the indices of the variables `x@n` in `expr` with `n >= min`.
+fn shift(delta: isize, x: &Label, min: usize, expr: &Expr) -> Expr {
+    let kind = match expr.kind() {
+        ExprKind::Var(V(y, n)) if x == y && *n >= min => {
+            ExprKind::Var(V(y.clone(), (*n as isize + delta) as usize))
+        }
+        kind => kind.map_ref_maybe_binder(|binder, e| {
+            let min = if binder == Some(x) { min + 1 } else { min };
+            shift(delta, x, min, e)
+        }),
+    };
+    Expr::new(kind, expr.span())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::parse::parse_str;
+
+    fn codes(s: &str) -> Vec<&str> {
+        lint(&parse_str(s).unwrap().to_expr())
+            .iter()
+            .map(|lint| lint.kind.code())
+            .collect()
+    }
+
+    fn fixed(s: &str) -> String {
+        fix(&parse_str(s).unwrap().to_expr()).to_string()
+    }
+
+    fn fixed_text(s: &str) -> String {
+        fix_text(s, ImportLocation::Missing).unwrap()
+    }
+
+    #[test]
+    fn lint_should_report_problems() {
+        assert_eq!(codes("let x = 1 in 2"), vec!["unused-let"]);
+        assert!(codes("let x = 1 in λ(x : Natural) → x@1").is_empty());
+        assert!(codes("let x = assert : 1 ≡ 1 in 2").is_empty());
+        assert_eq!(codes("[1 : Natural, (x : Bool) : Bool]").len(), 2);
+        assert_eq!(
+            codes("let x : Text = \"a\" : Text in x"),
+            vec!["redundant-annotation"]
+        );
+        assert_eq!(
+            codes("let x : Bool = x : Bool in x"),
+            vec!["redundant-annotation"]
+        );
+        assert_eq!(
+            codes("λ(`Natural` : Type) → `Natural`"),
+            vec!["shadowed-builtin"]
+        );
+        assert_eq!(
+            codes("Optional/fold Natural o Natural (λ(x : Natural) → x) 0"),
+            vec!["deprecated-builtin"]
+        );
+        assert!(codes("λ(`Optional/fold` : Bool) → `Optional/fold`").is_empty());
+        assert_eq!(
+            codes("merge { None = 0, Some = λ(x : Natural) → x } o : Natural"),
+            vec!["optional-merge-annotation"]
+        );
+        assert!(codes("merge { A = 0 } x : Natural").is_empty());
+        // `with` duplicates its subject for each label of the path.
+        assert_eq!(
+            codes("λ(r : T) → ((r : T) : T) with a.b.c = 3"),
+            vec!["redundant-annotation"]
+        );
+    }
+
+    #[test]
+    fn fix_should_rewrite_the_expression() {
+        assert_eq!(fixed("let x = 1 let y = x in 2"), "2");
+        assert_eq!(fixed("let x = 1 in let x = 2 in x@1"), "let x = 1 in x");
+        assert_eq!(
+            fixed("let x = 1 in λ(x : Natural) → x@1 + x"),
+            "let x = 1 in λ(x : Natural) → x@1 + x"
+        );
+        assert_eq!(fixed("(1 : Natural) : Natural"), "1");
+        assert_eq!(
+            fixed("let x : Bool = x : Bool in x"),
+            "let x : Bool = x in x"
+        );
+        assert_eq!(
+            fixed("Optional/fold Natural o Natural (λ(x : Natural) → x) 0"),
+            "merge { `None` = 0, `Some` = λ(x : Natural) → x } o"
+        );
+        assert_eq!(
+            fixed("Optional/build Natural g"),
+            "g (Optional Natural) (λ(a : Natural) → Some a) (None Natural)"
+        );
+        assert_eq!(
+            fixed("merge { None = 0, Some = λ(x : Natural) → x } o : Natural"),
+            "merge { `None` = 0, `Some` = λ(x : Natural) → x } o"
+        );
+    }
+
+    #[test]
+    fn fix_text_should_keep_comments_and_layout() {
+        assert_eq!(
+            fixed_text(
+                "-- Header\n\
+                 let x = 1\n\n\
+                 -- The answer\n\
+                 let y = 42\n\n\
+                 in  { y, n = 1 {- no need -} : Natural, z = (True) : Bool }\n"
+            ),
+            "-- Header\n\
+             -- The answer\n\
+             let y = 42\n\n\
+             in  { y, n = 1 {- no need -}, z = (True) }\n"
+        );
+        assert_eq!(
+            fixed_text("let x = 1 -- used\nin  (let x = 2 in x@1)"),
+            "let x = 1 -- used\nin  (x)"
+        );
+        assert_eq!(
+            fixed_text("let x = 1 -- unused\nin  (2)"),
+            "-- unused\n(2)"
+        );
+        assert_eq!(
+            fixed_text(
+                "merge { None = 0, Some = λ(x : Natural) → x }\n  o\n  : Natural"
+            ),
+            "merge { None = 0, Some = λ(x : Natural) → x }\n  o"
+        );
+        assert_eq!(
+            fixed_text("let x = r in (let x = 2 in x@1 with a.b = 3)"),
+            "let x = r in (x with a.b = 3)"
+        );
+        assert_eq!(
+            fixed_text("Optional/build Natural g"),
+            "g (Optional Natural) (λ(a : Natural) → Some a) (None Natural)"
+        );
+    }
+}
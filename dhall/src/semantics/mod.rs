@@ -1,5 +1,8 @@
+pub mod inspect;
+pub mod lint;
 pub mod nze;
 pub mod parse;
+pub mod repl;
 pub mod resolve;
 pub mod tck;
 pub use self::nze::*;
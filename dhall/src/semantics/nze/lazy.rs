@@ -1,17 +1,19 @@
-use once_cell::unsync::OnceCell;
-use std::cell::Cell;
+use once_cell::sync::OnceCell;
 use std::fmt::Debug;
 use std::ops::Deref;
+use std::sync::Mutex;
 
 pub trait Eval<Tgt> {
     fn eval(self) -> Tgt;
 }
 
-/// A value which is initialized from a `Src` on the first access.
+/// A value which is initialized from a `Src` on the first access. It is `Sync` as long as `Src`
+/// is `Send` and `Tgt` is `Send + Sync`: if two threads access it at the same time, one of them
+/// waits for the other to finish evaluating.
 pub struct Lazy<Src, Tgt> {
     /// Exactly one of `src` of `tgt` must be set at a given time.
     /// Once `src` is unset and `tgt` is set, we never go back.
-    src: Cell<Option<Src>>,
+    src: Mutex<Option<Src>>,
     tgt: OnceCell<Tgt>,
 }
 
@@ -22,14 +24,14 @@ where
     /// Creates a new lazy value with the given initializing value.
     pub fn new(src: Src) -> Self {
         Lazy {
-            src: Cell::new(Some(src)),
+            src: Mutex::new(Some(src)),
             tgt: OnceCell::new(),
         }
     }
     /// Creates a new lazy value with the given already-initialized value.
     pub fn new_completed(tgt: Tgt) -> Self {
         let lazy = Lazy {
-            src: Cell::new(None),
+            src: Mutex::new(None),
             tgt: OnceCell::new(),
         };
         let _ = lazy.tgt.set(tgt);
@@ -44,7 +46,8 @@ where
     type Target = Tgt;
     fn deref(&self) -> &Self::Target {
         self.tgt.get_or_init(|| {
-            let src = self.src.take().unwrap();
+            // The lock is released before evaluating, which may access other lazy values.
+            let src = self.src.lock().unwrap().take().unwrap();
             src.eval()
         })
     }
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::builtins::{Builtin, BuiltinClosure};
 use crate::operations::{BinOp, OpKind};
@@ -14,13 +14,13 @@ use crate::syntax::{
 use crate::ToExprOptions;
 
 /// Stores a possibly unevaluated value. Gets (partially) normalized on-demand, sharing computation
-/// automatically. Uses a Rc<RefCell> to share computation.
+/// automatically. Uses an `Arc` to share computation, including between threads.
 /// If you compare for equality two `Nir`s, then equality will be up to alpha-equivalence
 /// (renaming of bound variables) and beta-equivalence (normalization). It will recursively
 /// normalize as needed.
 /// Stands for "Normalized intermediate representation"
 #[derive(Clone)]
-pub struct Nir(Rc<NirInternal>);
+pub struct Nir(Arc<NirInternal>);
 
 #[derive(Debug)]
 struct NirInternal {
@@ -285,7 +285,7 @@ impl NirInternal {
         }
     }
     fn into_nir(self) -> Nir {
-        Nir(Rc::new(self))
+        Nir(Arc::new(self))
     }
 
     fn kind(&self) -> &NirKind {
@@ -424,7 +424,7 @@ impl lazy::Eval<NirKind> for Thunk {
 /// Compare two values for equality modulo alpha/beta-equivalence.
 impl std::cmp::PartialEq for Nir {
     fn eq(&self, other: &Self) -> bool {
-        Rc::ptr_eq(&self.0, &other.0) || self.kind() == other.kind()
+        Arc::ptr_eq(&self.0, &other.0) || self.kind() == other.kind()
     }
 }
 impl std::cmp::Eq for Nir {}
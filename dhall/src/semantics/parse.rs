@@ -4,22 +4,24 @@ use std::path::Path;
 use url::Url;
 
 use crate::error::Error;
-use crate::semantics::resolve::{download_http_text, ImportLocation};
+use crate::semantics::resolve::{
+    DefaultFetcher, Fetcher, Headers, ImportLocation,
+};
 use crate::syntax::binary;
-use crate::syntax::parse_expr;
+use crate::syntax::{parse_expr, parse_expr_at};
 use crate::Parsed;
 
 pub fn parse_file(f: &Path) -> Result<Parsed, Error> {
     let text = std::fs::read_to_string(f)?;
-    let expr = parse_expr(&text)?;
-    let root = ImportLocation::Local(f.to_owned());
+    let root = ImportLocation::local(f);
+    let expr = parse_expr_at(&text, root.clone())?;
     Ok(Parsed(expr, root))
 }
 
-pub fn parse_remote(url: Url) -> Result<Parsed, Error> {
-    let body = download_http_text(url.clone())?;
-    let expr = parse_expr(&body)?;
-    let root = ImportLocation::Remote(url);
+pub fn parse_remote(url: Url, headers: Headers) -> Result<Parsed, Error> {
+    let body = DefaultFetcher.fetch_url(&url, &headers)?;
+    let root = ImportLocation::Remote(url, headers);
+    let expr = parse_expr_at(&body, root.clone())?;
     Ok(Parsed(expr, root))
 }
 
@@ -29,6 +31,14 @@ pub fn parse_str(s: &str) -> Result<Parsed, Error> {
     Ok(Parsed(expr, root))
 }
 
+pub fn parse_str_at(
+    s: &str,
+    location: ImportLocation,
+) -> Result<Parsed, Error> {
+    let expr = parse_expr_at(s, location.clone())?;
+    Ok(Parsed(expr, location))
+}
+
 pub fn parse_binary(data: &[u8]) -> Result<Parsed, Error> {
     let expr = binary::decode(data)?;
     let root = ImportLocation::Missing;
@@ -39,6 +49,6 @@ pub fn parse_binary_file(f: &Path) -> Result<Parsed, Error> {
     let mut buffer = Vec::new();
     File::open(f)?.read_to_end(&mut buffer)?;
     let expr = binary::decode(&buffer)?;
-    let root = ImportLocation::Local(f.to_owned());
+    let root = ImportLocation::local(f);
     Ok(Parsed(expr, root))
 }
@@ -0,0 +1,150 @@
+//! The state of an interactive session, as used by `dhall repl`.
+use crate::error::Error;
+use crate::semantics::{
+    resolve_one_import, traverse_resolve_expr, type_with, Cache,
+    DefaultFetcher, Hir, ImportEnv, ImportLocation, ImportPolicy, TyEnv, Type,
+};
+use crate::syntax::{parse_expr, parse_let_binding_at, Expr, Label};
+
+/// A binding added with `Repl::insert_let`, as it was written.
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: Label,
+    pub annot: Option<Expr>,
+    pub value: Expr,
+}
+
+/// Expressions are evaluated in the context of the bindings added so far. Imports are resolved
+/// relative to the current directory, and are only read once per session.
+#[derive(Debug)]
+pub struct Repl {
+    env: TyEnv,
+    bindings: Vec<Binding>,
+    imports: ImportEnv,
+    cache: Cache,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl {
+            env: TyEnv::new(),
+            bindings: Vec::new(),
+            imports: ImportEnv::new(),
+            cache: Cache::new(),
+        }
+    }
+
+    /// The bindings added so far, oldest first.
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    fn resolve(&mut self, expr: &Expr) -> Result<Hir, Error> {
+        let Repl {
+            env,
+            imports,
+            cache,
+            ..
+        } = self;
+        traverse_resolve_expr(
+            &mut env.as_nameenv().clone(),
+            expr,
+            &mut |import, span| {
+                resolve_one_import(
+                    imports,
+                    cache,
+                    &DefaultFetcher,
+                    &ImportPolicy::new(),
+                    &import,
+                    &ImportLocation::Missing,
+                    span,
+                )
+            },
+        )
+    }
+
+    fn typecheck(&mut self, input: &str) -> Result<(Hir, Type), Error> {
+        let hir = self.resolve(&parse_expr(input)?)?;
+        let ty = type_with(&self.env, &hir, None)?.ty().clone();
+        Ok((hir, ty))
+    }
+
+    /// The normal form of the expression.
+    pub fn eval(&mut self, input: &str) -> Result<Expr, Error> {
+        let (hir, _) = self.typecheck(input)?;
+        Ok(hir.eval(&self.env).to_expr_tyenv(&self.env))
+    }
+
+    /// The type of the expression.
+    pub fn type_of(&mut self, input: &str) -> Result<Expr, Error> {
+        let (_, ty) = self.typecheck(input)?;
+        Ok(ty.to_expr_tyenv(&self.env))
+    }
+
+    /// The semantic hash of the expression, as used in `sha256:` integrity checks.
+    pub fn hash(&mut self, input: &str) -> Result<Box<[u8]>, Error> {
+        let (hir, _) = self.typecheck(input)?;
+        let nir = hir.eval(&self.env);
+        nir.to_hir(self.env.as_varenv()).to_expr_alpha().hash()
+    }
+
+    /// Add a binding written `let x = e` or `let x : T = e`. Returns its name and type.
+    pub fn insert_let(&mut self, input: &str) -> Result<(Label, Expr), Error> {
+        let (name, annot, value) =
+            parse_let_binding_at(input, ImportLocation::Missing)?;
+        let annot_ty = match &annot {
+            Some(annot) => {
+                let annot = self.resolve(annot)?;
+                Some(
+                    type_with(&self.env, &annot, None)?
+                        .eval_to_type(&self.env)?,
+                )
+            }
+            None => None,
+        };
+        let hir = self.resolve(&value)?;
+        let ty = type_with(&self.env, &hir, annot_ty)?.ty().clone();
+        let ty_expr = ty.to_expr_tyenv(&self.env);
+        self.env = self.env.insert_value(&name, hir.eval(&self.env), ty);
+        self.bindings.push(Binding {
+            name: name.clone(),
+            annot,
+            value,
+        });
+        Ok((name, ty_expr))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn repl_should_keep_bindings_across_inputs() {
+        let mut repl = Repl::new();
+        let (name, ty) = repl.insert_let("let x = 1").unwrap();
+        assert_eq!(
+            (String::from(&name), ty.to_string()),
+            ("x".into(), "Natural".into())
+        );
+        repl.insert_let("let f : Natural → Natural = λ(n : Natural) → n + x")
+            .unwrap();
+        repl.insert_let("let x = 2").unwrap();
+        assert_eq!(repl.eval("f x@0 + x@1").unwrap().to_string(), "4");
+        assert_eq!(
+            repl.eval("λ(y : Natural) → f y").unwrap().to_string(),
+            "λ(y : Natural) → y + 1"
+        );
+        assert_eq!(
+            repl.type_of("f").unwrap().to_string(),
+            "∀(n : Natural) → Natural"
+        );
+        assert_eq!(repl.hash("x").unwrap(), repl.hash("1 + 1").unwrap());
+        assert_eq!(repl.bindings().len(), 3);
+
+        assert!(repl.insert_let("let y : Bool = 1").is_err());
+        assert!(repl.insert_let("let y = 1 in y").is_err());
+        assert!(repl.eval("y").is_err());
+        assert_eq!(repl.bindings().len(), 3);
+    }
+}
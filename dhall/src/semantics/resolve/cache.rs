@@ -67,6 +67,11 @@ impl Cache {
     pub fn new() -> Cache {
         Cache::new_with_provider(|name| env::var(OsStr::new(name)))
     }
+
+    /// A cache that is never read nor written, so that resolution doesn't touch the disk cache.
+    pub fn disabled() -> Cache {
+        Cache { cache_dir: None }
+    }
 }
 
 impl Cache {
@@ -96,7 +101,7 @@ impl Cache {
     }
 
     // Side effect since we don't use the result
-    fn save_expr(&self, import: &Import, expr: &Expr) {
+    pub(crate) fn save_expr(&self, import: &Import, expr: &Expr) {
         self.cache_file(import)
             .map(|cache_file| save_expr(cache_file.as_path(), expr));
     }
@@ -272,8 +277,10 @@ mod test {
         });
 
         // Create cache file
-        let expr =
-            Expr::new(ExprKind::Num(NumKind::Natural(1)), Span::Artificial);
+        let expr = Expr::new(
+            ExprKind::Num(NumKind::Natural(1u64.into())),
+            Span::Artificial,
+        );
         File::create(dir.join("dhall").join("1220d60d8415e36e86dae7f42933d3b0c4fe3ca238f057fba206c7e9fbf5d784fe15"))?
             .write_all(binary::encode(&expr)?.as_ref())?;
 
@@ -478,8 +485,10 @@ mod test {
             Ok(dir.clone().to_str().map(String::from).unwrap())
         });
 
-        let expr =
-            Expr::new(ExprKind::Num(NumKind::Natural(2)), Span::Artificial);
+        let expr = Expr::new(
+            ExprKind::Num(NumKind::Natural(2u64.into())),
+            Span::Artificial,
+        );
         File::create(dir.join("dhall").join("1220d60d8415e36e86dae7f42933d3b0c4fe3ca238f057fba206c7e9fbf5d784fe15"))?
             .write_all(binary::encode(&expr)?.as_slice())?;
 
@@ -538,8 +547,10 @@ mod test {
             Ok(dir.clone().to_str().map(String::from).unwrap())
         });
 
-        let expr =
-            Expr::new(ExprKind::Num(NumKind::Natural(2)), Span::Artificial);
+        let expr = Expr::new(
+            ExprKind::Num(NumKind::Natural(2u64.into())),
+            Span::Artificial,
+        );
         File::create(dir.join("dhall").join("1220d60d8415e36e86dae7f42933d3b0c4fe3ca238f057fba206c7e9fbf5d784fe15"))?
             .write_all(binary::encode(&expr)?.as_slice())?;
 
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
 use crate::error::{Error, ImportError};
-use crate::semantics::{AlphaVar, ImportLocation, TypedHir, VarEnv};
+use crate::semantics::{
+    AlphaVar, ImportEdge, ImportLocation, TypedHir, VarEnv,
+};
 use crate::syntax::{Label, V};
 
 /// Environment for resolving names.
@@ -18,6 +20,7 @@ pub type ImportStack = Vec<ImportLocation>;
 pub struct ImportEnv {
     cache: ImportCache,
     stack: ImportStack,
+    imports: Vec<ImportEdge>,
 }
 
 impl NameEnv {
@@ -69,6 +72,31 @@ impl ImportEnv {
         ImportEnv::default()
     }
 
+    /// An environment that starts with the given resolved imports.
+    pub fn with_cache(cache: ImportCache) -> Self {
+        ImportEnv {
+            cache,
+            ..ImportEnv::default()
+        }
+    }
+
+    pub fn record_import(&mut self, edge: ImportEdge) {
+        self.imports.push(edge)
+    }
+    /// Whether the import of `location` recorded earlier was read from the on-disk cache.
+    pub fn served_from_cache(&self, location: &ImportLocation) -> bool {
+        self.imports
+            .iter()
+            .any(|edge| edge.child == *location && edge.from_cache)
+    }
+    pub fn into_imports(self) -> Vec<ImportEdge> {
+        self.imports
+    }
+    /// The resolved imports, and the imports recorded with `record_import`.
+    pub fn into_parts(self) -> (ImportCache, Vec<ImportEdge>) {
+        (self.cache, self.imports)
+    }
+
     pub fn handle_import(
         &mut self,
         mut location: ImportLocation,
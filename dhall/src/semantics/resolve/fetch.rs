@@ -0,0 +1,130 @@
+#[cfg(any(test, feature = "test-util"))]
+use std::collections::HashMap;
+use std::env;
+use std::io;
+use std::path::Path;
+#[cfg(any(test, feature = "test-util"))]
+use std::path::PathBuf;
+use url::Url;
+
+/// Provides the contents of imported files, urls and environment variables.
+///
+/// Resolution uses [`DefaultFetcher`] unless told otherwise. Implement this trait to serve
+/// imports from somewhere else, for example from memory in tests or on platforms without
+/// filesystem or network access.
+///
+/// [`DefaultFetcher`]: struct.DefaultFetcher.html
+pub trait Fetcher {
+    /// Read the contents of a local file.
+    fn read_file(&self, path: &Path) -> io::Result<String>;
+    /// Download the contents of a remote file, sending the given HTTP headers.
+    fn fetch_url(
+        &self,
+        url: &Url,
+        headers: &[(String, String)],
+    ) -> io::Result<String>;
+    /// Read an environment variable. Returns `None` if the variable is not set.
+    fn read_env(&self, name: &str) -> Option<String>;
+}
+
+impl std::fmt::Debug for dyn Fetcher + '_ {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("Fetcher")
+    }
+}
+
+/// Fetches imports from the filesystem, the network and the process environment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultFetcher;
+
+impl Fetcher for DefaultFetcher {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fetch_url(
+        &self,
+        url: &Url,
+        headers: &[(String, String)],
+    ) -> io::Result<String> {
+        let to_io_error = |e| io::Error::new(io::ErrorKind::Other, e);
+        let mut request = reqwest::blocking::Client::new().get(url.clone());
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .map_err(to_io_error)
+    }
+    #[cfg(target_arch = "wasm32")]
+    fn fetch_url(
+        &self,
+        _url: &Url,
+        _headers: &[(String, String)],
+    ) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Remote imports are not supported on wasm yet",
+        ))
+    }
+
+    fn read_env(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+}
+
+/// Serves imports from memory, for tests. Files are looked up by their full path, urls by their
+/// full text. Anything not added is not found.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryFetcher {
+    files: HashMap<PathBuf, String>,
+    urls: HashMap<String, String>,
+    env: HashMap<String, String>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl InMemoryFetcher {
+    pub fn new() -> Self {
+        InMemoryFetcher::default()
+    }
+    /// Serve `text` as the contents of the file at `path`.
+    pub fn file(mut self, path: impl Into<PathBuf>, text: &str) -> Self {
+        self.files.insert(path.into(), text.to_owned());
+        self
+    }
+    /// Serve `text` as the contents of `url`.
+    pub fn url(mut self, url: &str, text: &str) -> Self {
+        self.urls.insert(url.to_owned(), text.to_owned());
+        self
+    }
+    /// Set the environment variable `name` to `value`.
+    pub fn env(mut self, name: &str, value: &str) -> Self {
+        self.env.insert(name.to_owned(), value.to_owned());
+        self
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Fetcher for InMemoryFetcher {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "file not found")
+        })
+    }
+    fn fetch_url(
+        &self,
+        url: &Url,
+        _headers: &[(String, String)],
+    ) -> io::Result<String> {
+        self.urls.get(url.as_str()).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "url not found")
+        })
+    }
+    fn read_env(&self, name: &str) -> Option<String> {
+        self.env.get(name).cloned()
+    }
+}
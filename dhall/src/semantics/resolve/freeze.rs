@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use crate::error::Error;
+use crate::operations::{BinOp, OpKind};
+use crate::semantics::parse::parse_str_at;
+use crate::semantics::resolve::{resolve_one_import, traverse_resolve_expr};
+use crate::semantics::{
+    Cache, DefaultFetcher, Fetcher, ImportEnv, ImportLocation, ImportPolicy,
+    NameEnv,
+};
+use crate::syntax::{
+    import_hash_range, Expr, ExprKind, Hash, Import, ImportMode, ImportTarget,
+    Span,
+};
+use crate::Parsed;
+
+/// Controls which imports `freeze` adds integrity checks to.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FreezeOptions {
+    /// Whether to also freeze local and environment imports. By default only remote imports are
+    /// frozen.
+    pub all_imports: bool,
+    /// Whether to store the frozen expressions in the cache directory.
+    pub cache: bool,
+}
+
+struct FreezeEnv<'a> {
+    env: ImportEnv,
+    cache: Cache,
+    fetcher: &'a dyn Fetcher,
+    policy: ImportPolicy,
+    root: ImportLocation,
+    options: FreezeOptions,
+    /// The byte range in the source of each frozen import, with its new hash.
+    hashes: Vec<(usize, usize, Hash)>,
+}
+
+enum FreezeError {
+    /// An import failed to resolve. This is what a `?` falls back from.
+    Resolve(Error),
+    /// Anything else, e.g. failing to hash the resolved expression.
+    Other(Error),
+}
+
+impl<'a> FreezeEnv<'a> {
+    fn new(
+        root: ImportLocation,
+        fetcher: &'a dyn Fetcher,
+        options: FreezeOptions,
+    ) -> Self {
+        FreezeEnv {
+            env: ImportEnv::new(),
+            cache: Cache::new(),
+            fetcher,
+            policy: ImportPolicy::new(),
+            root,
+            options,
+            hashes: Vec::new(),
+        }
+    }
+}
+
+/// Add a `sha256:` hash to the imports of the expression, replacing any existing hash.
+/// `as Location` and `missing` imports are left alone, as are alternatives of a `?` that fail to
+/// resolve.
+pub fn freeze(
+    parsed: Parsed,
+    fetcher: &dyn Fetcher,
+    options: FreezeOptions,
+) -> Result<Expr, Error> {
+    let Parsed(expr, root) = parsed;
+    let mut env = FreezeEnv::new(root, fetcher, options);
+    Ok(freeze_expr(&mut env, &expr)?)
+}
+
+/// Like `freeze`, but returns the source text with only the hashes of the imports changed, so
+/// that comments and layout are kept.
+pub fn freeze_text(
+    text: &str,
+    location: ImportLocation,
+    fetcher: &dyn Fetcher,
+    options: FreezeOptions,
+) -> Result<String, Error> {
+    let Parsed(expr, root) = parse_str_at(text, location)?;
+    let mut env = FreezeEnv::new(root, fetcher, options);
+    freeze_expr(&mut env, &expr)?;
+
+    // Desugaring can duplicate an import, e.g. with `with`.
+    let mut hashes = env.hashes;
+    hashes.sort_by_key(|(start, _, _)| *start);
+    hashes.dedup_by_key(|(start, _, _)| *start);
+    let mut frozen = text.to_owned();
+    for (start, end, hash) in hashes.into_iter().rev() {
+        let (hash_start, hash_end) = import_hash_range(&text[start..end])?;
+        let hash = if hash_start == hash_end {
+            format!(" {}", hash)
+        } else {
+            hash.to_string()
+        };
+        frozen.replace_range(start + hash_start..start + hash_end, &hash);
+    }
+    Ok(frozen)
+}
+
+/// Freeze the imports of the given file and write the result back to it.
+pub fn freeze_file(path: &Path, options: FreezeOptions) -> Result<(), Error> {
+    let text = std::fs::read_to_string(path)?;
+    let location = ImportLocation::local(path);
+    let frozen = freeze_text(&text, location, &DefaultFetcher, options)?;
+    if frozen != text {
+        std::fs::write(path, frozen)?;
+    }
+    Ok(())
+}
+
+fn freeze_expr(
+    env: &mut FreezeEnv<'_>,
+    expr: &Expr,
+) -> Result<Expr, FreezeError> {
+    let kind = match expr.kind() {
+        ExprKind::Import(import) => {
+            ExprKind::Import(freeze_import(env, import, expr)?)
+        }
+        ExprKind::Op(OpKind::BinOp(BinOp::ImportAlt, l, r)) => {
+            // As in resolution, the expression only fails if both alternatives fail to resolve.
+            let frozen_l = freeze_alternative(env, l)?;
+            let frozen_r = match freeze_alternative(env, r)? {
+                Err(e) if frozen_l.is_err() => {
+                    return Err(FreezeError::Resolve(e))
+                }
+                frozen_r => frozen_r.unwrap_or_else(|_| r.clone()),
+            };
+            let frozen_l = frozen_l.unwrap_or_else(|_| l.clone());
+            ExprKind::Op(OpKind::BinOp(BinOp::ImportAlt, frozen_l, frozen_r))
+        }
+        kind => kind.traverse_ref(|e| freeze_expr(env, e))?,
+    };
+    Ok(Expr::new(kind, expr.span()))
+}
+
+/// Freeze one side of a `?`. Failing to resolve is not an error there, since `?` recovers from
+/// it, so it is returned separately from other errors.
+fn freeze_alternative(
+    env: &mut FreezeEnv<'_>,
+    expr: &Expr,
+) -> Result<Result<Expr, Error>, FreezeError> {
+    let frozen_count = env.hashes.len();
+    match freeze_expr(env, expr) {
+        Ok(expr) => Ok(Ok(expr)),
+        Err(FreezeError::Resolve(e)) => {
+            env.hashes.truncate(frozen_count);
+            Ok(Err(e))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn freeze_import(
+    env: &mut FreezeEnv<'_>,
+    import: &Import<Expr>,
+    expr: &Expr,
+) -> Result<Import<Expr>, FreezeError> {
+    let should_freeze = import.mode != ImportMode::Location
+        && match &import.location {
+            ImportTarget::Remote(_) => true,
+            ImportTarget::Local(..) | ImportTarget::Env(_) => {
+                env.options.all_imports
+            }
+            ImportTarget::Missing => false,
+        };
+    if !should_freeze {
+        return Ok(import.clone());
+    }
+
+    let FreezeEnv {
+        env: import_env,
+        cache,
+        fetcher,
+        policy,
+        root,
+        ..
+    } = env;
+    // Resolve the import without its hash, so that we compute the hash of the current contents.
+    let mut unfrozen = import
+        .traverse_ref(|headers| {
+            traverse_resolve_expr(
+                &mut NameEnv::new(),
+                headers,
+                &mut |import, span| {
+                    resolve_one_import(
+                        import_env, cache, *fetcher, policy, &import, root,
+                        span,
+                    )
+                },
+            )
+        })
+        .map_err(FreezeError::Resolve)?;
+    unfrozen.hash = None;
+    let (hir, _) = resolve_one_import(
+        import_env,
+        cache,
+        *fetcher,
+        policy,
+        &unfrozen,
+        root,
+        expr.span(),
+    )
+    .map_err(FreezeError::Resolve)?;
+    let hash = Hash::SHA256(hir.to_expr_alpha().hash()?.into_vec());
+
+    if env.options.cache {
+        let mut frozen = unfrozen;
+        frozen.hash = Some(hash.clone());
+        env.cache.save_expr(&frozen, &hir.to_expr_noopts());
+    }
+    if let Span::Parsed(span) = expr.span() {
+        env.hashes.push((span.start(), span.end(), hash.clone()));
+    }
+    let mut import = import.clone();
+    import.hash = Some(hash);
+    Ok(import)
+}
+
+impl From<Error> for FreezeError {
+    fn from(e: Error) -> Self {
+        FreezeError::Other(e)
+    }
+}
+
+impl From<FreezeError> for Error {
+    fn from(e: FreezeError) -> Self {
+        match e {
+            FreezeError::Resolve(e) | FreezeError::Other(e) => e,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::parse::parse_str_at;
+    use crate::semantics::InMemoryFetcher;
+
+    fn fetcher() -> InMemoryFetcher {
+        InMemoryFetcher::new()
+            .file("/dir/one.dhall", "0 + 1")
+            .url("https://example.com/one.dhall", "1")
+    }
+
+    /// Relative imports are resolved from `/dir`.
+    fn root() -> ImportLocation {
+        ImportLocation::Local("/dir/main.dhall".into())
+    }
+
+    fn parse(s: &str) -> Parsed {
+        parse_str_at(s, root()).unwrap()
+    }
+
+    const ONE_HASH: &str =
+        "sha256:d60d8415e36e86dae7f42933d3b0c4fe3ca238f057fba206c7e9fbf5d784fe15";
+
+    fn freeze_str(s: &str, all_imports: bool) -> String {
+        let options = FreezeOptions {
+            all_imports,
+            cache: false,
+        };
+        freeze(parse(s), &fetcher(), options).unwrap().to_string()
+    }
+
+    #[test]
+    fn freeze_should_hash_remote_imports() {
+        assert_eq!(
+            freeze_str("[https://example.com/one.dhall, ./one.dhall]", false),
+            format!(
+                "[https://example.com/one.dhall {}, ./\"one.dhall\"]",
+                ONE_HASH
+            )
+        );
+        // Existing hashes are replaced.
+        assert_eq!(
+            freeze_str(
+                &format!(
+                    "https://example.com/one.dhall sha256:{}",
+                    "0".repeat(64)
+                ),
+                false
+            ),
+            format!("https://example.com/one.dhall {}", ONE_HASH)
+        );
+    }
+
+    #[test]
+    fn freeze_should_hash_all_imports_when_asked() {
+        assert_eq!(
+            freeze_str(
+                "{ x = ./one.dhall, y = ./one.dhall as Location }",
+                true
+            ),
+            format!(
+                "{{ x = ./\"one.dhall\" {}, y = ./\"one.dhall\" as Location }}",
+                ONE_HASH
+            )
+        );
+        assert_eq!(
+            freeze_str("missing ? ./nope.dhall ? ./one.dhall", true),
+            format!(
+                "missing ? ./\"nope.dhall\" ? ./\"one.dhall\" {}",
+                ONE_HASH
+            )
+        );
+        assert!(freeze(
+            parse("./nope.dhall"),
+            &fetcher(),
+            FreezeOptions {
+                all_imports: true,
+                cache: false
+            }
+        )
+        .is_err());
+        // Failing to resolve both alternatives is an error, as in resolution.
+        assert!(freeze(
+            parse("./nope.dhall ? https://example.com/nope.dhall"),
+            &fetcher(),
+            FreezeOptions {
+                all_imports: true,
+                cache: false
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn freeze_text_should_keep_comments_and_layout() {
+        let text = format!(
+            "-- Header\n\
+             let one =\n      https://example.com/one.dhall\n        sha256:{}\n\n\
+             {{- frozen -}}\n\
+             in  [ one, https://example.com/one.dhall  -- two\n    , ./one.dhall\n    ]\n",
+            "0".repeat(64)
+        );
+        let expected = format!(
+            "-- Header\n\
+             let one =\n      https://example.com/one.dhall\n        {0}\n\n\
+             {{- frozen -}}\n\
+             in  [ one, https://example.com/one.dhall {0}  -- two\n    , ./one.dhall\n    ]\n",
+            ONE_HASH
+        );
+        let options = FreezeOptions {
+            all_imports: false,
+            cache: false,
+        };
+        assert_eq!(
+            freeze_text(&text, root(), &fetcher(), options).unwrap(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use crate::semantics::ImportLocation;
+use crate::syntax::{Hash, ImportMode};
+
+/// An import from one location to another, as found during resolution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEdge {
+    /// The location of the expression containing the import.
+    pub parent: ImportLocation,
+    /// The location the import points to.
+    pub child: ImportLocation,
+    pub mode: ImportMode,
+    pub hash: Option<Hash>,
+    /// Whether the imported expression was read from the on-disk cache instead of being fetched.
+    /// When that is the case, the imports of `child` are not known: cached expressions have
+    /// their imports already resolved, so the graph has no edges from `child`.
+    pub from_cache: bool,
+    /// Whether the import failed to resolve, e.g. on the failing side of a `?`. The imports that
+    /// `child` made before failing are recorded too.
+    pub failed: bool,
+}
+
+/// The imports pulled in while resolving an expression, transitively.
+///
+/// Imports that failed to resolve are recorded with `failed` set, except for those whose
+/// location could not be computed, e.g. because it is forbidden by the import policy. The imports
+/// of expressions read from the on-disk cache are not known; see `ImportEdge::from_cache`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportGraph {
+    root: ImportLocation,
+    edges: Vec<ImportEdge>,
+}
+
+impl ImportGraph {
+    pub(crate) fn new(root: ImportLocation, edges: Vec<ImportEdge>) -> Self {
+        ImportGraph { root, edges }
+    }
+
+    /// The location of the expression that was resolved.
+    pub fn root(&self) -> &ImportLocation {
+        &self.root
+    }
+    /// All the imports, in the order they were resolved.
+    pub fn edges(&self) -> &[ImportEdge] {
+        &self.edges
+    }
+    /// The locations that were imported successfully, directly or not, without duplicates.
+    pub fn dependencies(&self) -> Vec<&ImportLocation> {
+        let mut deps: Vec<&ImportLocation> = Vec::new();
+        for edge in &self.edges {
+            if !edge.failed && !deps.contains(&&edge.child) {
+                deps.push(&edge.child);
+            }
+        }
+        deps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::parse::parse_str;
+    use crate::semantics::InMemoryFetcher;
+    use std::path::PathBuf;
+
+    fn fetcher() -> InMemoryFetcher {
+        InMemoryFetcher::new()
+            .file("/dir/a.dhall", "./b.dhall + ./b.dhall")
+            .file("/dir/b.dhall", "1")
+            .env("FOO", "FOO")
+    }
+
+    #[test]
+    fn import_graph_should_record_transitive_imports() {
+        let resolved = parse_str(
+            "{ x = /dir/a.dhall, y = env:FOO as Text, z = /dir/missing.dhall ? 2 }",
+        )
+        .unwrap()
+        .resolve_with_fetcher(&fetcher())
+        .unwrap();
+        let graph = resolved.import_graph();
+        assert_eq!(graph.root(), &ImportLocation::Missing);
+
+        let a = ImportLocation::Local(PathBuf::from("/dir/a.dhall"));
+        let b = ImportLocation::Local(PathBuf::from("/dir/b.dhall"));
+        let env = ImportLocation::Env("FOO".to_string());
+        let edges: Vec<_> = graph
+            .edges()
+            .iter()
+            .map(|e| (&e.parent, &e.child, e.mode, e.failed))
+            .collect();
+        let missing =
+            ImportLocation::Local(PathBuf::from("/dir/missing.dhall"));
+        assert_eq!(
+            edges,
+            vec![
+                (&a, &b, ImportMode::Code, false),
+                (&a, &b, ImportMode::Code, false),
+                (&ImportLocation::Missing, &a, ImportMode::Code, false),
+                (&ImportLocation::Missing, &env, ImportMode::RawText, false),
+                (&ImportLocation::Missing, &missing, ImportMode::Code, true),
+            ]
+        );
+        assert!(graph.edges().iter().all(|e| !e.from_cache));
+        assert_eq!(graph.dependencies(), vec![&b, &a, &env]);
+    }
+}
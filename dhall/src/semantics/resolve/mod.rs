@@ -1,8 +1,18 @@
 pub mod cache;
 pub mod env;
+pub mod fetch;
+pub mod freeze;
+pub mod graph;
 pub mod hir;
+pub mod policy;
 pub mod resolve;
+pub mod session;
 pub use cache::*;
 pub use env::*;
+pub use fetch::*;
+pub use freeze::*;
+pub use graph::*;
 pub use hir::*;
+pub use policy::*;
 pub use resolve::*;
+pub use session::*;
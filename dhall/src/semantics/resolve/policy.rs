@@ -0,0 +1,323 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{Error, ImportError};
+use crate::semantics::ImportLocation;
+
+/// Restricts which locations imports are allowed to read from.
+///
+/// The default policy allows everything. Each kind of import can then be restricted to an
+/// allowlist:
+///
+/// ```
+/// use dhall::semantics::ImportPolicy;
+///
+/// let policy = ImportPolicy::new()
+///     .allow_local_root("/etc/myapp")
+///     .allow_host("prelude.dhall-lang.org")
+///     .allow_scheme("https")
+///     .deny_env_vars()
+///     .require_remote_hash(true);
+/// ```
+///
+/// The policy is checked before anything gets read. It does not apply to `as Location` imports,
+/// since those do not read anything.
+#[derive(Debug, Clone, Default)]
+pub struct ImportPolicy {
+    /// `None` means all locations of that kind are allowed.
+    /// A root that goes above the filesystem root is kept as an `Err`, and reported when checking
+    /// local imports.
+    local_roots: Option<Vec<Result<PathBuf, PathBuf>>>,
+    hosts: Option<Vec<String>>,
+    schemes: Option<Vec<String>>,
+    env_vars: Option<Vec<String>>,
+    require_remote_hash: bool,
+}
+
+impl ImportPolicy {
+    /// A policy that allows all imports.
+    pub fn new() -> Self {
+        ImportPolicy::default()
+    }
+
+    /// Allow local imports of files under `root`. Once a root has been given, local imports
+    /// outside of the allowed roots are rejected, including ones that escape a root through `..`
+    /// or a symlink.
+    ///
+    /// If `root` goes above the root of the filesystem, e.g. `/../etc`, all local imports are
+    /// rejected.
+    pub fn allow_local_root(mut self, root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        let resolved = resolve_path(root).ok_or_else(|| root.to_owned());
+        self.local_roots.get_or_insert_with(Vec::new).push(resolved);
+        self
+    }
+    /// Forbid all local imports, except from roots explicitly allowed.
+    pub fn deny_local(mut self) -> Self {
+        self.local_roots.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Allow remote imports from the given host. Once a host has been given, remote imports from
+    /// other hosts are rejected.
+    pub fn allow_host(mut self, host: impl Into<String>) -> Self {
+        self.hosts.get_or_insert_with(Vec::new).push(host.into());
+        self
+    }
+    /// Allow remote imports using the given url scheme, e.g. `"https"`. Once a scheme has been
+    /// given, remote imports using other schemes are rejected.
+    pub fn allow_scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.schemes
+            .get_or_insert_with(Vec::new)
+            .push(scheme.into());
+        self
+    }
+    /// Forbid all remote imports, including from the hosts allowed so far. Hosts allowed
+    /// afterwards with `allow_host` are allowed again.
+    pub fn deny_remote(mut self) -> Self {
+        self.hosts = Some(Vec::new());
+        self
+    }
+    /// Whether remote imports must be protected by a `sha256:` hash.
+    pub fn require_remote_hash(mut self, require: bool) -> Self {
+        self.require_remote_hash = require;
+        self
+    }
+
+    /// Allow reading the given environment variable. Once a variable has been given, reading
+    /// other environment variables is rejected.
+    pub fn allow_env_var(mut self, name: impl Into<String>) -> Self {
+        self.env_vars.get_or_insert_with(Vec::new).push(name.into());
+        self
+    }
+    /// Forbid reading environment variables, except the ones explicitly allowed.
+    pub fn deny_env_vars(mut self) -> Self {
+        self.env_vars.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Check whether reading from `location` is allowed. `hashed` indicates whether the import
+    /// carries an integrity check.
+    pub(crate) fn check(
+        &self,
+        location: &ImportLocation,
+        hashed: bool,
+    ) -> Result<(), Error> {
+        let forbid = |reason: String| -> Result<(), Error> {
+            Err(ImportError::Forbidden(location.clone(), reason).into())
+        };
+        match location {
+            ImportLocation::Local(path) => {
+                if let Some(roots) = &self.local_roots {
+                    if let Some(Err(root)) = roots.iter().find(|r| r.is_err()) {
+                        return forbid(format!(
+                            "the allowed directory `{}` goes above the root of the filesystem",
+                            root.display()
+                        ));
+                    }
+                    let allowed = match resolve_path(path) {
+                        Some(path) => roots
+                            .iter()
+                            .filter_map(|root| root.as_ref().ok())
+                            .any(|root| path.starts_with(root)),
+                        None => false,
+                    };
+                    if !allowed {
+                        return forbid(format!(
+                            "`{}` is not inside an allowed directory",
+                            path.display()
+                        ));
+                    }
+                }
+            }
+            ImportLocation::Remote(url, _) => {
+                if let Some(schemes) = &self.schemes {
+                    if !schemes.iter().any(|s| s == url.scheme()) {
+                        return forbid(format!(
+                            "the `{}` scheme is not allowed",
+                            url.scheme()
+                        ));
+                    }
+                }
+                if let Some(hosts) = &self.hosts {
+                    let host = url.host_str().unwrap_or("");
+                    if !hosts.iter().any(|h| h == host) {
+                        return forbid(format!(
+                            "the host `{}` is not allowed",
+                            host
+                        ));
+                    }
+                }
+                if self.require_remote_hash && !hashed {
+                    return forbid(
+                        "remote imports must be protected by a hash".into(),
+                    );
+                }
+            }
+            ImportLocation::Env(name) => {
+                if let Some(vars) = &self.env_vars {
+                    if !vars.iter().any(|v| v == name) {
+                        return forbid(format!(
+                            "the environment variable `{}` is not allowed",
+                            name
+                        ));
+                    }
+                }
+            }
+            ImportLocation::Missing => {}
+        }
+        Ok(())
+    }
+}
+
+/// Make the path absolute and resolve `.`, `..` and symlinks the way the OS would when opening
+/// it: each `..` applies to the real location of what precedes it. Components that don't exist
+/// are kept as written. Returns `None` if the path goes above the filesystem root.
+fn resolve_path(path: &Path) -> Option<PathBuf> {
+    let path = match std::env::current_dir() {
+        Ok(dir) => dir.join(path),
+        Err(_) => path.to_owned(),
+    };
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            c => {
+                resolved.push(c);
+                if let Ok(real) = std::fs::canonicalize(&resolved) {
+                    resolved = real;
+                }
+            }
+        }
+    }
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::distributions::Alphanumeric;
+    use rand::Rng;
+    use std::env::temp_dir;
+    use url::Url;
+
+    fn remote(url: &str) -> ImportLocation {
+        ImportLocation::Remote(Url::parse(url).unwrap(), Vec::new())
+    }
+
+    #[test]
+    fn default_policy_should_allow_everything() {
+        let policy = ImportPolicy::new();
+        let local = ImportLocation::Local("/etc/passwd".into());
+        assert!(policy.check(&local, false).is_ok());
+        assert!(policy.check(&remote("http://x.org/a"), false).is_ok());
+        let env = ImportLocation::Env("HOME".into());
+        assert!(policy.check(&env, false).is_ok());
+    }
+
+    #[test]
+    fn local_roots_should_reject_parent_escapes() {
+        let policy = ImportPolicy::new().allow_local_root("/config");
+        let inside = ImportLocation::Local("/config/a/../b.dhall".into());
+        assert!(policy.check(&inside, false).is_ok());
+        let outside = ImportLocation::Local("/config/../etc/passwd".into());
+        assert!(policy.check(&outside, false).is_err());
+        let above_root = ImportLocation::Local("/../config/b.dhall".into());
+        assert!(policy.check(&above_root, false).is_err());
+        let prefix = ImportLocation::Local("/config2/b.dhall".into());
+        assert!(policy.check(&prefix, false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn local_roots_should_reject_symlink_escapes() {
+        let test_id = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(36)
+            .collect::<String>();
+        let dir = temp_dir().join(test_id);
+        let root = dir.join("root");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(dir.join("secret.dhall"), "1").unwrap();
+        std::fs::write(root.join("ok.dhall"), "1").unwrap();
+        std::os::unix::fs::symlink(
+            dir.join("secret.dhall"),
+            root.join("link.dhall"),
+        )
+        .unwrap();
+
+        let policy = ImportPolicy::new().allow_local_root(&root);
+        let ok = ImportLocation::Local(root.join("ok.dhall"));
+        assert!(policy.check(&ok, false).is_ok());
+        let link = ImportLocation::Local(root.join("link.dhall"));
+        assert!(policy.check(&link, false).is_err());
+
+        // `root/dir_link/..` is the parent of the directory `dir_link` points to, not `root`.
+        std::fs::create_dir(dir.join("other")).unwrap();
+        std::os::unix::fs::symlink(dir.join("other"), root.join("dir_link"))
+            .unwrap();
+        let secret = root.join("dir_link").join("..").join("secret.dhall");
+        let secret = ImportLocation::Local(secret);
+        assert!(policy.check(&secret, false).is_err());
+        let missing = root.join("dir_link").join("..").join("missing.dhall");
+        let missing = ImportLocation::Local(missing);
+        assert!(policy.check(&missing, false).is_err());
+        let inside = root.join("dir_link").join("..").join("root/ok.dhall");
+        let inside = ImportLocation::Local(inside);
+        assert!(policy.check(&inside, false).is_ok());
+
+        std::fs::remove_dir_all(dir.as_path()).unwrap();
+    }
+
+    #[test]
+    fn remote_restrictions_should_apply() {
+        let policy = ImportPolicy::new()
+            .allow_host("prelude.dhall-lang.org")
+            .allow_scheme("https")
+            .require_remote_hash(true);
+        let ok = remote("https://prelude.dhall-lang.org/package.dhall");
+        assert!(policy.check(&ok, true).is_ok());
+        assert!(policy.check(&ok, false).is_err());
+        let http = remote("http://prelude.dhall-lang.org/package.dhall");
+        assert!(policy.check(&http, true).is_err());
+        let other_host = remote("https://example.com/package.dhall");
+        assert!(policy.check(&other_host, true).is_err());
+
+        let policy = ImportPolicy::new().deny_remote();
+        assert!(policy.check(&ok, true).is_err());
+        let policy = ImportPolicy::new()
+            .allow_host("prelude.dhall-lang.org")
+            .deny_remote();
+        assert!(policy.check(&ok, true).is_err());
+        let local = ImportLocation::Local("/etc/passwd".into());
+        assert!(policy.check(&local, false).is_ok());
+    }
+
+    #[test]
+    fn invalid_local_roots_should_reject_local_imports() {
+        let policy = ImportPolicy::new()
+            .allow_local_root("/config")
+            .allow_local_root("/../config");
+        let local = ImportLocation::Local("/config/b.dhall".into());
+        let err = policy.check(&local, false).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`/../config` goes above the root of the filesystem"));
+        assert!(policy.check(&remote("https://x.org/a"), false).is_ok());
+    }
+
+    #[test]
+    fn env_var_restrictions_should_apply() {
+        let policy = ImportPolicy::new().allow_env_var("CONFIG");
+        let ok = ImportLocation::Env("CONFIG".into());
+        assert!(policy.check(&ok, false).is_ok());
+        let secret = ImportLocation::Env("AWS_SECRET_ACCESS_KEY".into());
+        assert!(policy.check(&secret, false).is_err());
+        let policy = ImportPolicy::new().deny_env_vars();
+        assert!(policy.check(&ok, false).is_err());
+    }
+}
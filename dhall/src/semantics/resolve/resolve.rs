@@ -1,24 +1,30 @@
 use itertools::Itertools;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
-use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use url::Url;
 
 use crate::builtins::Builtin;
-use crate::error::ErrorBuilder;
-use crate::error::{Error, ImportError};
+use crate::error::{Error, ImportError, TypeMessage};
 use crate::operations::{BinOp, OpKind};
-use crate::semantics::{mkerr, Cache, Hir, HirKind, ImportEnv, NameEnv, Type};
+use crate::semantics::{
+    mkerr, typecheck, Cache, DefaultFetcher, Fetcher, Hir, HirKind, ImportEdge,
+    ImportEnv, ImportGraph, ImportPolicy, NameEnv, NirKind, Session, TyEnv,
+    Type,
+};
 use crate::syntax;
+use crate::syntax::parse_expr_at;
 use crate::syntax::{
     Expr, ExprKind, FilePath, FilePrefix, Hash, ImportMode, ImportTarget, Span,
     UnspannedExpr, URL,
 };
-use crate::{Parsed, Resolved};
+use crate::{Parsed, Resolved, Typed};
+
+/// An import whose headers expression has been resolved.
+pub type Import = syntax::Import<Hir>;
 
-// TODO: evaluate import headers
-pub type Import = syntax::Import<()>;
+/// HTTP headers to send with a remote import, as given by a `using` clause.
+pub type Headers = Vec<(String, String)>;
 
 /// Owned Hir with a type. Different from Tir because the Hir is owned.
 pub type TypedHir = (Hir, Type);
@@ -28,25 +34,54 @@ pub type TypedHir = (Hir, Type);
 pub enum ImportLocation {
     /// Local file
     Local(PathBuf),
-    /// Remote file
-    Remote(Url),
+    /// Remote file, with the headers to send when fetching it
+    Remote(Url, Headers),
     /// Environment variable
     Env(String),
     /// Data without a location
     Missing,
 }
 
+impl std::fmt::Display for ImportLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImportLocation::Local(path) => write!(f, "{}", path.display()),
+            ImportLocation::Remote(url, _) => write!(f, "{}", url),
+            ImportLocation::Env(name) => write!(f, "env:{}", name),
+            ImportLocation::Missing => write!(f, "missing"),
+        }
+    }
+}
+
 impl ImportLocation {
-    /// Given an import pointing to `target` found in the current location, compute the next
-    /// location, or error if not allowed.
-    /// `sanity_check` indicates whether to check if that location is allowed to be referenced,
-    /// for example to prevent a remote file from reading an environment variable.
-    fn chain(
+    /// The location of a local file. The `.` and `..` components of the path are resolved the
+    /// same way as in relative imports, so that a file is always spelled the same way.
+    pub fn local(path: &Path) -> ImportLocation {
+        let path: Vec<String> = path
+            .components()
+            .map(|component| {
+                component.as_os_str().to_string_lossy().into_owned()
+            })
+            .collect();
+        let path = (FilePath { file_path: path }).canonicalize().file_path;
+        let path = Some(".".to_string()).into_iter().chain(path).collect();
+        ImportLocation::Local(path)
+    }
+
+    /// Given an import found in the current location, compute the location it points to, or
+    /// error if not allowed.
+    /// Unless this is an `as Location` import, we check whether that location is allowed to be
+    /// referenced, both by the standard (for example to prevent a remote file from reading an
+    /// environment variable) and by `policy`.
+    /// `headers` are the evaluated headers of the import, if any.
+    pub(crate) fn chain<SE>(
         &self,
-        target: &ImportTarget<()>,
-        sanity_check: bool,
+        import: &syntax::Import<SE>,
+        headers: Option<Headers>,
+        policy: &ImportPolicy,
     ) -> Result<ImportLocation, Error> {
-        Ok(match target {
+        let sanity_check = import.mode != ImportMode::Location;
+        let location = match &import.location {
             ImportTarget::Local(prefix, path) => {
                 self.chain_local(*prefix, path)?
             }
@@ -63,7 +98,7 @@ impl ImportLocation {
                 ))?;
                 url.set_path(&remote.path.file_path.iter().join("/"));
                 url.set_query(remote.query.as_ref().map(String::as_ref));
-                ImportLocation::Remote(url)
+                ImportLocation::Remote(url, headers.unwrap_or_default())
             }
             ImportTarget::Env(var_name) => {
                 if sanity_check {
@@ -74,7 +109,11 @@ impl ImportLocation {
                 ImportLocation::Env(var_name.clone())
             }
             ImportTarget::Missing => ImportLocation::Missing,
-        })
+        };
+        if sanity_check {
+            policy.check(&location, import.hash.is_some())?;
+        }
+        Ok(location)
     }
 
     fn chain_local(
@@ -125,7 +164,8 @@ impl ImportLocation {
                     Some(prefix.to_string()).into_iter().chain(path).collect();
                 ImportLocation::Local(path)
             }
-            ImportLocation::Remote(url) => {
+            // Relative imports inherit the headers of the importing file.
+            ImportLocation::Remote(url, headers) => {
                 let mut url = url.clone();
                 match prefix {
                     FilePrefix::Here => {}
@@ -136,34 +176,31 @@ impl ImportLocation {
                     FilePrefix::Home => panic!("error"),
                 }
                 url = url.join(&path.file_path.join("/"))?;
-                ImportLocation::Remote(url)
+                ImportLocation::Remote(url, headers.clone())
             }
         })
     }
 
-    fn fetch_dhall(self) -> Result<Parsed, Error> {
-        Ok(match self {
-            ImportLocation::Local(path) => Parsed::parse_file(&path)?,
-            ImportLocation::Remote(url) => Parsed::parse_remote(url)?,
-            ImportLocation::Env(var_name) => {
-                let val = match env::var(var_name) {
-                    Ok(val) => val,
-                    Err(_) => return Err(ImportError::MissingEnvVar.into()),
-                };
-                Parsed::parse_str(&val)?
-            }
-            ImportLocation::Missing => return Err(ImportError::Missing.into()),
-        })
+    fn fetch_dhall(self, fetcher: &dyn Fetcher) -> Result<Parsed, Error> {
+        let text = self.clone().fetch_text(fetcher)?;
+        let expr = parse_expr_at(&text, self.clone())?;
+        Ok(Parsed(expr, self))
     }
 
-    fn fetch_text(self) -> Result<String, Error> {
+    fn fetch_text(self, fetcher: &dyn Fetcher) -> Result<String, Error> {
         Ok(match self {
-            ImportLocation::Local(path) => std::fs::read_to_string(&path)?,
-            ImportLocation::Remote(url) => download_http_text(url)?,
-            ImportLocation::Env(var_name) => match env::var(var_name) {
-                Ok(val) => val,
-                Err(_) => return Err(ImportError::MissingEnvVar.into()),
-            },
+            ImportLocation::Local(path) => fetcher.read_file(&path)?,
+            ImportLocation::Remote(url, headers) => {
+                fetcher.fetch_url(&url, &headers)?
+            }
+            ImportLocation::Env(var_name) => {
+                match fetcher.read_env(&var_name) {
+                    Some(val) => val,
+                    None => {
+                        return Err(ImportError::MissingEnvVar(var_name).into())
+                    }
+                }
+            }
             ImportLocation::Missing => return Err(ImportError::Missing.into()),
         })
     }
@@ -173,7 +210,9 @@ impl ImportLocation {
             ImportLocation::Local(path) => {
                 ("Local", Some(path.to_string_lossy().into_owned()))
             }
-            ImportLocation::Remote(url) => ("Remote", Some(url.into_string())),
+            ImportLocation::Remote(url, _) => {
+                ("Remote", Some(url.into_string()))
+            }
             ImportLocation::Env(name) => ("Environment", Some(name)),
             ImportLocation::Missing => ("Missing", None),
         };
@@ -195,16 +234,6 @@ fn mkexpr(kind: UnspannedExpr) -> Expr {
     Expr::new(kind, Span::Artificial)
 }
 
-// TODO: error handling
-#[cfg(not(target_arch = "wasm32"))]
-pub(crate) fn download_http_text(url: Url) -> Result<String, Error> {
-    Ok(reqwest::blocking::get(url).unwrap().text().unwrap())
-}
-#[cfg(target_arch = "wasm32")]
-pub(crate) fn download_http_text(_url: Url) -> Result<String, Error> {
-    panic!("Remote imports are not supported on wasm yet")
-}
-
 fn make_aslocation_uniontype() -> Expr {
     let text_type = mkexpr(ExprKind::Builtin(Builtin::Text));
     let mut union = BTreeMap::default();
@@ -215,65 +244,154 @@ fn make_aslocation_uniontype() -> Expr {
     mkexpr(ExprKind::UnionType(union))
 }
 
-fn resolve_one_import(
+fn make_headers_type() -> Expr {
+    let text_type = mkexpr(ExprKind::Builtin(Builtin::Text));
+    let mut entry = BTreeMap::default();
+    entry.insert("mapKey".into(), text_type.clone());
+    entry.insert("mapValue".into(), text_type);
+    mkexpr(ExprKind::Op(OpKind::App(
+        mkexpr(ExprKind::Builtin(Builtin::List)),
+        mkexpr(ExprKind::RecordType(entry)),
+    )))
+}
+
+/// Typecheck and normalize the expression of a `using` clause.
+fn eval_headers(headers: &Hir) -> Result<Headers, Error> {
+    let expected_ty = skip_resolve_expr(&make_headers_type())?;
+    let expected_ty =
+        expected_ty.typecheck_noenv()?.eval_to_type(&TyEnv::new())?;
+    let ty = headers.typecheck_noenv()?.ty().clone();
+    if ty != expected_ty {
+        mkerr(
+            &TyEnv::new(),
+            headers.span(),
+            TypeMessage::InvalidImportHeaders {
+                expected: expected_ty,
+                actual: ty,
+            },
+        )?
+    }
+
+    let headers = headers.eval_closed_expr();
+    let entries = match headers.kind() {
+        NirKind::EmptyListLit(_) => return Ok(Vec::new()),
+        NirKind::NEListLit(entries) => entries,
+        _ => unreachable!("Headers should normalize to a list literal"),
+    };
+    Ok(entries
+        .iter()
+        .map(|entry| {
+            let field = |name: &str| match entry.kind() {
+                NirKind::RecordLit(kvs) => {
+                    match kvs.get(name).map(|x| x.kind()) {
+                        Some(NirKind::TextLit(t)) => t.as_text().expect(
+                            "Normal form should ensure the text is a string",
+                        ),
+                        _ => unreachable!("Internal type error"),
+                    }
+                }
+                _ => unreachable!("Internal type error"),
+            };
+            (field("mapKey"), field("mapValue"))
+        })
+        .collect())
+}
+
+pub(crate) fn resolve_one_import(
     env: &mut ImportEnv,
     cache: &Cache,
+    fetcher: &dyn Fetcher,
+    policy: &ImportPolicy,
     import: &Import,
     location: &ImportLocation,
     span: Span,
 ) -> Result<TypedHir, Error> {
+    let parent = location;
     let do_sanity_check = import.mode != ImportMode::Location;
-    let location = location.chain(&import.location, do_sanity_check)?;
-    env.handle_import(location.clone(), |env| match import.mode {
-        ImportMode::Code => {
-            let (hir, ty) = cache.caching_import(
-                import,
-                || location.fetch_dhall(),
-                |parsed| {
-                    let typed =
-                        resolve_with_env(env, cache, parsed)?.typecheck()?;
-                    let hir = typed.normalize().to_hir();
-                    Ok((hir, typed.ty))
-                },
-            )?;
-            match &import.hash {
-                Some(Hash::SHA256(hash)) => {
-                    let actual_hash = hir.to_expr_alpha().hash()?;
-                    if hash[..] != actual_hash[..] {
-                        mkerr(
-                            ErrorBuilder::new("hash mismatch")
-                                .span_err(span, "hash mismatch")
-                                .note(format!(
-                                    "Expected sha256:{}",
-                                    hex::encode(hash)
-                                ))
-                                .note(format!(
-                                    "Found    sha256:{}",
-                                    hex::encode(actual_hash)
-                                ))
-                                .format(),
-                        )?
+    let headers = match &import.location {
+        ImportTarget::Remote(URL {
+            headers: Some(headers),
+            ..
+        }) if do_sanity_check => Some(
+            eval_headers(headers)
+                .map_err(|e| e.in_import(parent, None, &span))?,
+        ),
+        _ => None,
+    };
+    let location = parent
+        .chain(import, headers, policy)
+        .map_err(|e| e.in_import(parent, None, &span))?;
+    // Whether the import was resolved now, as opposed to found in `env`.
+    let mut resolved_now = false;
+    let mut fetched = false;
+    let typed_hir = env.handle_import(location.clone(), |env| {
+        resolved_now = true;
+        match import.mode {
+            ImportMode::Code => {
+                let (hir, ty) = cache.caching_import(
+                    import,
+                    || {
+                        fetched = true;
+                        location.clone().fetch_dhall(fetcher)
+                    },
+                    |parsed| {
+                        let hir = resolve_with_env(
+                            env, cache, fetcher, policy, parsed,
+                        )?;
+                        let typed = Typed::from_tir(typecheck(&hir)?);
+                        let hir = typed.normalize().to_hir();
+                        Ok((hir, typed.ty))
+                    },
+                )?;
+                match &import.hash {
+                    Some(Hash::SHA256(hash)) => {
+                        let actual_hash = hir.to_expr_alpha().hash()?;
+                        if hash[..] != actual_hash[..] {
+                            return Err(ImportError::HashMismatch {
+                                expected: Hash::SHA256(hash.clone()),
+                                actual: Hash::SHA256(actual_hash.into_vec()),
+                            }
+                            .into());
+                        }
                     }
+                    None => {}
                 }
-                None => {}
+                Ok((hir, ty))
+            }
+            ImportMode::RawText => {
+                let text = location.clone().fetch_text(fetcher)?;
+                let hir = Hir::new(
+                    HirKind::Expr(ExprKind::TextLit(text.into())),
+                    Span::Artificial,
+                );
+                Ok((hir, Type::from_builtin(Builtin::Text)))
+            }
+            ImportMode::Location => {
+                let expr = location.clone().into_location();
+                let hir = skip_resolve_expr(&expr)?;
+                let ty = hir.typecheck_noenv()?.ty().clone();
+                Ok((hir, ty))
             }
-            Ok((hir, ty))
-        }
-        ImportMode::RawText => {
-            let text = location.fetch_text()?;
-            let hir = Hir::new(
-                HirKind::Expr(ExprKind::TextLit(text.into())),
-                Span::Artificial,
-            );
-            Ok((hir, Type::from_builtin(Builtin::Text)))
-        }
-        ImportMode::Location => {
-            let expr = location.into_location();
-            let hir = skip_resolve_expr(&expr)?;
-            let ty = hir.typecheck_noenv()?.ty().clone();
-            Ok((hir, ty))
         }
-    })
+    });
+
+    let failed = typed_hir.is_err();
+    let from_cache = if failed {
+        false
+    } else if resolved_now {
+        import.mode == ImportMode::Code && !fetched
+    } else {
+        env.served_from_cache(&location)
+    };
+    env.record_import(ImportEdge {
+        parent: parent.clone(),
+        child: location.clone(),
+        mode: import.mode,
+        hash: import.hash.clone(),
+        from_cache,
+        failed,
+    });
+    typed_hir.map_err(|e| e.in_import(parent, Some(&location), &span))
 }
 
 /// Desugar the first level of the expression.
@@ -307,7 +425,7 @@ fn desugar(expr: &Expr) -> Cow<'_, Expr> {
 
 /// Traverse the expression, handling import alternatives and passing
 /// found imports to the provided function. Also resolving names.
-fn traverse_resolve_expr(
+pub(crate) fn traverse_resolve_expr(
     name_env: &mut NameEnv,
     expr: &Expr,
     f: &mut impl FnMut(Import, Span) -> Result<TypedHir, Error>,
@@ -317,9 +435,9 @@ fn traverse_resolve_expr(
         ExprKind::Var(var) => match name_env.unlabel_var(&var) {
             Some(v) => Hir::new(HirKind::Var(v), expr.span()),
             None => mkerr(
-                ErrorBuilder::new(format!("unbound variable `{}`", var))
-                    .span_err(expr.span(), "not found in this scope")
-                    .format(),
+                &TyEnv::new(),
+                expr.span(),
+                TypeMessage::UnboundVariable(var.clone()),
             )?,
         },
         ExprKind::Op(OpKind::BinOp(BinOp::ImportAlt, l, r)) => {
@@ -334,6 +452,15 @@ fn traverse_resolve_expr(
                 }
             }
         }
+        ExprKind::Import(import) => {
+            // Headers can't refer to variables in scope, so they are resolved in an empty
+            // environment.
+            let import = import.traverse_ref(|headers| {
+                traverse_resolve_expr(&mut NameEnv::new(), headers, f)
+            })?;
+            let imported = f(import, expr.span())?;
+            Hir::new(HirKind::Import(imported.0, imported.1), expr.span())
+        }
         kind => {
             let kind = kind.traverse_ref_maybe_binder(|l, e| {
                 if let Some(l) = l {
@@ -345,16 +472,7 @@ fn traverse_resolve_expr(
                 }
                 Ok::<_, Error>(hir)
             })?;
-            let kind = match kind {
-                ExprKind::Import(import) => {
-                    // TODO: evaluate import headers
-                    let import = import.traverse_ref(|_| Ok::<_, Error>(()))?;
-                    let imported = f(import, expr.span())?;
-                    HirKind::Import(imported.0, imported.1)
-                }
-                kind => HirKind::Expr(kind),
-            };
-            Hir::new(kind, expr.span())
+            Hir::new(HirKind::Expr(kind), expr.span())
         }
     })
 }
@@ -362,34 +480,80 @@ fn traverse_resolve_expr(
 fn resolve_with_env(
     env: &mut ImportEnv,
     cache: &Cache,
+    fetcher: &dyn Fetcher,
+    policy: &ImportPolicy,
     parsed: Parsed,
-) -> Result<Resolved, Error> {
+) -> Result<Hir, Error> {
     let Parsed(expr, location) = parsed;
     let resolved = traverse_resolve_expr(
         &mut NameEnv::new(),
         &expr,
         &mut |import, span| {
-            resolve_one_import(env, cache, &import, &location, span)
+            resolve_one_import(
+                env, cache, fetcher, policy, &import, &location, span,
+            )
         },
     )?;
-    Ok(Resolved(resolved))
+    Ok(resolved)
 }
 
 pub fn resolve(parsed: Parsed) -> Result<Resolved, Error> {
-    let cache = Cache::new();
-    resolve_with_env(&mut ImportEnv::new(), &cache, parsed)
+    resolve_with_fetcher(parsed, &DefaultFetcher)
+}
+
+pub fn resolve_with_fetcher(
+    parsed: Parsed,
+    fetcher: &dyn Fetcher,
+) -> Result<Resolved, Error> {
+    resolve_with(parsed, fetcher, &ImportPolicy::new())
+}
+
+pub fn resolve_with(
+    parsed: Parsed,
+    fetcher: &dyn Fetcher,
+    policy: &ImportPolicy,
+) -> Result<Resolved, Error> {
+    resolve_with_cache(parsed, fetcher, policy, &Cache::new())
+}
+
+pub fn resolve_with_cache(
+    parsed: Parsed,
+    fetcher: &dyn Fetcher,
+    policy: &ImportPolicy,
+    cache: &Cache,
+) -> Result<Resolved, Error> {
+    let root = parsed.1.clone();
+    let mut env = ImportEnv::new();
+    let resolved = resolve_with_env(&mut env, cache, fetcher, policy, parsed)?;
+    let graph = ImportGraph::new(root, env.into_imports());
+    Ok(Resolved(resolved, graph))
+}
+
+/// Like `resolve_with`, but reuses the imports resolved by earlier calls with the same session.
+pub fn resolve_in(
+    parsed: Parsed,
+    session: &Session,
+    fetcher: &dyn Fetcher,
+    policy: &ImportPolicy,
+) -> Result<Resolved, Error> {
+    let root = parsed.1.clone();
+    let (resolved, edges) =
+        session.resolve(fetcher, policy, |env, cache, fetcher| {
+            resolve_with_env(env, cache, fetcher, policy, parsed)
+        });
+    Ok(Resolved(resolved?, ImportGraph::new(root, edges?)))
 }
 
 pub fn skip_resolve_expr(expr: &Expr) -> Result<Hir, Error> {
     traverse_resolve_expr(&mut NameEnv::new(), expr, &mut |import, _span| {
-        Err(ImportError::UnexpectedImport(import).into())
+        Err(ImportError::UnexpectedImport(import.map_ref(|_| ())).into())
     })
 }
 
 pub fn skip_resolve(parsed: Parsed) -> Result<Resolved, Error> {
-    let Parsed(expr, _) = parsed;
+    let Parsed(expr, root) = parsed;
     let resolved = skip_resolve_expr(&expr)?;
-    Ok(Resolved(resolved))
+    Ok(Resolved(resolved, ImportGraph::new(root, Vec::new())))
 }
 
 pub trait Canonicalize {
@@ -0,0 +1,347 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+use url::Url;
+
+use crate::error::Error;
+use crate::semantics::{
+    Cache, Fetcher, ImportCache, ImportEdge, ImportEnv, ImportLocation,
+    ImportPolicy,
+};
+use crate::syntax::ImportMode;
+
+/// Keeps the imports resolved by earlier calls, so that resolving many expressions that share
+/// imports (e.g. the Prelude) only reads, parses and typechecks each of them once.
+///
+/// Use it with `Parsed::resolve_in` or `serde_dhall::Deserializer::session`.
+///
+/// Before each use, local files and environment variables that were read by earlier calls are
+/// checked for changes: a file is considered unchanged if its modification time is the same, or
+/// else if its contents hash to the same value, and a file that could not be read is considered
+/// unchanged if it still can't. Changed imports are dropped, along with the imports that depend
+/// on them. Remote imports are assumed never to change.
+///
+/// Imports found in the session are checked against the `ImportPolicy` of each call, including the
+/// imports they make transitively.
+#[derive(Debug)]
+pub struct Session {
+    cache: Cache,
+    state: RefCell<SessionState>,
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    imports: ImportCache,
+    /// The imports made directly by each import in `imports`.
+    edges: HashMap<ImportLocation, Vec<ImportEdge>>,
+    /// What the local files and environment variables looked like when they were read.
+    stamps: HashMap<ImportLocation, Stamp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stamp {
+    File {
+        modified: Option<SystemTime>,
+        hash: Box<[u8]>,
+    },
+    /// A local file that could not be read.
+    MissingFile,
+    Env(Option<String>),
+}
+
+/// Records a `Stamp` for everything read through it.
+struct Recorder<'a> {
+    fetcher: &'a dyn Fetcher,
+    stamps: RefCell<Vec<(ImportLocation, Stamp)>>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn content_hash(text: &str) -> Box<[u8]> {
+    use sha2::Digest;
+    sha2::Sha256::digest(text.as_bytes()).to_vec().into()
+}
+
+impl Fetcher for Recorder<'_> {
+    fn read_file(&self, path: &Path) -> io::Result<String> {
+        // Get the time before reading, so that a concurrent change is detected next time.
+        let modified = modified(path);
+        let text = self.fetcher.read_file(path);
+        let stamp = match &text {
+            Ok(text) => Stamp::File {
+                modified,
+                hash: content_hash(text),
+            },
+            Err(_) => Stamp::MissingFile,
+        };
+        let location = ImportLocation::Local(path.to_owned());
+        self.stamps.borrow_mut().push((location, stamp));
+        text
+    }
+    fn fetch_url(
+        &self,
+        url: &Url,
+        headers: &[(String, String)],
+    ) -> io::Result<String> {
+        self.fetcher.fetch_url(url, headers)
+    }
+    fn read_env(&self, name: &str) -> Option<String> {
+        let value = self.fetcher.read_env(name);
+        let location = ImportLocation::Env(name.to_owned());
+        let stamp = Stamp::Env(value.clone());
+        self.stamps.borrow_mut().push((location, stamp));
+        value
+    }
+}
+
+impl Stamp {
+    fn is_fresh(
+        &self,
+        location: &ImportLocation,
+        fetcher: &dyn Fetcher,
+    ) -> bool {
+        match (self, location) {
+            (
+                Stamp::File { modified: m, hash },
+                ImportLocation::Local(path),
+            ) => {
+                (m.is_some() && *m == modified(path))
+                    || match fetcher.read_file(path) {
+                        Ok(text) => content_hash(&text) == *hash,
+                        Err(_) => false,
+                    }
+            }
+            (Stamp::MissingFile, ImportLocation::Local(path)) => {
+                fetcher.read_file(path).is_err()
+            }
+            (Stamp::Env(value), ImportLocation::Env(name)) => {
+                fetcher.read_env(name) == *value
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::with_cache(Cache::new())
+    }
+
+    /// A session that uses `cache` for frozen imports instead of the on-disk cache in the default
+    /// location.
+    pub fn with_cache(cache: Cache) -> Self {
+        Session {
+            cache,
+            state: RefCell::new(SessionState::default()),
+        }
+    }
+
+    /// Forget all the imports resolved so far.
+    pub fn clear(&self) {
+        *self.state.borrow_mut() = SessionState::default();
+    }
+
+    /// Resolve with the imports of the session, then store the new ones in it. `resolve` gets
+    /// the environment to resolve in, the on-disk cache and the fetcher to use.
+    pub(crate) fn resolve<T>(
+        &self,
+        fetcher: &dyn Fetcher,
+        policy: &ImportPolicy,
+        resolve: impl FnOnce(&mut ImportEnv, &Cache, &dyn Fetcher) -> T,
+    ) -> (T, Result<Vec<ImportEdge>, Error>) {
+        // Don't keep the state borrowed during resolution, in case the fetcher uses the session.
+        let mut state = std::mem::take(&mut *self.state.borrow_mut());
+        state.remove_stale(fetcher);
+        let recorder = Recorder {
+            fetcher,
+            stamps: RefCell::new(Vec::new()),
+        };
+        let mut env = ImportEnv::with_cache(std::mem::take(&mut state.imports));
+        let result = resolve(&mut env, &self.cache, &recorder);
+
+        let (imports, edges) = env.into_parts();
+        state.imports = imports;
+        state.stamps.extend(recorder.stamps.into_inner());
+        let graph = state.record(edges, policy);
+        *self.state.borrow_mut() = state;
+        (result, graph)
+    }
+}
+
+impl SessionState {
+    /// Drop the imports that changed since they were read, and those that depend on them.
+    fn remove_stale(&mut self, fetcher: &dyn Fetcher) {
+        let mut stale: HashSet<ImportLocation> = self
+            .stamps
+            .iter()
+            .filter(|(location, stamp)| !stamp.is_fresh(location, fetcher))
+            .map(|(location, _)| location.clone())
+            .collect();
+        loop {
+            let dependents: Vec<ImportLocation> = self
+                .edges
+                .iter()
+                .filter(|(parent, _)| !stale.contains(*parent))
+                .filter(|(_, edges)| {
+                    edges.iter().any(|edge| stale.contains(&edge.child))
+                })
+                .map(|(parent, _)| parent.clone())
+                .collect();
+            if dependents.is_empty() {
+                break;
+            }
+            stale.extend(dependents);
+        }
+        for location in &stale {
+            self.imports.remove(location);
+            self.edges.remove(location);
+            self.stamps.remove(location);
+        }
+    }
+
+    /// Remember the imports made by newly resolved imports, and return the complete import graph
+    /// of the call: the imports of expressions found in the session are added back, before the
+    /// edge that pointed to them, like when they are resolved anew.
+    fn record(
+        &mut self,
+        edges: Vec<ImportEdge>,
+        policy: &ImportPolicy,
+    ) -> Result<Vec<ImportEdge>, Error> {
+        let parents: HashSet<ImportLocation> =
+            edges.iter().map(|edge| edge.parent.clone()).collect();
+        for parent in &parents {
+            if self.imports.contains_key(parent) {
+                let children = edges
+                    .iter()
+                    .filter(|edge| edge.parent == *parent)
+                    .cloned()
+                    .collect();
+                self.edges.insert(parent.clone(), children);
+            }
+        }
+
+        let mut graph = Vec::new();
+        let mut seen = HashSet::new();
+        for edge in edges {
+            if seen.insert(edge.child.clone()) && !parents.contains(&edge.child)
+            {
+                self.replay(&edge.child, policy, &mut seen, &mut graph)?;
+            }
+            graph.push(edge);
+        }
+        Ok(graph)
+    }
+
+    /// Add the imports made by `location`, found in the session, to `graph`.
+    fn replay(
+        &self,
+        location: &ImportLocation,
+        policy: &ImportPolicy,
+        seen: &mut HashSet<ImportLocation>,
+        graph: &mut Vec<ImportEdge>,
+    ) -> Result<(), Error> {
+        for edge in self.edges.get(location).into_iter().flatten() {
+            if edge.mode != ImportMode::Location && !edge.failed {
+                policy.check(&edge.child, edge.hash.is_some())?;
+            }
+            if seen.insert(edge.child.clone()) {
+                self.replay(&edge.child, policy, seen, graph)?;
+            }
+            graph.push(edge.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::semantics::parse::parse_str;
+    use std::cell::Cell;
+    use std::path::PathBuf;
+
+    /// Serves `/dir/a.dhall`, which imports `/lib/b.dhall`, and `/dir/p.dhall`, which imports
+    /// `/lib/c.dhall` if it exists. Counts the reads.
+    struct Files {
+        b: RefCell<String>,
+        c: RefCell<Option<String>>,
+        reads: Cell<usize>,
+    }
+
+    impl Fetcher for Files {
+        fn read_file(&self, path: &Path) -> io::Result<String> {
+            self.reads.set(self.reads.get() + 1);
+            match path.file_name().unwrap().to_str().unwrap() {
+                "a.dhall" => Ok("/lib/b.dhall + 1".to_owned()),
+                "b.dhall" => Ok(self.b.borrow().clone()),
+                "p.dhall" => Ok("/lib/c.dhall ? 0".to_owned()),
+                "c.dhall" => match &*self.c.borrow() {
+                    Some(c) => Ok(c.clone()),
+                    None => Err(io::ErrorKind::NotFound.into()),
+                },
+                _ => Err(io::ErrorKind::NotFound.into()),
+            }
+        }
+        fn fetch_url(
+            &self,
+            _url: &Url,
+            _headers: &[(String, String)],
+        ) -> io::Result<String> {
+            Err(io::ErrorKind::NotFound.into())
+        }
+        fn read_env(&self, _name: &str) -> Option<String> {
+            None
+        }
+    }
+
+    #[test]
+    fn session_should_reuse_imports_until_they_change() {
+        let files = Files {
+            b: RefCell::new("1".to_owned()),
+            c: RefCell::new(None),
+            reads: Cell::new(0),
+        };
+        let session = Session::new();
+        let policy = ImportPolicy::new();
+        let eval = |s: &str| {
+            let resolved = parse_str(s)
+                .unwrap()
+                .resolve_in_with(&session, &files, &policy)
+                .unwrap();
+            let value = resolved.typecheck().unwrap().normalize().to_expr();
+            (value.to_string(), resolved.import_graph().edges().len())
+        };
+
+        assert_eq!(eval("/dir/a.dhall"), ("2".to_owned(), 2));
+        assert_eq!(files.reads.get(), 2);
+        // The files don't exist on disk, so they are read again to compare their contents.
+        assert_eq!(eval("/dir/a.dhall + /lib/b.dhall"), ("3".to_owned(), 3));
+        assert_eq!(files.reads.get(), 4);
+
+        *files.b.borrow_mut() = "10".to_owned();
+        assert_eq!(eval("/dir/a.dhall"), ("11".to_owned(), 2));
+        assert_eq!(files.reads.get(), 8);
+
+        // Files that were missing are checked too.
+        assert_eq!(eval("/dir/p.dhall"), ("0".to_owned(), 2));
+        assert_eq!(eval("/dir/p.dhall"), ("0".to_owned(), 2));
+        *files.c.borrow_mut() = Some("5".to_owned());
+        assert_eq!(eval("/dir/p.dhall"), ("5".to_owned(), 2));
+
+        // `/lib/b.dhall` is only imported by `/dir/a.dhall`, which is in the session.
+        let denied = ImportPolicy::new().allow_local_root("/dir");
+        let result = parse_str("/dir/a.dhall")
+            .unwrap()
+            .resolve_in_with(&session, &files, &denied);
+        assert!(result.is_err());
+
+        let a = ImportLocation::Local(PathBuf::from("/dir/a.dhall"));
+        assert!(session.state.borrow().imports.contains_key(&a));
+        session.clear();
+        assert!(session.state.borrow().imports.is_empty());
+    }
+}
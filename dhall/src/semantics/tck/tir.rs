@@ -1,5 +1,5 @@
 use crate::builtins::Builtin;
-use crate::error::{ErrorBuilder, TypeError};
+use crate::error::{TypeError, TypeMessage};
 use crate::semantics::{mkerr, Hir, Nir, NirKind, NzEnv, TyEnv, VarEnv};
 use crate::syntax::{Const, Expr, Span};
 
@@ -135,22 +135,12 @@ impl<'hir> Tir<'hir> {
     pub fn ensure_is_type(&self, env: &TyEnv) -> Result<(), TypeError> {
         if self.ty().as_const().is_none() {
             return mkerr(
-                ErrorBuilder::new(format!(
-                    "Expected a type, found: `{}`",
-                    self.to_expr_tyenv(env),
-                ))
-                .span_err(
-                    self.span(),
-                    format!(
-                        "this has type: `{}`",
-                        self.ty().to_expr_tyenv(env)
-                    ),
-                )
-                .help(format!(
-                    "An expression in type position must have type `Type`, \
-                     `Kind` or `Sort`",
-                ))
-                .format(),
+                env,
+                self.span(),
+                TypeMessage::NotAType {
+                    expr: self.to_expr_tyenv(env),
+                    actual: self.ty().clone(),
+                },
             );
         }
         Ok(())
@@ -1,7 +1,7 @@
 use std::cmp::max;
 
 use crate::builtins::{type_of_builtin, Builtin};
-use crate::error::{ErrorBuilder, TypeError, TypeMessage};
+use crate::error::{TypeError, TypeMessage};
 use crate::operations::typecheck_operation;
 use crate::semantics::{Hir, HirKind, Nir, NirKind, Tir, TyEnv, Type};
 use crate::syntax::{Const, ExprKind, InterpolatedTextContents, NumKind, Span};
@@ -14,16 +14,12 @@ fn function_check(a: Const, b: Const) -> Const {
     }
 }
 
-pub fn mkerr<T, S: ToString>(msg: S) -> Result<T, TypeError> {
-    Err(TypeError::new(TypeMessage::Custom(msg.to_string())))
-}
-
-pub fn mk_span_err<T, S: ToString>(span: Span, msg: S) -> Result<T, TypeError> {
-    mkerr(
-        ErrorBuilder::new(msg.to_string())
-            .span_err(span, msg.to_string())
-            .format(),
-    )
+pub fn mkerr<T>(
+    env: &TyEnv,
+    span: Span,
+    message: TypeMessage<Type>,
+) -> Result<T, TypeError> {
+    Err(TypeError::new(env, span, message))
 }
 
 /// When all sub-expressions have been typed, check the remaining toplevel
@@ -33,7 +29,7 @@ fn type_one_layer(
     ekind: ExprKind<Tir<'_>>,
     span: Span,
 ) -> Result<Type, TypeError> {
-    let span_err = |msg: &str| mk_span_err(span.clone(), msg);
+    let span_err = |msg| mkerr(env, span.clone(), msg);
 
     Ok(match &ekind {
         ExprKind::Import(..) => {
@@ -66,7 +62,11 @@ fn type_one_layer(
                 use InterpolatedTextContents::Expr;
                 if let Expr(x) = contents {
                     if *x.ty() != text_type {
-                        return span_err("InvalidTextInterpolation");
+                        return span_err(
+                            TypeMessage::InvalidTextInterpolation {
+                                actual: x.ty().clone(),
+                            },
+                        );
                     }
                 }
             }
@@ -74,7 +74,9 @@ fn type_one_layer(
         }
         ExprKind::SomeLit(x) => {
             if x.ty().ty().as_const() != Some(Const::Type) {
-                return span_err("InvalidOptionalType");
+                return span_err(TypeMessage::InvalidOptionalType {
+                    actual: x.ty().clone(),
+                });
             }
 
             let t = x.ty().to_nir();
@@ -86,7 +88,11 @@ fn type_one_layer(
             let t = t.eval_to_type(env)?;
             match t.kind() {
                 NirKind::ListType(..) => {}
-                _ => return span_err("InvalidListType"),
+                _ => {
+                    return span_err(TypeMessage::InvalidListType {
+                        actual: t.clone(),
+                    })
+                }
             };
             t
         }
@@ -95,11 +101,16 @@ fn type_one_layer(
             let x = iter.next().unwrap();
             for y in iter {
                 if x.ty() != y.ty() {
-                    return span_err("InvalidListElement");
+                    return span_err(TypeMessage::InvalidListElement {
+                        expected: x.ty().clone(),
+                        actual: y.ty().clone(),
+                    });
                 }
             }
             if x.ty().ty().as_const() != Some(Const::Type) {
-                return span_err("InvalidListType");
+                return span_err(TypeMessage::InvalidListType {
+                    actual: x.ty().clone(),
+                });
             }
 
             let t = x.ty().to_nir();
@@ -112,7 +123,15 @@ fn type_one_layer(
                 // Check that the fields have a valid kind
                 match v.ty().ty().as_const() {
                     Some(c) => k = max(k, c),
-                    None => return mk_span_err(v.span(), "InvalidFieldType"),
+                    None => {
+                        return mkerr(
+                            env,
+                            v.span(),
+                            TypeMessage::InvalidFieldType {
+                                actual: v.ty().clone(),
+                            },
+                        )
+                    }
                 }
             }
 
@@ -130,7 +149,15 @@ fn type_one_layer(
                 // Check the type is a Const and compute final type
                 match t.ty().as_const() {
                     Some(c) => k = max(k, c),
-                    None => return mk_span_err(t.span(), "InvalidFieldType"),
+                    None => {
+                        return mkerr(
+                            env,
+                            t.span(),
+                            TypeMessage::InvalidFieldType {
+                                actual: t.ty().clone(),
+                            },
+                        )
+                    }
                 }
             }
 
@@ -145,7 +172,13 @@ fn type_one_layer(
                     match t.ty().as_const() {
                         Some(c) => k = max(k, c),
                         None => {
-                            return mk_span_err(t.span(), "InvalidVariantType")
+                            return mkerr(
+                                env,
+                                t.span(),
+                                TypeMessage::InvalidVariantType {
+                                    actual: t.ty().clone(),
+                                },
+                            )
                         }
                     }
                 }
@@ -158,8 +191,16 @@ fn type_one_layer(
             let t = t.eval_to_type(env)?;
             match t.kind() {
                 NirKind::Equivalence(x, y) if x == y => {}
-                NirKind::Equivalence(..) => return span_err("AssertMismatch"),
-                _ => return span_err("AssertMustTakeEquivalence"),
+                NirKind::Equivalence(..) => {
+                    return span_err(TypeMessage::AssertMismatch {
+                        assertion: t.clone(),
+                    })
+                }
+                _ => {
+                    return span_err(TypeMessage::AssertMustTakeEquivalence {
+                        actual: t.clone(),
+                    })
+                }
             }
             t
         }
@@ -182,7 +223,7 @@ pub fn type_with<'hir>(
             unreachable!("Hir should contain no unresolved variables")
         }
         HirKind::Expr(ExprKind::Const(Const::Sort)) => {
-            return mk_span_err(hir.span(), "Sort does not have a type")
+            return mkerr(env, hir.span(), TypeMessage::Untyped)
         }
         HirKind::Expr(ExprKind::Annot(x, t)) => {
             let t = match t.kind() {
@@ -203,7 +244,15 @@ pub fn type_with<'hir>(
             let u_annot = annot.ty().as_const().unwrap();
             let u_body = match body.ty().ty().as_const() {
                 Some(k) => k,
-                _ => return mk_span_err(hir.span(), "Invalid output type"),
+                _ => {
+                    return mkerr(
+                        env,
+                        hir.span(),
+                        TypeMessage::InvalidOutputType {
+                            actual: body.ty().clone(),
+                        },
+                    )
+                }
             };
             let u = function_check(u_annot, u_body).to_universe();
             let ty_hir = Hir::new(
@@ -251,13 +300,13 @@ pub fn type_with<'hir>(
 
     if let Some(annot) = annot {
         if *tir.ty() != annot {
-            return mk_span_err(
+            return mkerr(
+                env,
                 hir.span(),
-                &format!(
-                    "annot mismatch: {} != {}",
-                    tir.ty().to_expr_tyenv(env),
-                    annot.to_expr_tyenv(env)
-                ),
+                TypeMessage::TypeMismatch {
+                    expected: annot,
+                    actual: tir.ty().clone(),
+                },
             );
         }
     }
@@ -7,8 +7,6 @@ use crate::semantics::Universe;
 use crate::syntax::visitor;
 use crate::syntax::*;
 
-pub type Integer = isize;
-pub type Natural = usize;
 pub type Double = NaiveDouble;
 
 /// Double with bitwise equality
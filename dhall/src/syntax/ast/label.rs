@@ -1,9 +1,9 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 // The type for labels throughout the AST
 // It owns the data because otherwise lifetimes would make recursive imports impossible
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub struct Label(Rc<str>);
+pub struct Label(Arc<str>);
 
 impl From<String> for Label {
     fn from(s: String) -> Self {
@@ -14,7 +14,7 @@ impl From<String> for Label {
 
 impl<'a> From<&'a str> for Label {
     fn from(s: &'a str) -> Self {
-        Label(Rc::from(s))
+        Label(Arc::from(s))
     }
 }
 
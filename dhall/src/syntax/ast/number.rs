@@ -0,0 +1,330 @@
+use num_bigint::{BigInt, BigUint};
+use num_traits::{ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg};
+
+/// A Dhall `Natural`. Natural numbers are unbounded; those that fit in a `u64` don't allocate.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Natural(NaturalRepr);
+
+/// A Dhall `Integer`. Integers are unbounded; those that fit in an `i64` don't allocate.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Integer(IntegerRepr);
+
+// A number is only stored as `Big` if it doesn't fit in `Small`, so that each number has a single
+// representation and the derived `Eq` and `Hash` are correct.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum NaturalRepr {
+    Small(u64),
+    Big(BigUint),
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum IntegerRepr {
+    Small(i64),
+    Big(BigInt),
+}
+
+impl Natural {
+    fn from_big(n: BigUint) -> Self {
+        Natural(match n.to_u64() {
+            Some(n) => NaturalRepr::Small(n),
+            None => NaturalRepr::Big(n),
+        })
+    }
+
+    fn to_big(&self) -> BigUint {
+        match &self.0 {
+            NaturalRepr::Small(n) => BigUint::from(*n),
+            NaturalRepr::Big(n) => n.clone(),
+        }
+    }
+
+    /// Parses the digits of a natural number in the given radix, without a prefix.
+    pub fn from_str_radix(s: &str, radix: u32) -> Option<Self> {
+        match u64::from_str_radix(s, radix) {
+            Ok(n) => Some(n.into()),
+            Err(_) => {
+                BigUint::parse_bytes(s.as_bytes(), radix).map(Natural::from_big)
+            }
+        }
+    }
+
+    /// The number as big-endian bytes, as in CBOR bignums.
+    pub(crate) fn to_bytes_be(&self) -> Vec<u8> {
+        self.to_big().to_bytes_be()
+    }
+
+    pub(crate) fn from_bytes_be(bytes: &[u8]) -> Self {
+        Natural::from_big(BigUint::from_bytes_be(bytes))
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        match &self.0 {
+            NaturalRepr::Small(n) => Some(*n),
+            NaturalRepr::Big(_) => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == NaturalRepr::Small(0)
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.0 == NaturalRepr::Small(1)
+    }
+
+    pub fn is_even(&self) -> bool {
+        match &self.0 {
+            NaturalRepr::Small(n) => n % 2 == 0,
+            NaturalRepr::Big(n) => (n % 2u32).is_zero(),
+        }
+    }
+
+    /// `self - other`, or zero if `other` is larger.
+    pub fn saturating_sub(&self, other: &Natural) -> Natural {
+        match (&self.0, &other.0) {
+            (NaturalRepr::Small(x), NaturalRepr::Small(y)) => {
+                x.saturating_sub(*y).into()
+            }
+            _ if self <= other => 0u64.into(),
+            _ => Natural::from_big(self.to_big() - other.to_big()),
+        }
+    }
+}
+
+impl Integer {
+    fn from_big(n: BigInt) -> Self {
+        Integer(match n.to_i64() {
+            Some(n) => IntegerRepr::Small(n),
+            None => IntegerRepr::Big(n),
+        })
+    }
+
+    fn to_big(&self) -> BigInt {
+        match &self.0 {
+            IntegerRepr::Small(n) => BigInt::from(*n),
+            IntegerRepr::Big(n) => n.clone(),
+        }
+    }
+
+    /// Parses the digits of an integer in the given radix, with an optional sign but no prefix.
+    pub fn from_str_radix(s: &str, radix: u32) -> Option<Self> {
+        match i64::from_str_radix(s, radix) {
+            Ok(n) => Some(n.into()),
+            Err(_) => {
+                BigInt::parse_bytes(s.as_bytes(), radix).map(Integer::from_big)
+            }
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match &self.0 {
+            IntegerRepr::Small(n) => Some(*n),
+            IntegerRepr::Big(_) => None,
+        }
+    }
+
+    /// The nearest `f64`, which is infinite if the integer is too large.
+    pub fn to_f64(&self) -> f64 {
+        match &self.0 {
+            IntegerRepr::Small(n) => *n as f64,
+            IntegerRepr::Big(n) => {
+                n.to_f64().unwrap_or(if self.is_negative() {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                })
+            }
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match &self.0 {
+            IntegerRepr::Small(n) => *n < 0,
+            IntegerRepr::Big(n) => n.sign() == num_bigint::Sign::Minus,
+        }
+    }
+
+    pub fn abs(&self) -> Natural {
+        match &self.0 {
+            IntegerRepr::Small(n) => (n.wrapping_abs() as u64).into(),
+            IntegerRepr::Big(n) => Natural::from_big(n.magnitude().clone()),
+        }
+    }
+
+    /// The integer as a `Natural`, or zero if it is negative.
+    pub fn clamp_to_natural(&self) -> Natural {
+        if self.is_negative() {
+            0u64.into()
+        } else {
+            self.abs()
+        }
+    }
+}
+
+impl From<u64> for Natural {
+    fn from(n: u64) -> Self {
+        Natural(NaturalRepr::Small(n))
+    }
+}
+
+impl From<usize> for Natural {
+    fn from(n: usize) -> Self {
+        Natural::from_big(BigUint::from(n))
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(n: i64) -> Self {
+        Integer(IntegerRepr::Small(n))
+    }
+}
+
+impl From<Natural> for Integer {
+    fn from(n: Natural) -> Self {
+        match n.0 {
+            NaturalRepr::Small(n) if n <= i64::MAX as u64 => (n as i64).into(),
+            _ => Integer::from_big(BigInt::from(n.to_big())),
+        }
+    }
+}
+
+impl Add for &Natural {
+    type Output = Natural;
+    fn add(self, other: &Natural) -> Natural {
+        if let (NaturalRepr::Small(x), NaturalRepr::Small(y)) =
+            (&self.0, &other.0)
+        {
+            if let Some(n) = x.checked_add(*y) {
+                return n.into();
+            }
+        }
+        Natural::from_big(self.to_big() + other.to_big())
+    }
+}
+
+impl Mul for &Natural {
+    type Output = Natural;
+    fn mul(self, other: &Natural) -> Natural {
+        if let (NaturalRepr::Small(x), NaturalRepr::Small(y)) =
+            (&self.0, &other.0)
+        {
+            if let Some(n) = x.checked_mul(*y) {
+                return n.into();
+            }
+        }
+        Natural::from_big(self.to_big() * other.to_big())
+    }
+}
+
+impl Neg for &Integer {
+    type Output = Integer;
+    fn neg(self) -> Integer {
+        match &self.0 {
+            IntegerRepr::Small(n) => match n.checked_neg() {
+                Some(n) => n.into(),
+                None => Integer::from_big(-self.to_big()),
+            },
+            IntegerRepr::Big(n) => Integer::from_big(-n),
+        }
+    }
+}
+
+impl Ord for Natural {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (NaturalRepr::Small(x), NaturalRepr::Small(y)) => x.cmp(y),
+            (NaturalRepr::Small(_), NaturalRepr::Big(_)) => Ordering::Less,
+            (NaturalRepr::Big(_), NaturalRepr::Small(_)) => Ordering::Greater,
+            (NaturalRepr::Big(x), NaturalRepr::Big(y)) => x.cmp(y),
+        }
+    }
+}
+
+impl PartialOrd for Natural {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (IntegerRepr::Small(x), IntegerRepr::Small(y)) => x.cmp(y),
+            _ => self.to_big().cmp(&other.to_big()),
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Natural {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            NaturalRepr::Small(n) => n.fmt(f),
+            NaturalRepr::Big(n) => n.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            IntegerRepr::Small(n) => n.fmt(f),
+            IntegerRepr::Big(n) => n.fmt(f),
+        }
+    }
+}
+
+impl fmt::Debug for Natural {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Debug for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arithmetic_should_not_overflow() {
+        let max = Natural::from(u64::MAX);
+        let big = &max + &Natural::from(1u64);
+        assert_eq!(big.to_string(), "18446744073709551616");
+        assert_eq!(big.to_u64(), None);
+        assert_eq!(big.saturating_sub(&1u64.into()), max);
+        assert_eq!(
+            (&max * &max).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert!(big.is_even() && !max.is_even());
+        assert_eq!(
+            Natural::from_str_radix("18446744073709551616", 10),
+            Some(big.clone())
+        );
+        assert_eq!(Natural::from_bytes_be(&big.to_bytes_be()), big);
+
+        let min = Integer::from(i64::MIN);
+        assert_eq!((-&min).to_string(), "9223372036854775808");
+        assert_eq!(-&-&min, min);
+        assert_eq!(min.abs().to_string(), "9223372036854775808");
+        assert!(min < Integer::from(0));
+        assert_eq!(Integer::from(big.clone()).clamp_to_natural(), big);
+        assert_eq!(
+            Integer::from_str_radix("-10000000000000000", 16),
+            Some(-&Integer::from(big))
+        );
+    }
+}
@@ -1,9 +1,18 @@
-use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::semantics::resolve::ImportLocation;
+
+/// The text of a parsed file, along with where it was read from.
+#[derive(Debug)]
+pub struct SourceFile {
+    text: String,
+    location: ImportLocation,
+}
 
 /// A location in the source text
 #[derive(Debug, Clone)]
 pub struct ParsedSpan {
-    input: Rc<str>,
+    input: Arc<SourceFile>,
     /// # Safety
     ///
     /// Must be a valid character boundary index into `input`.
@@ -29,22 +38,84 @@ pub enum Span {
     Artificial,
 }
 
+impl SourceFile {
+    pub fn new(text: String, location: ImportLocation) -> Self {
+        SourceFile { text, location }
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn location(&self) -> &ImportLocation {
+        &self.location
+    }
+}
+
 impl ParsedSpan {
     pub fn to_input(&self) -> String {
-        self.input.to_string()
+        self.input.text.clone()
+    }
+    /// The file this span points into.
+    pub fn location(&self) -> &ImportLocation {
+        &self.input.location
+    }
+    /// The byte offset of the start of the span.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    /// The byte offset just after the end of the span.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    /// The beginning of the span, up to byte offset `end`.
+    pub(crate) fn up_to(&self, end: usize) -> ParsedSpan {
+        ParsedSpan {
+            input: self.input.clone(),
+            start: self.start,
+            end: end.max(self.start).min(self.end),
+        }
+    }
+    /// The part of the span between byte offsets `start` and `end`.
+    pub(crate) fn between(&self, start: usize, end: usize) -> ParsedSpan {
+        let start = start.max(self.start).min(self.end);
+        ParsedSpan {
+            input: self.input.clone(),
+            start,
+            end: end.max(start).min(self.end),
+        }
+    }
+    /// The source text covered by the span.
+    pub fn text(&self) -> &str {
+        &self.input.text[self.start..self.end]
+    }
+    /// Whether the two spans point into the same parsed file.
+    pub fn same_file(&self, other: &ParsedSpan) -> bool {
+        Arc::ptr_eq(&self.input, &other.input)
     }
     /// Convert to a char range for consumption by annotate_snippets.
     /// This compensates for  https://github.com/rust-lang/annotate-snippets-rs/issues/24
     pub fn as_char_range(&self) -> (usize, usize) {
         (
-            char_idx_from_byte_idx(&self.input, self.start),
-            char_idx_from_byte_idx(&self.input, self.end),
+            char_idx_from_byte_idx(&self.input.text, self.start),
+            char_idx_from_byte_idx(&self.input.text, self.end),
         )
     }
+    /// The line and column of the start and end of the span. Both start at 1; columns count
+    /// characters.
+    pub fn line_col(&self) -> ((usize, usize), (usize, usize)) {
+        let line_col = |idx: usize| {
+            let before = &self.input.text[..idx];
+            let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+            (
+                before.matches('\n').count() + 1,
+                before[line_start..].chars().count() + 1,
+            )
+        };
+        (line_col(self.start), line_col(self.end))
+    }
 }
 
 impl Span {
-    pub fn make(input: Rc<str>, sp: pest::Span) -> Self {
+    pub fn make(input: Arc<SourceFile>, sp: pest::Span) -> Self {
         Span::Parsed(ParsedSpan {
             input,
             start: sp.start(),
@@ -59,13 +130,11 @@ impl Span {
         use std::cmp::{max, min};
         use Span::*;
         match (self, other) {
-            (Parsed(x), Parsed(y)) if Rc::ptr_eq(&x.input, &y.input) => {
-                Parsed(ParsedSpan {
-                    input: x.input.clone(),
-                    start: min(x.start, y.start),
-                    end: max(x.end, y.end),
-                })
-            }
+            (Parsed(x), Parsed(y)) if x.same_file(y) => Parsed(ParsedSpan {
+                input: x.input.clone(),
+                start: min(x.start, y.start),
+                end: max(x.end, y.end),
+            }),
             (Parsed(_), Parsed(_)) => panic!(
                 "Tried to union incompatible spans: {:?} and {:?}",
                 self, other
@@ -1,3 +1,4 @@
+use crate::syntax::trivial_result;
 use std::iter::FromIterator;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -106,6 +107,12 @@ impl<SubExpr> InterpolatedText<SubExpr> {
                 .collect::<Result<_, _>>()?,
         })
     }
+    pub fn map_ref<'a, SubExpr2>(
+        &'a self,
+        mut f: impl FnMut(&'a SubExpr) -> SubExpr2,
+    ) -> InterpolatedText<SubExpr2> {
+        trivial_result(self.traverse_ref(|x| Ok(f(x))))
+    }
 
     pub fn iter<'a>(
         &'a self,
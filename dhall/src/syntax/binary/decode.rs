@@ -1,5 +1,5 @@
 use itertools::Itertools;
-use serde_cbor::value::value as cbor;
+use serde_cbor::value as cbor;
 use std::iter::FromIterator;
 
 use crate::error::DecodeError;
@@ -32,7 +32,7 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
     use ExprKind::*;
     use OpKind::*;
     Ok(rc(match data {
-        String(s) => match Builtin::parse(s) {
+        Text(s) => match Builtin::parse(s) {
             Some(b) => ExprKind::Builtin(b),
             None => match s.as_str() {
                 "True" => Num(NumKind::Bool(true)),
@@ -47,20 +47,20 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                 }
             },
         },
-        U64(n) => Var(V(Label::from("_"), *n as usize)),
-        F64(x) => Num(NumKind::Double((*x).into())),
+        Integer(n) => Var(V(Label::from("_"), cbor_index(*n)?)),
+        Float(x) => Num(NumKind::Double((*x).into())),
         Bool(b) => Num(NumKind::Bool(*b)),
         Array(vec) => match vec.as_slice() {
-            [String(l), U64(n)] => {
+            [Text(l), Integer(n)] => {
                 if l.as_str() == "_" {
                     return Err(DecodeError::WrongFormatError(
                         "`_` variable was encoded incorrectly".to_owned(),
                     ));
                 }
                 let l = Label::from(l.as_str());
-                Var(V(l, *n as usize))
+                Var(V(l, cbor_index(*n)?))
             }
-            [U64(0), f, args @ ..] => {
+            [Integer(0), f, args @ ..] => {
                 if args.is_empty() {
                     return Err(DecodeError::WrongFormatError(
                         "Function application must have at least one argument"
@@ -74,12 +74,12 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                 }
                 return Ok(f);
             }
-            [U64(1), x, y] => {
+            [Integer(1), x, y] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 Lam(Label::from("_"), x, y)
             }
-            [U64(1), String(l), x, y] => {
+            [Integer(1), Text(l), x, y] => {
                 if l.as_str() == "_" {
                     return Err(DecodeError::WrongFormatError(
                         "`_` variable was encoded incorrectly".to_owned(),
@@ -90,12 +90,12 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                 let l = Label::from(l.as_str());
                 Lam(l, x, y)
             }
-            [U64(2), x, y] => {
+            [Integer(2), x, y] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 Pi(Label::from("_"), x, y)
             }
-            [U64(2), String(l), x, y] => {
+            [Integer(2), Text(l), x, y] => {
                 if l.as_str() == "_" {
                     return Err(DecodeError::WrongFormatError(
                         "`_` variable was encoded incorrectly".to_owned(),
@@ -106,12 +106,12 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                 let l = Label::from(l.as_str());
                 Pi(l, x, y)
             }
-            [U64(3), U64(13), x, y] => {
+            [Integer(3), Integer(13), x, y] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 Op(Completion(x, y))
             }
-            [U64(3), U64(n), x, y] => {
+            [Integer(3), Integer(n), x, y] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 use BinOp::*;
@@ -137,30 +137,30 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                 };
                 Op(BinOp(op, x, y))
             }
-            [U64(4), t] => {
+            [Integer(4), t] => {
                 let t = cbor_value_to_dhall(&t)?;
                 EmptyListLit(rc(Op(App(
                     rc(ExprKind::Builtin(Builtin::List)),
                     t,
                 ))))
             }
-            [U64(4), Null, rest @ ..] => {
+            [Integer(4), Null, rest @ ..] => {
                 let rest = rest
                     .iter()
                     .map(cbor_value_to_dhall)
                     .collect::<Result<Vec<_>, _>>()?;
                 NEListLit(rest)
             }
-            [U64(5), Null, x] => {
+            [Integer(5), Null, x] => {
                 let x = cbor_value_to_dhall(&x)?;
                 SomeLit(x)
             }
             // Old-style optional literals
-            [U64(5), t] => {
+            [Integer(5), t] => {
                 let t = cbor_value_to_dhall(&t)?;
                 Op(App(rc(ExprKind::Builtin(Builtin::OptionalNone)), t))
             }
-            [U64(5), t, x] => {
+            [Integer(5), t, x] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let t = cbor_value_to_dhall(&t)?;
                 Annot(
@@ -168,31 +168,31 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                     rc(Op(App(rc(ExprKind::Builtin(Builtin::Optional)), t))),
                 )
             }
-            [U64(6), x, y] => {
+            [Integer(6), x, y] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 Op(Merge(x, y, None))
             }
-            [U64(6), x, y, z] => {
+            [Integer(6), x, y, z] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 let z = cbor_value_to_dhall(&z)?;
                 Op(Merge(x, y, Some(z)))
             }
-            [U64(7), Object(map)] => {
+            [Integer(7), Map(map)] => {
                 let map = cbor_map_to_dhall_map(map)?;
                 RecordType(map)
             }
-            [U64(8), Object(map)] => {
+            [Integer(8), Map(map)] => {
                 let map = cbor_map_to_dhall_map(map)?;
                 RecordLit(map)
             }
-            [U64(9), x, String(l)] => {
+            [Integer(9), x, Text(l)] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let l = Label::from(l.as_str());
                 Op(Field(x, l))
             }
-            [U64(10), x, Array(arr)] => {
+            [Integer(10), x, Array(arr)] => {
                 let x = cbor_value_to_dhall(&x)?;
                 if let [y] = arr.as_slice() {
                     let y = cbor_value_to_dhall(&y)?;
@@ -203,12 +203,12 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                     ));
                 }
             }
-            [U64(10), x, rest @ ..] => {
+            [Integer(10), x, rest @ ..] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let labels = rest
                     .iter()
                     .map(|s| match s {
-                        String(s) => Ok(Label::from(s.as_str())),
+                        Text(s) => Ok(Label::from(s.as_str())),
                         _ => Err(DecodeError::WrongFormatError(
                             "projection".to_owned(),
                         )),
@@ -216,25 +216,24 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                     .collect::<Result<_, _>>()?;
                 Op(Projection(x, labels))
             }
-            [U64(11), Object(map)] => {
+            [Integer(11), Map(map)] => {
                 let map = cbor_map_to_dhall_opt_map(map)?;
                 UnionType(map)
             }
-            [U64(12), ..] => {
+            [Integer(12), ..] => {
                 return Err(DecodeError::WrongFormatError(
                     "Union literals are not supported anymore".to_owned(),
                 ))
             }
-            [U64(14), x, y, z] => {
+            [Integer(14), x, y, z] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 let z = cbor_value_to_dhall(&z)?;
                 Op(BoolIf(x, y, z))
             }
-            [U64(15), U64(x)] => Num(NumKind::Natural(*x as Natural)),
-            [U64(16), U64(x)] => Num(NumKind::Integer(*x as Integer)),
-            [U64(16), I64(x)] => Num(NumKind::Integer(*x as Integer)),
-            [U64(18), String(first), rest @ ..] => {
+            [Integer(15), x] => Num(NumKind::Natural(cbor_natural(x)?)),
+            [Integer(16), x] => Num(NumKind::Integer(cbor_integer(x)?)),
+            [Integer(18), Text(first), rest @ ..] => {
                 TextLit(InterpolatedText::from((
                     first.clone(),
                     rest.iter()
@@ -242,7 +241,7 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                         .map(|(x, y)| {
                             let x = cbor_value_to_dhall(&x)?;
                             let y = match y {
-                                String(s) => s.clone(),
+                                Text(s) => s.clone(),
                                 _ => {
                                     return Err(DecodeError::WrongFormatError(
                                         "text".to_owned(),
@@ -254,11 +253,11 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                         .collect::<Result<_, _>>()?,
                 )))
             }
-            [U64(19), t] => {
+            [Integer(19), t] => {
                 let t = cbor_value_to_dhall(&t)?;
                 Assert(t)
             }
-            [U64(24), hash, U64(mode), U64(scheme), rest @ ..] => {
+            [Integer(24), hash, Integer(mode), Integer(scheme), rest @ ..] => {
                 let mode = match mode {
                     0 => ImportMode::Code,
                     1 => ImportMode::RawText,
@@ -309,7 +308,7 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                             }
                         };
                         let authority = match rest.next() {
-                            Some(String(s)) => s.to_owned(),
+                            Some(Text(s)) => s.to_owned(),
                             _ => {
                                 return Err(DecodeError::WrongFormatError(
                                     "import/remote/authority".to_owned(),
@@ -318,7 +317,7 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                         };
                         let query = match rest.next_back() {
                             Some(Null) => None,
-                            Some(String(s)) => Some(s.to_owned()),
+                            Some(Text(s)) => Some(s.to_owned()),
                             _ => {
                                 return Err(DecodeError::WrongFormatError(
                                     "import/remote/query".to_owned(),
@@ -326,9 +325,9 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                             }
                         };
                         let file_path = rest
-                            .map(|s| match s.as_string() {
-                                Some(s) => Ok(s.clone()),
-                                None => Err(DecodeError::WrongFormatError(
+                            .map(|s| match s {
+                                Text(s) => Ok(s.clone()),
+                                _ => Err(DecodeError::WrongFormatError(
                                     "import/remote/path".to_owned(),
                                 )),
                             })
@@ -355,9 +354,9 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                             }
                         };
                         let file_path = rest
-                            .map(|s| match s.as_string() {
-                                Some(s) => Ok(s.clone()),
-                                None => Err(DecodeError::WrongFormatError(
+                            .map(|s| match s {
+                                Text(s) => Ok(s.clone()),
+                                _ => Err(DecodeError::WrongFormatError(
                                     "import/local/path".to_owned(),
                                 )),
                             })
@@ -367,7 +366,7 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                     }
                     6 => {
                         let env = match rest.next() {
-                            Some(String(s)) => s.to_owned(),
+                            Some(Text(s)) => s.to_owned(),
                             _ => {
                                 return Err(DecodeError::WrongFormatError(
                                     "import/env".to_owned(),
@@ -389,16 +388,18 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                     location,
                 })
             }
-            [U64(25), bindings @ ..] => {
+            [Integer(25), bindings @ ..] => {
                 let mut tuples = bindings.iter().tuples();
                 let bindings = (&mut tuples)
                     .map(|(x, t, v)| {
-                        let x = x.as_string().ok_or_else(|| {
-                            DecodeError::WrongFormatError(
-                                "let/label".to_owned(),
-                            )
-                        })?;
-                        let x = Label::from(x.as_str());
+                        let x = match x {
+                            Text(x) => Label::from(x.as_str()),
+                            _ => {
+                                return Err(DecodeError::WrongFormatError(
+                                    "let/label".to_owned(),
+                                ))
+                            }
+                        };
                         let t = match t {
                             Null => None,
                             t => Some(cbor_value_to_dhall(&t)?),
@@ -416,21 +417,21 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
                     .rev()
                     .fold(expr, |acc, (x, t, v)| rc(Let(x, t, v, acc))));
             }
-            [U64(26), x, y] => {
+            [Integer(26), x, y] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 Annot(x, y)
             }
-            [U64(27), x] => {
+            [Integer(27), x] => {
                 let x = cbor_value_to_dhall(&x)?;
                 Op(ToMap(x, None))
             }
-            [U64(27), x, y] => {
+            [Integer(27), x, y] => {
                 let x = cbor_value_to_dhall(&x)?;
                 let y = cbor_value_to_dhall(&y)?;
                 Op(ToMap(x, Some(y)))
             }
-            [U64(28), x] => {
+            [Integer(28), x] => {
                 let x = cbor_value_to_dhall(&x)?;
                 EmptyListLit(x)
             }
@@ -445,17 +446,69 @@ fn cbor_value_to_dhall(data: &cbor::Value) -> Result<DecodedExpr, DecodeError> {
     }))
 }
 
+fn cbor_index(n: i128) -> Result<usize, DecodeError> {
+    use std::convert::TryFrom;
+    usize::try_from(n)
+        .map_err(|_| DecodeError::WrongFormatError("var/index".to_owned()))
+}
+
+fn cbor_natural(data: &cbor::Value) -> Result<Natural, DecodeError> {
+    use cbor::Value::{Bytes, Integer, Tag};
+    use std::convert::TryFrom;
+    match data {
+        Integer(n) => {
+            if let Ok(n) = u64::try_from(*n) {
+                return Ok(n.into());
+            }
+        }
+        Tag(2, bytes) => {
+            if let Bytes(bytes) = bytes.as_ref() {
+                return Ok(Natural::from_bytes_be(bytes));
+            }
+        }
+        _ => {}
+    }
+    Err(DecodeError::WrongFormatError("natural".to_owned()))
+}
+
+/// Negative integers are stored as `-1 - n` in CBOR.
+fn cbor_integer(data: &cbor::Value) -> Result<Integer, DecodeError> {
+    use cbor::Value::{Bytes, Integer, Tag};
+    use std::convert::TryFrom;
+    let negative = |n: Natural| -&syntax::Integer::from(&n + &1u64.into());
+    match data {
+        Integer(n) if *n < 0 => {
+            if let Ok(n) = u64::try_from(-1 - *n) {
+                return Ok(negative(n.into()));
+            }
+        }
+        Integer(_) | Tag(2, _) => return Ok(cbor_natural(data)?.into()),
+        Tag(3, bytes) => {
+            if let Bytes(bytes) = bytes.as_ref() {
+                return Ok(negative(Natural::from_bytes_be(bytes)));
+            }
+        }
+        _ => {}
+    }
+    Err(DecodeError::WrongFormatError("integer".to_owned()))
+}
+
 fn cbor_map_to_dhall_map<'a, T>(
-    map: impl IntoIterator<Item = (&'a cbor::ObjectKey, &'a cbor::Value)>,
+    map: impl IntoIterator<Item = (&'a cbor::Value, &'a cbor::Value)>,
 ) -> Result<T, DecodeError>
 where
     T: FromIterator<(Label, DecodedExpr)>,
 {
     map.into_iter()
         .map(|(k, v)| -> Result<(_, _), _> {
-            let k = k.as_string().ok_or_else(|| {
-                DecodeError::WrongFormatError("map/key".to_owned())
-            })?;
+            let k = match k {
+                cbor::Value::Text(k) => k,
+                _ => {
+                    return Err(DecodeError::WrongFormatError(
+                        "map/key".to_owned(),
+                    ))
+                }
+            };
             let v = cbor_value_to_dhall(v)?;
             Ok((Label::from(k.as_ref()), v))
         })
@@ -463,16 +516,21 @@ where
 }
 
 fn cbor_map_to_dhall_opt_map<'a, T>(
-    map: impl IntoIterator<Item = (&'a cbor::ObjectKey, &'a cbor::Value)>,
+    map: impl IntoIterator<Item = (&'a cbor::Value, &'a cbor::Value)>,
 ) -> Result<T, DecodeError>
 where
     T: FromIterator<(Label, Option<DecodedExpr>)>,
 {
     map.into_iter()
         .map(|(k, v)| -> Result<(_, _), _> {
-            let k = k.as_string().ok_or_else(|| {
-                DecodeError::WrongFormatError("map/key".to_owned())
-            })?;
+            let k = match k {
+                cbor::Value::Text(k) => k,
+                _ => {
+                    return Err(DecodeError::WrongFormatError(
+                        "map/key".to_owned(),
+                    ))
+                }
+            };
             let v = match v {
                 cbor::Value::Null => None,
                 _ => Some(cbor_value_to_dhall(v)?),
@@ -1,4 +1,4 @@
-use serde_cbor::value::value as cbor;
+use serde_cbor::value as cbor;
 use std::collections::BTreeMap;
 use std::vec;
 
@@ -44,7 +44,7 @@ fn serialize_subexpr<S>(ser: S, e: &Expr) -> Result<S::Ok, S::Error>
 where
     S: serde::ser::Serializer,
 {
-    use cbor::Value::{String, I64, U64};
+    use cbor::Value::Text;
     use std::iter::once;
     use syntax::ExprKind::*;
     use syntax::NumKind::*;
@@ -56,16 +56,16 @@ where
     }
     let cbor =
         |v: cbor::Value| -> self::Serialize<'_> { self::Serialize::CBOR(v) };
-    let tag = |x: u64| cbor(U64(x));
+    let tag = |x: i128| cbor(cbor::Value::Integer(x));
     let null = || cbor(cbor::Value::Null);
-    let label = |l: &Label| cbor(cbor::Value::String(l.into()));
+    let label = |l: &Label| cbor(Text(l.into()));
 
     match e.as_ref() {
         Const(c) => ser.serialize_str(&c.to_string()),
         Builtin(b) => ser.serialize_str(&b.to_string()),
         Num(Bool(b)) => ser.serialize_bool(*b),
-        Num(Natural(n)) => ser_seq!(ser; tag(15), U64(*n as u64)),
-        Num(Integer(n)) => ser_seq!(ser; tag(16), I64(*n as i64)),
+        Num(Natural(n)) => ser_seq!(ser; tag(15), natural(n)),
+        Num(Integer(n)) => ser_seq!(ser; tag(16), integer(n)),
         Num(Double(n)) => {
             let n: f64 = (*n).into();
             ser.serialize_f64(n)
@@ -74,7 +74,7 @@ where
             ser_seq!(ser; tag(14), expr(x), expr(y), expr(z))
         }
         Var(V(l, n)) if l == &"_".into() => ser.serialize_u64(*n as u64),
-        Var(V(l, n)) => ser_seq!(ser; label(l), U64(*n as u64)),
+        Var(V(l, n)) => ser_seq!(ser; label(l), tag(*n as i128)),
         Lam(l, x, y) if l == &"_".into() => {
             ser_seq!(ser; tag(1), expr(x), expr(y))
         }
@@ -128,7 +128,7 @@ where
             use syntax::InterpolatedTextContents::{Expr, Text};
             ser.collect_seq(once(tag(18)).chain(xs.iter().map(|x| match x {
                 Expr(x) => expr(x),
-                Text(x) => cbor(String(x)),
+                Text(x) => cbor(cbor::Value::Text(x)),
             })))
         }
         RecordType(map) => ser_seq!(ser; tag(7), RecordMap(map)),
@@ -152,7 +152,7 @@ where
                 ImportAlt => 11,
                 Equivalence => 12,
             };
-            ser_seq!(ser; tag(3), U64(op), expr(x), expr(y))
+            ser_seq!(ser; tag(3), tag(op), expr(x), expr(y))
         }
         Op(Merge(x, y, None)) => ser_seq!(ser; tag(6), expr(x), expr(y)),
         Op(Merge(x, y, Some(z))) => {
@@ -179,7 +179,7 @@ fn serialize_import<S>(ser: S, import: &Import<Expr>) -> Result<S::Ok, S::Error>
 where
     S: serde::ser::Serializer,
 {
-    use cbor::Value::{Bytes, Null, U64};
+    use cbor::Value::{Bytes, Integer, Null};
     use serde::ser::SerializeSeq;
 
     let count = 4 + match &import.location {
@@ -190,7 +190,7 @@ where
     };
     let mut ser_seq = ser.serialize_seq(Some(count))?;
 
-    ser_seq.serialize_element(&U64(24))?;
+    ser_seq.serialize_element(&Integer(24))?;
 
     let hash = match &import.hash {
         None => Null,
@@ -207,7 +207,7 @@ where
         ImportMode::RawText => 1,
         ImportMode::Location => 2,
     };
-    ser_seq.serialize_element(&U64(mode))?;
+    ser_seq.serialize_element(&Integer(mode))?;
 
     let scheme = match &import.location {
         ImportTarget::Remote(url) => match url.scheme {
@@ -223,7 +223,7 @@ where
         ImportTarget::Env(_) => 6,
         ImportTarget::Missing => 7,
     };
-    ser_seq.serialize_element(&U64(scheme))?;
+    ser_seq.serialize_element(&Integer(scheme))?;
 
     match &import.location {
         ImportTarget::Remote(url) => {
@@ -266,7 +266,7 @@ impl<'a> serde::ser::Serialize for Serialize<'a> {
             Serialize::CBOR(v) => v.serialize(ser),
             Serialize::RecordMap(map) => {
                 ser.collect_map(map.iter().map(|(k, v)| {
-                    (cbor::Value::String(k.into()), Serialize::Expr(v))
+                    (cbor::Value::Text(k.into()), Serialize::Expr(v))
                 }))
             }
             Serialize::UnionMap(map) => {
@@ -275,13 +275,35 @@ impl<'a> serde::ser::Serialize for Serialize<'a> {
                         Some(x) => Serialize::Expr(x),
                         None => Serialize::CBOR(cbor::Value::Null),
                     };
-                    (cbor::Value::String(k.into()), v)
+                    (cbor::Value::Text(k.into()), v)
                 }))
             }
         }
     }
 }
 
+/// Natural numbers that don't fit in 64 bits are encoded as bignums.
+fn natural(n: &syntax::Natural) -> cbor::Value {
+    use cbor::Value::{Bytes, Integer, Tag};
+    match n.to_u64() {
+        Some(n) => Integer(n.into()),
+        None => Tag(2, Box::new(Bytes(n.to_bytes_be()))),
+    }
+}
+
+/// Like in CBOR, a negative integer `n` is encoded as `-1 - n`.
+fn integer(n: &syntax::Integer) -> cbor::Value {
+    use cbor::Value::{Bytes, Integer, Tag};
+    if !n.is_negative() {
+        return natural(&n.abs());
+    }
+    let m = n.abs().saturating_sub(&1u64.into());
+    match m.to_u64() {
+        Some(m) => Integer(-1 - i128::from(m)),
+        None => Tag(3, Box::new(Bytes(m.to_bytes_be()))),
+    }
+}
+
 fn collect_nested_applications<'a>(e: &'a Expr) -> (&'a Expr, Vec<&'a Expr>) {
     fn go<'a>(e: &'a Expr, vec: &mut Vec<&'a Expr>) -> &'a Expr {
         match e.as_ref() {
@@ -2,3 +2,36 @@ mod decode;
 mod encode;
 pub use decode::decode;
 pub use encode::encode;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::parse_expr;
+
+    #[test]
+    fn encode_should_use_bignums_for_large_numbers() {
+        let roundtrip = |s: &str| {
+            let expr = parse_expr(s).unwrap();
+            let bytes = encode(&expr).unwrap();
+            assert_eq!(decode(&bytes).unwrap(), expr);
+            hex::encode(bytes)
+        };
+        assert_eq!(roundtrip("18446744073709551615"), "820f1bffffffffffffffff");
+        assert_eq!(
+            roundtrip("18446744073709551616"),
+            "820fc249010000000000000000"
+        );
+        assert_eq!(
+            roundtrip("-18446744073709551616"),
+            "82103bffffffffffffffff"
+        );
+        assert_eq!(
+            roundtrip("-18446744073709551617"),
+            "8210c349010000000000000000"
+        );
+        assert_eq!(
+            roundtrip("+0x10000000000000000"),
+            roundtrip("+18446744073709551616")
+        );
+    }
+}
@@ -8,6 +8,10 @@
 mod ast;
 pub use crate::syntax::ast::visitor;
 pub use crate::syntax::ast::*;
+pub use crate::syntax::text::cst::{Cst, CstElement, CstNode, Trivia};
+pub use crate::syntax::text::format::{
+    format_expr, DroppedComments, FormatOptions,
+};
 pub use crate::syntax::text::parser::*;
 pub use crate::syntax::text::printer::*;
 pub mod binary;
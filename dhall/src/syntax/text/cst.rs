@@ -0,0 +1,398 @@
+//! A concrete syntax tree, that keeps all of the source text including comments and whitespace.
+//!
+//! The tree is built on top of the `Expr` returned by the parser: every subexpression that has a
+//! location in the source becomes a `CstNode`, and the text between subexpressions is kept as
+//! tokens and trivia. Printing a `Cst` gives back the original text byte-for-byte.
+use std::fmt;
+
+use crate::semantics::resolve::ImportLocation;
+use crate::syntax::{parse_expr_at, Expr, ExprKind, ParseResult, Span};
+
+/// A lossless syntax tree of a whole file.
+#[derive(Debug, Clone)]
+pub struct Cst {
+    /// The root node, surrounded by leading and trailing trivia.
+    elements: Vec<CstElement>,
+}
+
+/// The part of the source text corresponding to a subexpression.
+#[derive(Debug, Clone)]
+pub struct CstNode {
+    expr: Expr,
+    start: usize,
+    end: usize,
+    elements: Vec<CstElement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CstElement {
+    Node(CstNode),
+    /// Other meaningful source text: keywords, labels, operators, punctuation, or the contents
+    /// of text literals and import locations.
+    Token(String),
+    Trivia(Trivia),
+}
+
+/// Source text that does not affect the meaning of the expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trivia {
+    Whitespace(String),
+    /// `-- comment`, without the final newline.
+    LineComment(String),
+    /// `{- comment -}`; these can be nested.
+    BlockComment(String),
+}
+
+impl Cst {
+    pub fn parse(input: &str) -> ParseResult<Cst> {
+        Cst::parse_at(input, ImportLocation::Missing)
+    }
+
+    /// Parse an expression read from `location`, like `parse_expr_at`.
+    pub fn parse_at(input: &str, location: ImportLocation) -> ParseResult<Cst> {
+        let expr = parse_expr_at(input, location)?;
+        // The span of the expression may not cover the whole text: it leaves out enclosing
+        // parentheses, and desugared expressions have no span. The root node is everything but
+        // the surrounding trivia.
+        let (start, end) = expr_range(&expr).unwrap_or((0, input.len()));
+        let start = trivia_len(lex(&input[..start]).iter());
+        let rest = &input[end..];
+        let end = end + rest.len() - trivia_len(lex(rest).iter().rev());
+        let mut elements = lex(&input[..start]);
+        elements.push(CstElement::Node(CstNode::new(input, expr, start, end)));
+        elements.extend(lex(&input[end..]));
+        Ok(Cst { elements })
+    }
+
+    /// The root node of the tree.
+    pub fn root(&self) -> &CstNode {
+        self.elements
+            .iter()
+            .find_map(|element| match element {
+                CstElement::Node(node) => Some(node),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    /// The parsed expression.
+    pub fn expr(&self) -> &Expr {
+        self.root().expr()
+    }
+
+    /// The root node, along with the trivia before and after it.
+    pub fn elements(&self) -> &[CstElement] {
+        &self.elements
+    }
+
+    /// All the comments in the file, in order.
+    pub fn comments(&self) -> Vec<&Trivia> {
+        let mut comments = Vec::new();
+        collect_comments(&self.elements, &mut comments);
+        comments
+    }
+}
+
+impl CstNode {
+    fn new(input: &str, expr: Expr, start: usize, end: usize) -> Self {
+        let mut children = Vec::new();
+        collect_children(&expr, &mut children);
+        children.sort_by_key(|(_, (start, end))| (*start, *end));
+
+        // Keep the children in order, skipping any that don't fit. This happens when desugaring
+        // duplicates a subexpression, e.g. with `with`.
+        let verbatim =
+            matches!(expr.kind(), ExprKind::TextLit(_) | ExprKind::Import(_));
+        let mut elements = Vec::new();
+        let mut pos = start;
+        for (child, (child_start, child_end)) in children {
+            if child_start < pos || child_end > end {
+                continue;
+            }
+            elements.extend(lex_segment(&input[pos..child_start], verbatim));
+            elements.push(CstElement::Node(CstNode::new(
+                input,
+                child.clone(),
+                child_start,
+                child_end,
+            )));
+            pos = child_end;
+        }
+        elements.extend(lex_segment(&input[pos..end], verbatim));
+
+        CstNode {
+            expr,
+            start,
+            end,
+            elements,
+        }
+    }
+
+    /// The expression this node corresponds to.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+    /// The byte offset of the start of the node in the source.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    /// The byte offset just after the end of the node.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    pub fn elements(&self) -> &[CstElement] {
+        &self.elements
+    }
+    /// The nodes of the direct subexpressions, in source order.
+    pub fn children(&self) -> impl Iterator<Item = &CstNode> {
+        self.elements.iter().filter_map(|element| match element {
+            CstElement::Node(node) => Some(node),
+            _ => None,
+        })
+    }
+}
+
+impl Trivia {
+    pub fn text(&self) -> &str {
+        match self {
+            Trivia::Whitespace(s)
+            | Trivia::LineComment(s)
+            | Trivia::BlockComment(s) => s,
+        }
+    }
+    pub fn is_comment(&self) -> bool {
+        match self {
+            Trivia::Whitespace(_) => false,
+            Trivia::LineComment(_) | Trivia::BlockComment(_) => true,
+        }
+    }
+}
+
+/// The byte range of an expression in the source, if it was not desugared.
+fn node_range(expr: &Expr) -> Option<(usize, usize)> {
+    match expr.span() {
+        Span::Parsed(span) => Some((span.start(), span.end())),
+        _ => None,
+    }
+}
+
+/// Find the subexpressions that have a location in the source, looking through desugared ones.
+fn collect_children<'a>(
+    expr: &'a Expr,
+    children: &mut Vec<(&'a Expr, (usize, usize))>,
+) {
+    expr.kind().map_ref(|child| match node_range(child) {
+        Some(range) => children.push((child, range)),
+        None => collect_children(child, children),
+    });
+}
+
+fn collect_comments<'a>(elements: &'a [CstElement], out: &mut Vec<&'a Trivia>) {
+    for element in elements {
+        match element {
+            CstElement::Node(node) => collect_comments(&node.elements, out),
+            CstElement::Trivia(trivia) if trivia.is_comment() => {
+                out.push(trivia)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The byte range of an expression in the source, or of its subexpressions if it was desugared.
+fn expr_range(expr: &Expr) -> Option<(usize, usize)> {
+    node_range(expr).or_else(|| {
+        let mut children = Vec::new();
+        collect_children(expr, &mut children);
+        let start = children.iter().map(|(_, (start, _))| *start).min()?;
+        let end = children.iter().map(|(_, (_, end))| *end).max()?;
+        Some((start, end))
+    })
+}
+
+/// The length of the trivia at the start of `elements`.
+fn trivia_len<'a>(elements: impl Iterator<Item = &'a CstElement>) -> usize {
+    elements
+        .take_while(|element| matches!(element, CstElement::Trivia(_)))
+        .map(|element| element.to_string().len())
+        .sum()
+}
+
+fn lex_segment(text: &str, verbatim: bool) -> Vec<CstElement> {
+    if text.is_empty() {
+        Vec::new()
+    } else if verbatim {
+        vec![CstElement::Token(text.to_owned())]
+    } else {
+        lex(text)
+    }
+}
+
+fn is_label_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '/' || c == '_'
+}
+
+/// Split source text that contains no subexpression into tokens and trivia.
+pub(crate) fn lex(text: &str) -> Vec<CstElement> {
+    let mut elements = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let (len, element): (usize, fn(String) -> CstElement) =
+            if c == ' ' || c == '\t' || c == '\n' || c == '\r' {
+                let len = rest
+                    .find(|c| c != ' ' && c != '\t' && c != '\n' && c != '\r')
+                    .unwrap_or(rest.len());
+                (len, |s| CstElement::Trivia(Trivia::Whitespace(s)))
+            } else if rest.starts_with("--") {
+                let len = rest.find(&['\n', '\r'][..]).unwrap_or(rest.len());
+                (len, |s| CstElement::Trivia(Trivia::LineComment(s)))
+            } else if rest.starts_with("{-") {
+                (block_comment_len(rest), |s| {
+                    CstElement::Trivia(Trivia::BlockComment(s))
+                })
+            } else if c == '`' {
+                let len = rest[1..].find('`').map(|i| i + 2);
+                (len.unwrap_or(rest.len()), CstElement::Token)
+            } else if c.is_ascii_alphanumeric() || c == '_' {
+                let len =
+                    rest.find(|c| !is_label_char(c)).unwrap_or(rest.len());
+                (len, CstElement::Token)
+            } else if "(){}[],".contains(c) {
+                (1, CstElement::Token)
+            } else {
+                let len = rest
+                    .char_indices()
+                    .find(|(i, c)| {
+                        *i > 0
+                            && (c.is_whitespace()
+                                || c.is_ascii_alphanumeric()
+                                || *c == '_'
+                                || "(){}[],`".contains(*c)
+                                || rest[*i..].starts_with("--")
+                                || rest[*i..].starts_with("{-"))
+                    })
+                    .map(|(i, _)| i)
+                    .unwrap_or(rest.len());
+                (len, CstElement::Token)
+            };
+        elements.push(element(rest[..len].to_owned()));
+        rest = &rest[len..];
+    }
+    elements
+}
+
+/// The length of the (possibly nested) block comment at the start of `text`.
+fn block_comment_len(text: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        if text[i..].starts_with("{-") {
+            depth += 1;
+            i += 2;
+        } else if text[i..].starts_with("-}") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += text[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    text.len()
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for element in &self.elements {
+            element.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CstNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for element in &self.elements {
+            element.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CstElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CstElement::Node(node) => node.fmt(f),
+            CstElement::Token(s) => f.write_str(s),
+            CstElement::Trivia(trivia) => f.write_str(trivia.text()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cst_should_keep_comments() {
+        let input = "-- header\nlet x = {- one -} 1 -- trailing\nin  { a = x, b = \"-- not a comment\" }\n";
+        let cst = Cst::parse(input).unwrap();
+        assert_eq!(cst.to_string(), input);
+        assert_eq!(cst.expr(), &crate::syntax::parse_expr(input).unwrap());
+        let comments: Vec<_> =
+            cst.comments().into_iter().map(Trivia::text).collect();
+        assert_eq!(comments, vec!["-- header", "{- one -}", "-- trailing"]);
+
+        // The `let` node has the value, the body and, through the record, `x` and the text.
+        let root = cst.root();
+        let children: Vec<_> =
+            root.children().map(|node| node.to_string()).collect();
+        assert_eq!(children, vec!["1", "{ a = x, b = \"-- not a comment\" }"]);
+    }
+
+    #[test]
+    fn cst_root_should_cover_the_whole_expression() {
+        let cst = Cst::parse("-- c\n(f (x)) {- d -}\n").unwrap();
+        assert_eq!(cst.root().to_string(), "(f (x))");
+        let cst = Cst::parse("\"x -- y\" ").unwrap();
+        assert_eq!(cst.root().to_string(), "\"x -- y\"");
+        assert!(cst.comments().is_empty());
+    }
+
+    #[test]
+    fn cst_should_handle_desugared_expressions() {
+        for input in &[
+            " r with a.b = 1 ",
+            "{ a.b = 1, a.c = 2 } -- done\n",
+            "{ x, y = {- nested {- -} -} 2 }",
+            "λ(`a--b` : Natural) → a--b",
+        ] {
+            let cst = Cst::parse(input).unwrap();
+            assert_eq!(&cst.to_string(), input);
+        }
+    }
+
+    /// Check that every node prints as the source text it covers.
+    fn check_node(input: &str, node: &CstNode) {
+        assert_eq!(node.to_string(), &input[node.start()..node.end()]);
+        node.children().for_each(|child| check_node(input, child));
+    }
+
+    #[test]
+    fn cst_should_print_any_expression_unchanged() {
+        for input in &[
+            "./foo--bar.dhall sha256:0000000000000000000000000000000000000000000000000000000000000000 as Text",
+            "https://example.com/a--b using ({- h -} [] : List { mapKey : Text, mapValue : Text }) ? ./x",
+            "\"a ${ {- c -} x } b\" ++ ''\n  -- kept\n  ${y}\n  ''",
+            "< A | B : Natural >.B 1 ⫽ { x = +1, y = -2.5e3 }.{ x }",
+            "merge { A = 1 } (x : < A >) : Natural\n-- end\n",
+            "\\(x : Bool) -> if x then [ 1, 2 ] # [3] else [] : List Natural",
+            "let f = Natural/fold let `g h` = toMap {=} in assert : f === f",
+        ] {
+            let cst = Cst::parse(input).unwrap();
+            assert_eq!(&cst.to_string(), input);
+            check_node(input, cst.root());
+        }
+    }
+}
@@ -0,0 +1,991 @@
+//! A width-aware pretty printer, in the style of `dhall format`.
+//!
+//! Expressions are first turned into a `Doc`, which describes the places where lines may be
+//! broken, and then rendered by choosing for each group whether it fits on the current line or
+//! must be broken. Parenthesization reuses the phases of the single-line printer.
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+use crate::error::Position;
+use crate::operations::{BinOp, OpKind};
+use crate::syntax::text::printer::{fmt_label, PhasedExpr, PrintPhase};
+use crate::syntax::{
+    Cst, CstElement, CstNode, Expr, ExprKind, InterpolatedText,
+    InterpolatedTextContents, Label, Span, Trivia,
+};
+
+/// Options for `format_expr` and `Cst::format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// The width the printer tries to keep lines within.
+    pub width: usize,
+    /// Use `\`, `->`, `forall`, etc. instead of `λ`, `→`, `∀`, etc.
+    pub ascii: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            width: 80,
+            ascii: false,
+        }
+    }
+}
+
+/// Format an expression over multiple lines. The result has no trailing newline.
+pub fn format_expr(expr: &Expr, options: &FormatOptions) -> String {
+    let printer = Printer {
+        options,
+        comments: None,
+        single_line: false,
+    };
+    render(&printer.expr(expr, PrintPhase::Base), options.width)
+}
+
+/// The error returned by `Cst::format` when formatting would lose some comments.
+#[derive(Debug, Clone)]
+pub struct DroppedComments {
+    /// The comments that are missing from `formatted`, with their position in the input.
+    pub comments: Vec<(Position, Trivia)>,
+    /// The formatted text, without those comments.
+    pub formatted: String,
+}
+
+impl Display for DroppedComments {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (position, comment) = &self.comments[0];
+        write!(
+            f,
+            "formatting would drop {} comment(s), the first one at line {}, column {}: {}",
+            self.comments.len(),
+            position.line,
+            position.column,
+            comment.text().lines().next().unwrap_or(""),
+        )
+    }
+}
+
+impl std::error::Error for DroppedComments {}
+
+impl Cst {
+    /// Format the whole file. Like `dhall format`, this keeps the comments at the start and end
+    /// of the file, as well as those around `let` bindings. Other comments can't be placed in the
+    /// output; if there are any, this returns them along with the text formatted without them.
+    pub fn format(
+        &self,
+        options: &FormatOptions,
+    ) -> Result<String, DroppedComments> {
+        let root = self.root();
+        let mut comments = LetComments::default();
+        collect_let_comments(root, &mut comments);
+        let printer = Printer {
+            options,
+            comments: Some(&comments),
+            single_line: false,
+        };
+
+        let trivia = |elements: &[CstElement]| -> String {
+            elements.iter().map(|element| element.to_string()).collect()
+        };
+        let position = self
+            .elements()
+            .iter()
+            .position(|element| matches!(element, CstElement::Node(_)));
+        let (before, after) = self.elements().split_at(position.unwrap());
+        let header = trivia(before);
+        let footer = trivia(&after[1..]);
+
+        let mut out = String::new();
+        if !header.trim().is_empty() {
+            out.push_str(header.trim());
+            out.push('\n');
+        }
+        out.push_str(&render(
+            &printer.expr(root.expr(), PrintPhase::Base),
+            options.width,
+        ));
+        out.push('\n');
+        if !footer.trim().is_empty() {
+            out.push_str(footer.trim());
+            out.push('\n');
+        }
+
+        let kept = comments.kept.into_inner();
+        let mut all = Vec::new();
+        collect_comments(root, &mut all);
+        let input = self.to_string();
+        let dropped: Vec<_> = all
+            .into_iter()
+            .filter(|(offset, _)| !kept.contains(offset))
+            .map(|(offset, comment)| {
+                let before = &input[..offset];
+                let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+                let position = Position {
+                    line: before.matches('\n').count() + 1,
+                    column: before[line_start..].chars().count() + 1,
+                };
+                (position, comment.clone())
+            })
+            .collect();
+        if dropped.is_empty() {
+            Ok(out)
+        } else {
+            Err(DroppedComments {
+                comments: dropped,
+                formatted: out,
+            })
+        }
+    }
+}
+
+/// The comments kept around `let` bindings, by the start offset of the `let` expression. Each
+/// comment comes with its own offset.
+#[derive(Default)]
+struct LetComments {
+    /// Comments on the lines before the binding.
+    before: HashMap<usize, Vec<(usize, String)>>,
+    /// A comment at the end of the line where the bound value ends.
+    after: HashMap<usize, (usize, String)>,
+    /// The offsets of the comments that were printed.
+    kept: RefCell<HashSet<usize>>,
+}
+
+fn start_offset(e: &Expr) -> Option<usize> {
+    match e.span() {
+        Span::Parsed(span) => Some(span.start()),
+        _ => None,
+    }
+}
+
+/// The byte offset just after `element`, which starts at `offset`.
+fn offset_after(element: &CstElement, offset: usize) -> usize {
+    match element {
+        CstElement::Node(node) => node.end(),
+        CstElement::Token(s) => offset + s.len(),
+        CstElement::Trivia(trivia) => offset + trivia.text().len(),
+    }
+}
+
+/// The comments in `node`, with their offsets.
+fn collect_comments<'a>(node: &'a CstNode, out: &mut Vec<(usize, &'a Trivia)>) {
+    let mut offset = node.start();
+    for element in node.elements() {
+        match element {
+            CstElement::Trivia(trivia) if trivia.is_comment() => {
+                out.push((offset, trivia))
+            }
+            CstElement::Node(child) => collect_comments(child, out),
+            _ => {}
+        }
+        offset = offset_after(element, offset);
+    }
+}
+
+fn collect_let_comments(node: &CstNode, comments: &mut LetComments) {
+    // The value of a `let` is its second-to-last subexpression, before the body.
+    let value_index = match node.expr().kind() {
+        ExprKind::Let(..) => node.children().count().checked_sub(2),
+        _ => None,
+    };
+    let mut pending = Vec::new();
+    let mut after_value = false;
+    let mut index = 0;
+    let mut offset = node.start();
+    for element in node.elements() {
+        match element {
+            CstElement::Trivia(trivia) if trivia.is_comment() => {
+                let comment = (offset, trivia.text().to_owned());
+                match start_offset(node.expr()) {
+                    Some(start) if after_value => {
+                        comments.after.insert(start, comment);
+                    }
+                    _ => pending.push(comment),
+                }
+                after_value = false;
+            }
+            CstElement::Trivia(trivia) => {
+                if trivia.text().contains('\n') {
+                    after_value = false;
+                }
+            }
+            CstElement::Token(_) => {
+                pending.clear();
+                after_value = false;
+            }
+            CstElement::Node(child) => {
+                if let ExprKind::Let(..) = child.expr().kind() {
+                    if let Some(start) = start_offset(child.expr()) {
+                        if !pending.is_empty() {
+                            comments
+                                .before
+                                .insert(start, std::mem::take(&mut pending));
+                        }
+                    }
+                }
+                pending.clear();
+                after_value = Some(index) == value_index;
+                index += 1;
+                collect_let_comments(child, comments);
+            }
+        }
+        offset = offset_after(element, offset);
+    }
+}
+
+/// A document with optional line breaks.
+#[derive(Debug, Clone)]
+enum Doc {
+    Text(String),
+    /// Prints `flat` if the enclosing group fits on the line, otherwise `newlines` newlines
+    /// followed by `broken`.
+    Break {
+        flat: &'static str,
+        newlines: usize,
+        broken: &'static str,
+    },
+    /// Always breaks the line, and forces the enclosing groups to break.
+    Hard(usize),
+    /// Increase the indentation of the lines broken inside.
+    Nest(usize, Box<Doc>),
+    /// Set the indentation of the lines broken inside to the current column.
+    Align(Box<Doc>),
+    /// Print the contents flat if they fit on the line.
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+fn text(s: impl Into<String>) -> Doc {
+    Doc::Text(s.into())
+}
+fn line() -> Doc {
+    Doc::Break {
+        flat: " ",
+        newlines: 1,
+        broken: "",
+    }
+}
+fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+fn nest(indent: usize, doc: Doc) -> Doc {
+    Doc::Nest(indent, Box::new(doc))
+}
+fn align(doc: Doc) -> Doc {
+    Doc::Align(Box::new(doc))
+}
+fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+fn text_width(s: &str) -> isize {
+    s.chars().count() as isize
+}
+
+struct Renderer {
+    out: String,
+    column: usize,
+    /// Indentation is only written before text, so that empty lines stay empty.
+    pending_indent: Option<usize>,
+}
+
+impl Renderer {
+    fn write(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if let Some(indent) = self.pending_indent.take() {
+            self.out.push_str(&" ".repeat(indent));
+        }
+        self.out.push_str(s);
+        self.column += s.chars().count();
+    }
+    fn newlines(&mut self, n: usize, indent: usize) {
+        if n == 0 {
+            return;
+        }
+        self.out.push_str(&"\n".repeat(n));
+        self.column = indent;
+        self.pending_indent = Some(indent);
+    }
+}
+
+fn render(doc: &Doc, width: usize) -> String {
+    let mut renderer = Renderer {
+        out: String::new(),
+        column: 0,
+        pending_indent: None,
+    };
+    // Entries are (indentation, whether to print flat, doc).
+    let mut stack: Vec<(usize, bool, &Doc)> = vec![(0, false, doc)];
+    while let Some((indent, flat, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => renderer.write(s),
+            Doc::Break {
+                flat: flat_text,
+                newlines,
+                broken,
+            } => {
+                if flat {
+                    renderer.write(flat_text);
+                } else {
+                    renderer.newlines(*newlines, indent);
+                    renderer.write(broken);
+                }
+            }
+            Doc::Hard(n) => renderer.newlines(*n, indent),
+            Doc::Nest(i, doc) => stack.push((indent + i, flat, doc)),
+            Doc::Align(doc) => stack.push((renderer.column, flat, doc)),
+            Doc::Group(doc) => {
+                let remaining = width as isize - renderer.column as isize;
+                let flat = flat || fits(remaining, doc, &stack);
+                stack.push((indent, flat, doc))
+            }
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (indent, flat, doc)))
+            }
+        }
+    }
+    renderer.out
+}
+
+/// Whether `doc` printed flat, followed by the rest of the line, fits in `remaining` columns.
+fn fits(mut remaining: isize, doc: &Doc, rest: &[(usize, bool, &Doc)]) -> bool {
+    let mut stack: Vec<(bool, &Doc)> = vec![(true, doc)];
+    let mut rest = rest.iter().rev();
+    while remaining >= 0 {
+        let (flat, doc) = match stack.pop() {
+            Some(entry) => entry,
+            None => match rest.next() {
+                Some((_, flat, doc)) => (*flat, *doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(s) => remaining -= text_width(s),
+            Doc::Break {
+                flat: flat_text,
+                newlines,
+                broken,
+            } => {
+                if flat {
+                    remaining -= text_width(flat_text);
+                } else if *newlines > 0 {
+                    return true;
+                } else {
+                    remaining -= text_width(broken);
+                }
+            }
+            Doc::Hard(_) => return !flat,
+            Doc::Nest(_, doc) | Doc::Align(doc) | Doc::Group(doc) => {
+                stack.push((flat, doc))
+            }
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (flat, doc)))
+            }
+        }
+    }
+    false
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    /// Comments to print around `let` bindings.
+    comments: Option<&'a LetComments>,
+    /// Print text literals on a single line, e.g. inside an interpolation.
+    single_line: bool,
+}
+
+/// Displays an expression on a single line, so we can reuse the `Display` impls of text literals
+/// and imports.
+struct Flat<'a, 'b>(&'b Printer<'a>, PhasedExpr<'b>);
+
+impl Display for Flat<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0.flat(self.1))
+    }
+}
+
+fn label(l: &Label) -> String {
+    struct Quoted<'a>(&'a Label);
+    impl Display for Quoted<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            fmt_label(self.0, f)
+        }
+    }
+    Quoted(l).to_string()
+}
+
+/// Builtins don't need quoting where only a field name can appear, e.g. `Prelude.List`.
+fn field_label(l: &Label) -> String {
+    let s = String::from(l);
+    let is_keyword = matches!(
+        s.as_str(),
+        "if" | "then"
+            | "else"
+            | "let"
+            | "in"
+            | "using"
+            | "missing"
+            | "assert"
+            | "as"
+            | "Infinity"
+            | "NaN"
+            | "merge"
+            | "Some"
+            | "toMap"
+            | "forall"
+            | "with"
+    );
+    let starts_well = match s.chars().next() {
+        Some(c) => c.is_ascii_alphabetic() || c == '_',
+        None => false,
+    };
+    let is_simple = s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if starts_well && is_simple && !is_keyword {
+        s
+    } else {
+        label(l)
+    }
+}
+
+impl<'a> Printer<'a> {
+    fn lambda(&self) -> &'static str {
+        if self.options.ascii {
+            "\\"
+        } else {
+            "λ"
+        }
+    }
+    fn arrow(&self) -> &'static str {
+        if self.options.ascii {
+            "->"
+        } else {
+            "→"
+        }
+    }
+    fn forall(&self) -> &'static str {
+        if self.options.ascii {
+            "forall"
+        } else {
+            "∀"
+        }
+    }
+    fn binop(&self, op: BinOp) -> String {
+        use BinOp::*;
+        match op {
+            RecursiveRecordMerge if self.options.ascii => "/\\".to_owned(),
+            RecursiveRecordTypeMerge if self.options.ascii => {
+                "//\\\\".to_owned()
+            }
+            RightBiasedRecordMerge if self.options.ascii => "//".to_owned(),
+            Equivalence if self.options.ascii => "===".to_owned(),
+            op => op.to_string(),
+        }
+    }
+
+    fn flat(&self, e: PhasedExpr) -> String {
+        let printer = Printer {
+            options: self.options,
+            comments: None,
+            single_line: true,
+        };
+        render(&printer.expr(e.0, e.1), isize::MAX as usize)
+    }
+
+    fn phased(&self, e: PhasedExpr) -> Doc {
+        self.expr(e.0, e.1)
+    }
+
+    fn expr(&self, e: &Expr, phase: PrintPhase) -> Doc {
+        let doc = self.kind(e);
+        if e.kind().needs_paren(phase) {
+            concat(vec![text("("), align(doc), text(")")])
+        } else {
+            doc
+        }
+    }
+
+    fn kind(&self, e: &Expr) -> Doc {
+        use ExprKind::*;
+        use OpKind::*;
+        match e.kind().annotate_with_phases() {
+            Var(v) => text(v.to_string()),
+            Const(k) => text(k.to_string()),
+            Builtin(b) => text(b.to_string()),
+            Num(n) => text(n.to_string()),
+            TextLit(t) => match self.multiline_text(&t) {
+                Some(doc) => doc,
+                None => text(t.map_ref(|e| Flat(self, *e)).to_string()),
+            },
+            Import(import) => {
+                text(import.map_ref(|e| Flat(self, *e)).to_string())
+            }
+            Lam(l, t, body) => self.binder(self.lambda(), &l, t, body),
+            Pi(l, t, body) if String::from(&l) != "_" => {
+                self.binder(self.forall(), &l, t, body)
+            }
+            Pi(_, t, body) => group(align(concat(vec![
+                self.phased(t),
+                line(),
+                text(format!("{} ", self.arrow())),
+                align(self.phased(body)),
+            ]))),
+            Let(..) => self.let_chain(e),
+            SomeLit(x) => group(align(concat(vec![
+                text("Some"),
+                nest(2, concat(vec![line(), self.phased(x)])),
+            ]))),
+            EmptyListLit(t) => {
+                concat(vec![text("[] : "), align(self.phased(t))])
+            }
+            NEListLit(items) => sequence(
+                "[",
+                ",",
+                "]",
+                items.into_iter().map(|x| self.phased(x)).collect(),
+            ),
+            RecordLit(fields) if fields.is_empty() => text("{=}"),
+            RecordLit(fields) => sequence(
+                "{",
+                ",",
+                "}",
+                self.sorted(fields.into_iter().collect())
+                    .into_iter()
+                    .map(|(l, x)| self.field(&l, "=", x))
+                    .collect(),
+            ),
+            RecordType(fields) if fields.is_empty() => text("{}"),
+            RecordType(fields) => sequence(
+                "{",
+                ",",
+                "}",
+                self.sorted(fields.into_iter().collect())
+                    .into_iter()
+                    .map(|(l, x)| self.field(&l, ":", x))
+                    .collect(),
+            ),
+            UnionType(alts) if alts.is_empty() => text("<>"),
+            UnionType(alts) => sequence(
+                "<",
+                "|",
+                ">",
+                alts.into_iter()
+                    .map(|(l, x)| match x {
+                        Some(x) => self.field(&l, ":", x),
+                        None => text(field_label(&l)),
+                    })
+                    .collect(),
+            ),
+            Annot(x, t) => group(align(concat(vec![
+                self.phased(x),
+                line(),
+                text(": "),
+                align(self.phased(t)),
+            ]))),
+            Assert(t) => concat(vec![text("assert : "), align(self.phased(t))]),
+            Op(App(f, a)) => {
+                let mut args = vec![a];
+                let mut head = f;
+                while let Op(App(f, a)) = head.0.kind() {
+                    args.push(PhasedExpr(a, PrintPhase::Import));
+                    head = PhasedExpr(f, PrintPhase::App);
+                }
+                let args = args
+                    .into_iter()
+                    .rev()
+                    .flat_map(|x| vec![line(), self.phased(x)])
+                    .collect();
+                group(align(concat(vec![
+                    self.phased(head),
+                    nest(2, concat(args)),
+                ])))
+            }
+            Op(BinOp(op, l, r)) => {
+                let mut operands = vec![r];
+                let mut head = l;
+                while let Op(BinOp(op2, l, r)) = head.0.kind() {
+                    if *op2 != op || head.0.kind().needs_paren(head.1) {
+                        break;
+                    }
+                    operands.push(PhasedExpr(r, head.1));
+                    head = PhasedExpr(l, head.1);
+                }
+                let op = self.binop(op);
+                let mut docs = vec![self.phased(head)];
+                for x in operands.into_iter().rev() {
+                    docs.push(line());
+                    docs.push(text(format!("{} ", op)));
+                    docs.push(align(self.phased(x)));
+                }
+                group(align(concat(docs)))
+            }
+            Op(BoolIf(c, t, f)) => group(align(concat(vec![
+                text("if "),
+                align(self.phased(c)),
+                line(),
+                text("then "),
+                align(self.phased(t)),
+                line(),
+                text("else "),
+                align(self.phased(f)),
+            ]))),
+            Op(Merge(x, y, t)) => {
+                let mut docs = vec![
+                    text("merge"),
+                    nest(
+                        2,
+                        concat(vec![
+                            line(),
+                            self.phased(x),
+                            line(),
+                            self.phased(y),
+                        ]),
+                    ),
+                ];
+                if let Some(t) = t {
+                    docs.extend(vec![
+                        line(),
+                        text(": "),
+                        align(self.phased(t)),
+                    ]);
+                }
+                group(align(concat(docs)))
+            }
+            Op(ToMap(x, t)) => {
+                let mut docs = vec![
+                    text("toMap"),
+                    nest(2, concat(vec![line(), self.phased(x)])),
+                ];
+                if let Some(t) = t {
+                    docs.extend(vec![
+                        line(),
+                        text(": "),
+                        align(self.phased(t)),
+                    ]);
+                }
+                group(align(concat(docs)))
+            }
+            Op(Field(x, l)) => concat(vec![
+                self.phased(x),
+                text(format!(".{}", field_label(&l))),
+            ]),
+            Op(Projection(x, ls)) => {
+                let ls: Vec<_> = ls.iter().map(field_label).collect();
+                let ls = if ls.is_empty() {
+                    "{}".to_owned()
+                } else {
+                    format!("{{ {} }}", ls.join(", "))
+                };
+                concat(vec![self.phased(x), text("."), text(ls)])
+            }
+            Op(ProjectionByExpr(x, t)) => concat(vec![
+                self.phased(x),
+                text(".("),
+                align(self.phased(t)),
+                text(")"),
+            ]),
+            Op(Completion(x, y)) => {
+                concat(vec![self.phased(x), text("::"), self.phased(y)])
+            }
+        }
+    }
+
+    /// `λ(x : T) → body` or `∀(x : T) → body`.
+    fn binder(
+        &self,
+        keyword: &str,
+        l: &Label,
+        t: PhasedExpr,
+        body: PhasedExpr,
+    ) -> Doc {
+        // Don't indent chains of binders.
+        let indent = match body.0.kind() {
+            ExprKind::Lam(..) => 0,
+            ExprKind::Pi(l, _, _) if String::from(l) != "_" => 0,
+            _ => 2,
+        };
+        group(align(concat(vec![
+            text(format!("{}({} : ", keyword, label(l))),
+            align(self.phased(t)),
+            text(format!(") {}", self.arrow())),
+            nest(indent, concat(vec![line(), self.phased(body)])),
+        ])))
+    }
+
+    /// `x = value` or `x : type`, breaking after the separator if needed.
+    fn field(&self, l: &Label, separator: &str, x: PhasedExpr) -> Doc {
+        group(concat(vec![
+            text(format!("{} {}", field_label(l), separator)),
+            nest(4, concat(vec![line(), self.phased(x)])),
+        ]))
+    }
+
+    /// Keep record fields in the order they were written, if we know it.
+    fn sorted<'b>(
+        &self,
+        mut fields: Vec<(Label, PhasedExpr<'b>)>,
+    ) -> Vec<(Label, PhasedExpr<'b>)> {
+        let positions: Option<Vec<usize>> =
+            fields.iter().map(|(_, x)| source_position(x.0)).collect();
+        if let Some(positions) = positions {
+            let mut indexed: Vec<_> =
+                positions.into_iter().zip(fields).collect();
+            indexed.sort_by_key(|(pos, _)| *pos);
+            fields = indexed.into_iter().map(|(_, field)| field).collect();
+        }
+        fields
+    }
+
+    /// A chain of `let` bindings, always on multiple lines.
+    fn let_chain(&self, mut e: &Expr) -> Doc {
+        let mut docs = Vec::new();
+        while let ExprKind::Let(l, t, v, body) = e.kind() {
+            docs.extend(self.comments_before(e));
+            let mut binding = vec![text(format!("let {}", label(l)))];
+            if let Some(t) = t {
+                binding.push(text(" : "));
+                binding.push(align(self.expr(t, PrintPhase::Base)));
+            }
+            binding.push(text(" ="));
+            binding.push(nest(
+                6,
+                concat(vec![line(), self.expr(v, PrintPhase::Base)]),
+            ));
+            docs.push(group(concat(binding)));
+            if let Some(comment) = self.comment_after(e) {
+                docs.push(text(format!(" {}", comment)));
+            }
+            docs.push(Doc::Hard(2));
+            e = body;
+        }
+        docs.push(text("in  "));
+        docs.push(align(self.expr(e, PrintPhase::Base)));
+        align(concat(docs))
+    }
+
+    /// A comment at the end of the line of a `let` binding.
+    fn comment_after(&self, e: &Expr) -> Option<&str> {
+        let start = start_offset(e)?;
+        let comments = self.comments?;
+        let (offset, comment) = comments.after.get(&start)?;
+        // A block comment spanning several lines would not be idempotent.
+        if comment.contains('\n') {
+            None
+        } else {
+            comments.kept.borrow_mut().insert(*offset);
+            Some(comment.trim_end())
+        }
+    }
+
+    fn comments_before(&self, e: &Expr) -> Vec<Doc> {
+        let comments = match self.comments {
+            Some(comments) => comments,
+            None => return Vec::new(),
+        };
+        let before = match start_offset(e).and_then(|s| comments.before.get(&s))
+        {
+            Some(before) => before,
+            None => return Vec::new(),
+        };
+        let mut docs = Vec::new();
+        for (offset, comment) in before {
+            comments.kept.borrow_mut().insert(*offset);
+            let mut lines = comment.trim_end().split('\n');
+            docs.push(text(lines.next().unwrap().trim_end()));
+            // Continuation lines of block comments keep their relative indentation.
+            let rest: Vec<&str> = lines.map(str::trim_end).collect();
+            let dedent = rest
+                .iter()
+                .filter(|l| !l.is_empty())
+                .map(|l| l.len() - l.trim_start().len())
+                .min()
+                .unwrap_or(0);
+            for l in rest {
+                docs.push(Doc::Hard(1));
+                if !l.is_empty() {
+                    docs.push(text(&l[dedent..]));
+                }
+            }
+            docs.push(Doc::Hard(1));
+        }
+        docs
+    }
+
+    /// Print text containing newlines as a `''` literal, if it can be.
+    fn multiline_text(&self, t: &InterpolatedText<PhasedExpr>) -> Option<Doc> {
+        let chunks: Vec<_> = t.iter().collect();
+        let texts = || {
+            chunks.iter().filter_map(|chunk| match chunk {
+                InterpolatedTextContents::Text(s) => Some(s),
+                InterpolatedTextContents::Expr(_) => None,
+            })
+        };
+        let has_newline = texts().any(|s| s.contains('\n'));
+        let printable = texts().all(|s| {
+            s.chars().all(|c| !c.is_control() || c == '\n' || c == '\t')
+        });
+        // The closing `''` goes on its own line, at the indentation of the contents, so that it
+        // doesn't change how much indentation is stripped; this needs a final newline.
+        let ends_with_newline = texts().next_back().unwrap().ends_with('\n');
+        if self.single_line || !has_newline || !printable || !ends_with_newline
+        {
+            return None;
+        }
+
+        let mut docs = vec![text("''"), Doc::Hard(1)];
+        for chunk in chunks {
+            match chunk {
+                InterpolatedTextContents::Text(s) => {
+                    let escaped = s.replace("''", "'''").replace("${", "''${");
+                    for (i, l) in escaped.split('\n').enumerate() {
+                        if i > 0 {
+                            docs.push(Doc::Hard(1));
+                        }
+                        docs.push(text(l));
+                    }
+                }
+                InterpolatedTextContents::Expr(e) => {
+                    docs.push(text(format!("${{ {} }}", self.flat(*e))))
+                }
+            }
+        }
+        docs.push(text("''"));
+        Some(align(concat(docs)))
+    }
+}
+
+/// Where an expression starts in the source, looking through desugared expressions.
+fn source_position(e: &Expr) -> Option<usize> {
+    match e.span() {
+        Span::Parsed(span) => Some(span.start()),
+        _ => {
+            let mut min = None;
+            e.kind().map_ref(|x| {
+                if let Some(pos) = source_position(x) {
+                    min = Some(min.map_or(pos, |min: usize| min.min(pos)));
+                }
+            });
+            min
+        }
+    }
+}
+
+/// `{ a, b }`, or one item per line with leading separators.
+fn sequence(open: &str, separator: &str, close: &str, items: Vec<Doc>) -> Doc {
+    let gap = if separator == "," { "" } else { " " };
+    let mut docs = vec![text(format!("{} ", open))];
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            docs.push(Doc::Break {
+                flat: gap,
+                newlines: 1,
+                broken: "",
+            });
+            docs.push(text(format!("{} ", separator)));
+        }
+        docs.push(align(item));
+    }
+    docs.push(line());
+    docs.push(text(close));
+    group(align(concat(docs)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::parse_expr;
+
+    fn format(input: &str, width: usize, ascii: bool) -> String {
+        let options = FormatOptions { width, ascii };
+        Cst::parse(input).unwrap().format(&options).unwrap()
+    }
+
+    #[test]
+    fn format_should_break_long_expressions() {
+        let input = "-- config\nlet xs = [1,2,3] -- the numbers\n-- the record\nlet r = { name = \"foo\", value = xs, nested = { enabled = True, count = 2 } } in \\(x : Natural) -> if Natural/isZero x then r else r // { value = List/reverse Natural xs }";
+        let expected = "\
+-- config
+let xs = [ 1, 2, 3 ] -- the numbers
+
+-- the record
+let r =
+      { name = \"foo\"
+      , value = xs
+      , nested = { enabled = True, count = 2 }
+      }
+
+in  \\(x : Natural) ->
+      if Natural/isZero x
+      then r
+      else r // { value = List/reverse Natural xs }
+";
+        assert_eq!(format(input, 60, true), expected);
+        assert_eq!(
+            format("λ(x : Bool) → x ∧ x", 80, false),
+            "λ(x : Bool) → x ∧ x\n"
+        );
+        assert_eq!(
+            format("{ text = \"a\\nb\\n${x}\\n\" }", 80, false),
+            "{ text =\n      ''\n      a\n      b\n      ${ x }\n      ''\n}\n"
+        );
+        // Without a final newline, the closing `''` would change the indentation.
+        assert_eq!(
+            format("{ text = \"  a\\n  b\" }", 80, false),
+            "{ text = \"  a\\n  b\" }\n"
+        );
+    }
+
+    #[test]
+    fn format_should_be_idempotent_and_preserve_meaning() {
+        let inputs = [
+            "let f = λ(a : Type) → λ(xs : List a) → List/length a xs in f Natural [ 1, 2, 3 ] + f Bool ([] : List Bool)",
+            "{ a = { b = { c = [ { d = 1, e = \"x\" }, { d = 2, e = \"y\" } ] } }, f = < A | B : Natural >.B 3 }",
+            "merge { A = \"a\", B = λ(n : Natural) → Natural/show n } (< A | B : Natural >.A) : Text",
+            "∀(a : Type) → (a → a → Bool) → List a → Optional a",
+            "toMap { foo = 1, bar = 2 } : List { mapKey : Text, mapValue : Natural }",
+            "\"tab\\there ''quoted'' ${\"nested\"} \\${not} end\\n\" ++ \"no newline\"",
+            "let x : Natural = 1 let y = x + x * 2 in assert : y ≡ 3",
+            "./foo.dhall sha256:0000000000000000000000000000000000000000000000000000000000000000 ? env:HOME as Text ? missing",
+            "(r with a.b = 1).{ a, c } ⫽ r.(T)::{ x = Some 1 }",
+            "{- block\n     comment -}\nlet a = 1\n\n{- inner\n     block -}\nlet b = a\n\nin  b\n-- trailing\n",
+            "{ a = \"  a\\n  b\", b = \"  a\\n  b\\n\", c = \"  ${x}\\n  y\\n\", d = \"\\n\\ta\\n\" }",
+        ];
+        for input in inputs.iter() {
+            for &width in &[10, 40, 80] {
+                for &ascii in &[false, true] {
+                    let once = format(input, width, ascii);
+                    assert_eq!(
+                        parse_expr(&once)
+                            .unwrap_or_else(|e| panic!("{}\n{}", once, e)),
+                        parse_expr(input).unwrap(),
+                        "{}",
+                        once
+                    );
+                    assert_eq!(format(&once, width, ascii), once);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn format_should_refuse_to_drop_comments() {
+        let input =
+            "let x = 1\nin  { a = x -- the field\n, b = {- inline -} 2 }\n";
+        let options = FormatOptions::default();
+        let err = Cst::parse(input).unwrap().format(&options).unwrap_err();
+        let comments: Vec<_> = err
+            .comments
+            .iter()
+            .map(|(position, comment)| {
+                (position.line, position.column, comment.text())
+            })
+            .collect();
+        assert_eq!(
+            comments,
+            vec![(2, 13, "-- the field"), (3, 7, "{- inline -}")]
+        );
+        assert_eq!(err.formatted, "let x = 1\n\nin  { a = x, b = 2 }\n");
+        assert!(err.to_string().contains("line 2, column 13"));
+    }
+}
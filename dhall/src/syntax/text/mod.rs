@@ -1,2 +1,4 @@
+pub mod cst;
+pub mod format;
 pub mod parser;
 pub mod printer;
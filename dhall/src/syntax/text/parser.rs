@@ -2,18 +2,20 @@ use itertools::Itertools;
 use pest::prec_climber as pcl;
 use pest::prec_climber::PrecClimber;
 use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
 use std::iter::once;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use pest_consume::{match_nodes, Parser};
 
 use crate::operations::OpKind::*;
+use crate::semantics::resolve::ImportLocation;
 use crate::syntax::ExprKind::*;
 use crate::syntax::NumKind::*;
 use crate::syntax::{
     Double, Expr, FilePath, FilePrefix, Hash, ImportMode, ImportTarget,
     Integer, InterpolatedText, InterpolatedTextContents, Label, NaiveDouble,
-    Natural, Scheme, Span, UnspannedExpr, URL, V,
+    Natural, Scheme, SourceFile, Span, UnspannedExpr, URL, V,
 };
 
 // This file consumes the parse tree generated by pest and turns it into
@@ -23,7 +25,7 @@ use crate::syntax::{
 
 type ParsedText = InterpolatedText<Expr>;
 type ParsedTextContents = InterpolatedTextContents<Expr>;
-type ParseInput<'input> = pest_consume::Node<'input, Rule, Rc<str>>;
+type ParseInput<'input> = pest_consume::Node<'input, Rule, Arc<SourceFile>>;
 
 pub type ParseError = pest::error::Error<Rule>;
 pub type ParseResult<T> = Result<T, ParseError>;
@@ -396,26 +398,25 @@ impl DhallParser {
 
     fn natural_literal(input: ParseInput) -> ParseResult<Natural> {
         let s = input.as_str().trim();
-        if s.starts_with("0x") {
-            let without_prefix = s.trim_start_matches("0x");
-            usize::from_str_radix(without_prefix, 16)
-                .map_err(|e| input.error(format!("{}", e)))
+        let n = if s.starts_with("0x") {
+            Natural::from_str_radix(s.trim_start_matches("0x"), 16)
         } else {
-            s.parse().map_err(|e| input.error(format!("{}", e)))
-        }
+            Natural::from_str_radix(s, 10)
+        };
+        n.ok_or_else(|| input.error(format!("Invalid natural literal '{}'", s)))
     }
 
     fn integer_literal(input: ParseInput) -> ParseResult<Integer> {
         let s = input.as_str().trim();
         let (sign, rest) = (&s[0..1], &s[1..]);
-        if rest.starts_with("0x") {
+        let n = if rest.starts_with("0x") {
             let without_prefix =
                 sign.to_owned() + rest.trim_start_matches("0x");
-            isize::from_str_radix(&without_prefix, 16)
-                .map_err(|e| input.error(format!("{}", e)))
+            Integer::from_str_radix(&without_prefix, 16)
         } else {
-            s.parse().map_err(|e| input.error(format!("{}", e)))
-        }
+            Integer::from_str_radix(s, 10)
+        };
+        n.ok_or_else(|| input.error(format!("Invalid integer literal '{}'", s)))
     }
 
     #[alias(expression, shortcut = true)]
@@ -427,8 +428,14 @@ impl DhallParser {
     }
 
     fn variable(input: ParseInput) -> ParseResult<V> {
-        Ok(match_nodes!(input.into_children();
-            [label(l), natural_literal(idx)] => V(l, idx),
+        Ok(match_nodes!(input.children();
+            [label(l), natural_literal(idx)] => {
+                let idx = idx.to_u64().and_then(|idx| usize::try_from(idx).ok());
+                let idx = idx.ok_or_else(|| {
+                    input.error("Variable index is too large".to_owned())
+                })?;
+                V(l, idx)
+            },
             [label(l)] => V(l, 0),
         ))
     }
@@ -690,14 +697,21 @@ impl DhallParser {
                 spanned(input, Op(BoolIf(cond, left, right)))
             },
             [let_binding(bindings).., expression(final_expr)] => {
+                // Each `let` extends to the end of the whole expression, even when the body was
+                // desugared and has no span of its own.
+                let whole = input_to_span(input);
                 bindings.rev().fold(
                     final_expr,
                     |acc, x| {
-                        spanned_union(
-                            acc.span(),
-                            x.3,
-                            Let(x.0, x.1, x.2, acc)
-                        )
+                        let span = match (&x.3, &whole) {
+                            (Span::Parsed(binding), Span::Parsed(whole)) => {
+                                Span::Parsed(
+                                    whole.between(binding.start(), whole.end())
+                                )
+                            }
+                            _ => x.3.union(&acc.span()),
+                        };
+                        Expr::new(Let(x.0, x.1, x.2, acc), span)
                     }
                 )
             },
@@ -1039,17 +1053,68 @@ impl DhallParser {
 }
 
 pub fn parse_expr(input_str: &str) -> ParseResult<Expr> {
-    let rc_input_str = input_str.to_string().into();
+    parse_expr_at(input_str, ImportLocation::Missing)
+}
+
+/// Parse an expression read from `location`. Spans of the result remember that location.
+pub fn parse_expr_at(
+    input_str: &str,
+    location: ImportLocation,
+) -> ParseResult<Expr> {
+    let source = Arc::new(SourceFile::new(input_str.to_string(), location));
     let inputs = DhallParser::parse_with_userdata(
         Rule::final_expression,
         input_str,
-        rc_input_str,
+        source,
     )?;
     Ok(match_nodes!(<DhallParser>; inputs;
         [expression(e)] => e,
     ))
 }
 
+/// Parse a single `let x : T = e` binding, without the `in` part, e.g. as typed in the REPL.
+pub fn parse_let_binding_at(
+    input_str: &str,
+    location: ImportLocation,
+) -> ParseResult<(Label, Option<Expr>, Expr)> {
+    let source = Arc::new(SourceFile::new(input_str.to_string(), location));
+    let inputs =
+        DhallParser::parse_with_userdata(Rule::let_binding, input_str, source)?;
+    let end = inputs.clone().single()?.as_span().end();
+    if end != input_str.len() {
+        return Err(ParseError::new_from_pos(
+            pest::error::ErrorVariant::CustomError {
+                message: "expected the end of the binding".to_owned(),
+            },
+            pest::Position::new(input_str, end).unwrap(),
+        ));
+    }
+    Ok(match_nodes!(<DhallParser>; inputs;
+        [let_binding((name, annot, value, _))] => (name, annot, value),
+    ))
+}
+
+/// The byte range of the hash in the source text of an import, or the empty range just after its
+/// location and headers if it has no hash.
+pub(crate) fn import_hash_range(
+    import_str: &str,
+) -> ParseResult<(usize, usize)> {
+    let source = Arc::new(SourceFile::new(
+        import_str.to_string(),
+        ImportLocation::Missing,
+    ));
+    let import =
+        DhallParser::parse_with_userdata(Rule::import, import_str, source)?
+            .single()?;
+    // `import_hashed` is the first child of `import`, and contains the location then the hash.
+    let mut parts = import.into_children().next().unwrap().into_children();
+    let location_end = parts.next().unwrap().as_span().end();
+    Ok(match parts.next() {
+        Some(hash) => (hash.as_span().start(), hash.as_span().end()),
+        None => (location_end, location_end),
+    })
+}
+
 #[test]
 #[cfg_attr(windows, ignore)]
 // Check that the local copy of the grammar file is in sync with the one from dhall-lang.
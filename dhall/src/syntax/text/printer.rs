@@ -10,7 +10,7 @@ use std::fmt::{self, Display};
 // of automatically getting all the parentheses and precedences right (in a manner dual do Pratt
 // parsing).
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
-enum PrintPhase {
+pub(crate) enum PrintPhase {
     // `expression`
     Base,
     // `operator-expression`
@@ -28,7 +28,7 @@ enum PrintPhase {
 // Wraps an Expr with a phase, so that phase selection can be done separate from the actual
 // printing.
 #[derive(Copy, Clone)]
-struct PhasedExpr<'a>(&'a Expr, PrintPhase);
+pub(crate) struct PhasedExpr<'a>(pub(crate) &'a Expr, pub(crate) PrintPhase);
 
 impl<'a> PhasedExpr<'a> {
     fn phase(self, phase: PrintPhase) -> PhasedExpr<'a> {
@@ -38,7 +38,7 @@ impl<'a> PhasedExpr<'a> {
 
 impl UnspannedExpr {
     // Annotate subexpressions with the appropriate phase, defaulting to Base
-    fn annotate_with_phases(&self) -> ExprKind<PhasedExpr<'_>> {
+    pub(crate) fn annotate_with_phases(&self) -> ExprKind<PhasedExpr<'_>> {
         use ExprKind::*;
         use OpKind::*;
         use PrintPhase::*;
@@ -86,15 +86,11 @@ impl UnspannedExpr {
         }
     }
 
-    fn fmt_phase(
-        &self,
-        f: &mut fmt::Formatter,
-        phase: PrintPhase,
-    ) -> Result<(), fmt::Error> {
+    // Whether the expression must be wrapped in parentheses when printed in the given phase.
+    pub(crate) fn needs_paren(&self, phase: PrintPhase) -> bool {
         use ExprKind::*;
         use OpKind::*;
-
-        let needs_paren = match self {
+        match self {
             Lam(_, _, _)
             | Pi(_, _, _)
             | Let(_, _, _, _)
@@ -110,8 +106,15 @@ impl UnspannedExpr {
             Op(App(_, _)) => phase > PrintPhase::App,
             Op(Completion(_, _)) => phase > PrintPhase::Import,
             _ => false,
-        };
+        }
+    }
 
+    fn fmt_phase(
+        &self,
+        f: &mut fmt::Formatter,
+        phase: PrintPhase,
+    ) -> Result<(), fmt::Error> {
+        let needs_paren = self.needs_paren(phase);
         if needs_paren {
             f.write_str("(")?;
         }
@@ -146,7 +149,10 @@ where
     f.write_str(close)
 }
 
-fn fmt_label(label: &Label, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+pub(crate) fn fmt_label(
+    label: &Label,
+    f: &mut fmt::Formatter,
+) -> Result<(), fmt::Error> {
     // TODO: distinguish between reserved and nonreserved locations for quoting builtins
     let s = String::from(label);
     let is_reserved = match s.as_str() {
@@ -293,7 +299,7 @@ impl Display for NumKind {
             Bool(true) => f.write_str("True")?,
             Bool(false) => f.write_str("False")?,
             Natural(a) => a.fmt(f)?,
-            Integer(a) if *a >= 0 => {
+            Integer(a) if !a.is_negative() => {
                 f.write_str("+")?;
                 a.fmt(f)?;
             }
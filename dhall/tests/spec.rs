@@ -442,13 +442,6 @@ fn define_features() -> Vec<TestFeature> {
             module_name: "binary_decoding_success",
             directory: "binary-decode/success/",
             variant: SpecTestKind::BinaryDecodingSuccess,
-            exclude_path: Rc::new(|path: &str| {
-                false
-                    // We don't support bignums
-                    || path == "unit/IntegerBigNegative"
-                    || path == "unit/IntegerBigPositive"
-                    || path == "unit/NaturalBig"
-            }),
             input_type: FileType::Binary,
             ..default_feature.clone()
         },
@@ -468,10 +461,6 @@ fn define_features() -> Vec<TestFeature> {
                 false
                     // TODO: the standard does not respect https://tools.ietf.org/html/rfc3986#section-5.2
                     || path == "unit/asLocation/RemoteCanonicalize4"
-                    // TODO: import headers
-                    || path == "customHeaders"
-                    || path == "headerForwarding"
-                    || path == "noHeaderForwarding"
                     // TODO: git changes newlines on windows
                     || (cfg!(windows) && path == "unit/AsText")
                     || (cfg!(windows) && path == "unit/QuotedPath")
@@ -489,8 +478,6 @@ fn define_features() -> Vec<TestFeature> {
                 false
                     // TODO: paths on windows have backslashes; this breaks many things
                     || cfg!(windows)
-                    // TODO: import headers
-                    || path == "customHeadersUsingBoundVariable"
             }),
             output_type: FileType::UI,
             ..default_feature.clone()
@@ -499,10 +486,6 @@ fn define_features() -> Vec<TestFeature> {
             module_name: "semantic_hash",
             directory: "semantic-hash/success/",
             variant: SpecTestKind::SemanticHash,
-            exclude_path: Rc::new(|path: &str| {
-                // We don't support bignums
-                path == "simple/integerToDouble"
-            }),
             output_type: FileType::Hash,
             ..default_feature.clone()
         },
@@ -515,8 +498,6 @@ fn define_features() -> Vec<TestFeature> {
                 false
                     // Cannot typecheck
                     || path == "unit/Sort"
-                    // We don't support bignums
-                    || path == "simple/integerToDouble"
                     // TODO: fix Double/show
                     || path == "prelude/JSON/number/1"
             }),
@@ -0,0 +1,494 @@
+//! The `dhall` command-line tool.
+//!
+//! Each subcommand reads an expression from the given file, or from standard input if no file is
+//! given. On failure the error is printed to standard error, as text or in the format given by
+//! `--error-format`, and the exit code indicates what kind of error happened; see `exit_code`.
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use structopt::StructOpt;
+
+mod repl;
+
+use dhall::error::{to_sarif, Diagnostic, Error, ErrorKind};
+use dhall::semantics::{
+    freeze_file, freeze_text, lint, DefaultFetcher, FreezeOptions,
+    ImportLocation,
+};
+use dhall::syntax::{binary, Cst, FormatOptions};
+use dhall::{Parsed, Typed};
+use serde_dhall::{JsonOptions, SimpleType, Value};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "dhall", about = "Interpret Dhall expressions")]
+struct Opts {
+    /// How to print errors: `human`, `json` or `sarif`
+    #[structopt(long, default_value = "human")]
+    error_format: ErrorFormat,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ErrorFormat {
+    /// Text with source snippets
+    Human,
+    /// A JSON-serialized `Diagnostic`
+    Json,
+    /// A SARIF 2.1.0 log with a single result
+    Sarif,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            "sarif" => Ok(ErrorFormat::Sarif),
+            _ => Err(format!("unknown error format: `{}`", s)),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Normalize an expression
+    Normalize {
+        /// Alpha-normalize the output, i.e. rename all variables to `_`
+        #[structopt(long)]
+        alpha: bool,
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Infer the type of an expression
+    Type {
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Resolve the imports of an expression
+    Resolve {
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Compute the semantic hash of an expression
+    Hash {
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Encode an expression to the binary format
+    Encode {
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Decode an expression from the binary format
+    Decode {
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Add integrity checks to remote imports. Files are modified in place.
+    Freeze {
+        /// Add integrity checks to all imports, not only remote ones
+        #[structopt(long)]
+        all: bool,
+        /// Store the frozen expressions in the cache
+        #[structopt(long)]
+        cache: bool,
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Format an expression. Files are modified in place.
+    Format {
+        /// Use ASCII syntax, e.g. `\\` and `->` instead of `λ` and `→`
+        #[structopt(long)]
+        ascii: bool,
+        /// The line width to stay within when possible
+        #[structopt(long, default_value = "80")]
+        width: usize,
+        /// Only check that the input is formatted, and fail if it isn't
+        #[structopt(long)]
+        check: bool,
+        /// Format even if some comments are in places where they can't be kept. Otherwise, the
+        /// input is left unchanged.
+        #[structopt(long)]
+        drop_comments: bool,
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Report unused bindings, redundant annotations, removed builtins, etc. Exits with code 1 if
+    /// problems are found.
+    Lint {
+        /// Fix the problems that can be fixed automatically. Files are modified in place.
+        #[structopt(long)]
+        fix: bool,
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Evaluate expressions interactively. Type `:help` for the list of commands.
+    Repl,
+    /// Convert an expression to JSON
+    ToJson {
+        #[structopt(flatten)]
+        options: ConvertOptions,
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Convert an expression to YAML
+    ToYaml {
+        #[structopt(flatten)]
+        options: ConvertOptions,
+        /// If the expression is a list, output each element as a separate YAML document
+        #[structopt(long)]
+        documents: bool,
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Convert a JSON document to a Dhall expression of the given type
+    FromJson {
+        /// The expected type, as a Dhall expression, e.g. `./schema.dhall`
+        schema: String,
+        #[structopt(flatten)]
+        input: Input,
+    },
+    /// Convert a YAML document to a Dhall expression of the given type
+    FromYaml {
+        /// The expected type, as a Dhall expression, e.g. `./schema.dhall`
+        schema: String,
+        #[structopt(flatten)]
+        input: Input,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct ConvertOptions {
+    /// Omit record fields that are null or empty records
+    #[structopt(long)]
+    omit_empty: bool,
+    /// Keep record fields that are null instead of omitting them
+    #[structopt(long)]
+    preserve_null: bool,
+}
+
+impl Command {
+    fn input(&self) -> Option<&Input> {
+        Some(match self {
+            Command::Normalize { input, .. }
+            | Command::Type { input }
+            | Command::Resolve { input }
+            | Command::Hash { input }
+            | Command::Encode { input }
+            | Command::Decode { input }
+            | Command::Freeze { input, .. }
+            | Command::Format { input, .. }
+            | Command::Lint { input, .. }
+            | Command::ToJson { input, .. }
+            | Command::ToYaml { input, .. }
+            | Command::FromJson { input, .. }
+            | Command::FromYaml { input, .. } => input,
+            Command::Repl => return None,
+        })
+    }
+}
+
+impl ConvertOptions {
+    fn to_json_options(&self, documents: bool) -> JsonOptions {
+        JsonOptions {
+            omit_empty: self.omit_empty,
+            preserve_null: self.preserve_null,
+            documents,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct Input {
+    /// The file to read. Reads from standard input if absent.
+    #[structopt(parse(from_os_str))]
+    file: Option<PathBuf>,
+}
+
+impl Input {
+    fn read_text(&self) -> Result<String, Error> {
+        let mut text = String::new();
+        match &self.file {
+            Some(path) => text = std::fs::read_to_string(path)?,
+            None => {
+                io::stdin().read_to_string(&mut text)?;
+            }
+        }
+        Ok(text)
+    }
+
+    fn read_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        match &self.file {
+            Some(path) => data = std::fs::read(path)?,
+            None => {
+                io::stdin().read_to_end(&mut data)?;
+            }
+        }
+        Ok(data)
+    }
+
+    fn location(&self) -> ImportLocation {
+        match &self.file {
+            Some(path) => ImportLocation::local(path),
+            None => ImportLocation::Missing,
+        }
+    }
+
+    fn parse(&self) -> Result<Parsed, Error> {
+        match &self.file {
+            Some(path) => Parsed::parse_file(path),
+            None => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                Parsed::parse_str(&text)
+            }
+        }
+    }
+
+    fn typecheck(&self) -> Result<Typed, Error> {
+        Ok(self.parse()?.resolve()?.typecheck()?)
+    }
+
+    fn to_value(&self) -> Result<Value, Box<dyn std::error::Error>> {
+        Ok(match &self.file {
+            Some(path) => serde_dhall::from_file(path).parse()?,
+            None => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                serde_dhall::from_str(&text).parse()?
+            }
+        })
+    }
+}
+
+/// The exit code for a given error.
+///
+/// | Code | Error |
+/// |------|-------|
+/// | 1 | other errors |
+/// | 2 | I/O error |
+/// | 3 | parse or decode error |
+/// | 4 | import resolution error |
+/// | 5 | type error |
+fn exit_code(err: &(dyn std::error::Error + 'static)) -> i32 {
+    if let Some(err) = err.downcast_ref::<Error>() {
+        match err.kind() {
+            ErrorKind::IO(_) => 2,
+            ErrorKind::Parse(_) | ErrorKind::Decode(_) => 3,
+            ErrorKind::Resolve(_) | ErrorKind::Cache(_) => 4,
+            ErrorKind::Typecheck(_) => 5,
+            _ => 1,
+        }
+    } else if let Some(source) = err.source() {
+        exit_code(source)
+    } else {
+        1
+    }
+}
+
+/// The error as a `Diagnostic`. Spans without a file point into the input file, if any.
+fn diagnostic(
+    err: &(dyn std::error::Error + 'static),
+    file: Option<&Path>,
+) -> Diagnostic {
+    let mut cause = Some(err);
+    let mut diagnostic = loop {
+        match cause {
+            Some(e) => match e.downcast_ref::<Error>() {
+                Some(e) => break e.to_diagnostic(),
+                None => cause = e.source(),
+            },
+            None => break Diagnostic::error(err),
+        }
+    };
+    if let Some(path) = file {
+        for span in &mut diagnostic.spans {
+            if span.file.is_none() {
+                span.file = Some(path.display().to_string());
+            }
+        }
+    }
+    diagnostic
+}
+
+/// Print the diagnostics to standard error.
+fn print_diagnostics(diagnostics: &[Diagnostic], format: ErrorFormat) {
+    match format {
+        ErrorFormat::Human => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic)
+            }
+        }
+        ErrorFormat::Json => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", serde_json::to_string(diagnostic).unwrap())
+            }
+        }
+        ErrorFormat::Sarif => eprintln!("{}", to_sarif(diagnostics)),
+    }
+}
+
+fn run(
+    cmd: Command,
+    error_format: ErrorFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd {
+        Command::Normalize { alpha, input } => {
+            let normalized = input.typecheck()?.normalize();
+            if alpha {
+                println!("{}", normalized.to_expr_alpha());
+            } else {
+                println!("{}", normalized.to_expr());
+            }
+        }
+        Command::Type { input } => {
+            println!("{}", input.typecheck()?.get_type()?.to_expr());
+        }
+        Command::Resolve { input } => {
+            println!("{}", input.parse()?.resolve()?.to_expr());
+        }
+        Command::Hash { input } => {
+            let expr = input.typecheck()?.normalize().to_expr_alpha();
+            println!("sha256:{}", hex::encode(expr.hash()?));
+        }
+        Command::Encode { input } => {
+            let data = binary::encode(&input.parse()?.to_expr())?;
+            io::stdout().write_all(&data)?;
+        }
+        Command::Decode { input } => {
+            let parsed = Parsed::parse_binary(&input.read_bytes()?)?;
+            println!("{}", parsed.to_expr());
+        }
+        Command::Freeze { all, cache, input } => {
+            let options = FreezeOptions {
+                all_imports: all,
+                cache,
+            };
+            match &input.file {
+                Some(path) => freeze_file(path, options)?,
+                None => print!(
+                    "{}",
+                    freeze_text(
+                        &input.read_text()?,
+                        input.location(),
+                        &DefaultFetcher,
+                        options
+                    )?
+                ),
+            }
+        }
+        Command::Format {
+            ascii,
+            width,
+            check,
+            drop_comments,
+            input,
+        } => {
+            let text = input.read_text()?;
+            let cst =
+                Cst::parse_at(&text, input.location()).map_err(Error::from)?;
+            let formatted = match cst.format(&FormatOptions { width, ascii }) {
+                Ok(formatted) => formatted,
+                Err(err) if drop_comments => err.formatted,
+                Err(err) => return Err(err.into()),
+            };
+            match &input.file {
+                _ if check && formatted != text => {
+                    let name = match &input.file {
+                        Some(path) => path.display().to_string(),
+                        None => "the input".to_owned(),
+                    };
+                    return Err(format!("{} is not formatted", name).into());
+                }
+                _ if check => {}
+                Some(path) => {
+                    if formatted != text {
+                        std::fs::write(path, formatted)?;
+                    }
+                }
+                None => print!("{}", formatted),
+            }
+        }
+        Command::Lint { fix, input } => {
+            let text = input.read_text()?;
+            let mut cst =
+                Cst::parse_at(&text, input.location()).map_err(Error::from)?;
+            if fix {
+                let fixed = lint::fix_text(&text, input.location())
+                    .map_err(Error::from)?;
+                if fixed != text {
+                    // Parse the new text so that the remaining problems point into it.
+                    cst = Cst::parse_at(&fixed, input.location())
+                        .map_err(Error::from)?;
+                    match &input.file {
+                        Some(path) => std::fs::write(path, &fixed)?,
+                        None => print!("{}", fixed),
+                    }
+                } else if input.file.is_none() {
+                    print!("{}", text);
+                }
+            }
+            let mut diagnostics: Vec<_> = lint::lint(cst.expr())
+                .iter()
+                .map(|lint| lint.diagnostic())
+                .collect();
+            if !diagnostics.is_empty() {
+                if let Some(path) = &input.file {
+                    for span in
+                        diagnostics.iter_mut().flat_map(|d| &mut d.spans)
+                    {
+                        span.file = Some(path.display().to_string());
+                    }
+                }
+                print_diagnostics(&diagnostics, error_format);
+                std::process::exit(1);
+            }
+        }
+        Command::Repl => repl::run()?,
+        Command::ToJson { options, input } => {
+            let value = input.to_value()?;
+            println!(
+                "{}",
+                value.to_json_string(options.to_json_options(false))?
+            );
+        }
+        Command::ToYaml {
+            options,
+            documents,
+            input,
+        } => {
+            let value = input.to_value()?;
+            print!(
+                "{}",
+                value.to_yaml_string(options.to_json_options(documents))?
+            );
+        }
+        Command::FromJson { schema, input } => {
+            let ty: SimpleType = serde_dhall::from_str(&schema).parse()?;
+            println!("{}", Value::from_json_str(&input.read_text()?, &ty)?);
+        }
+        Command::FromYaml { schema, input } => {
+            let ty: SimpleType = serde_dhall::from_str(&schema).parse()?;
+            println!("{}", Value::from_yaml_str(&input.read_text()?, &ty)?);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let opts = Opts::from_args();
+    let file = opts.command.input().and_then(|input| input.file.clone());
+    if let Err(err) = run(opts.command, opts.error_format) {
+        match opts.error_format {
+            ErrorFormat::Human => eprintln!("{}", err),
+            format => print_diagnostics(
+                &[diagnostic(err.as_ref(), file.as_deref())],
+                format,
+            ),
+        }
+        std::process::exit(exit_code(err.as_ref()));
+    }
+}
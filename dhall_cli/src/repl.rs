@@ -0,0 +1,94 @@
+//! `dhall repl`: evaluate expressions interactively.
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+
+use dhall::semantics::repl::Repl;
+
+const HELP: &str = "\
+Type an expression to print its normal form, or one of the following commands:
+  :let x = e       Bind `x` to `e` for the following inputs; `:let x : T = e` checks its type
+  :type e          Print the type of `e`
+  :hash e          Print the semantic hash of `e`
+  :load file       Run the commands in `file`, one per line
+  :save file       Save the bindings as commands, to be used with `:load`
+  :help            Print this message
+  :quit            Exit";
+
+/// Whether to go on reading commands.
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Read commands from standard input until the end of the input or `:quit`.
+pub fn run() -> io::Result<()> {
+    let mut repl = Repl::new();
+    let interactive = atty::is(atty::Stream::Stdin);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("⊢ ");
+            io::stdout().flush()?;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match command(&mut repl, &line) {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Quit) => break,
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    Ok(())
+}
+
+fn command(
+    repl: &mut Repl,
+    line: &str,
+) -> Result<Flow, Box<dyn std::error::Error>> {
+    let line = line.trim();
+    let (cmd, arg) = if line.starts_with(':') {
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let cmd = parts.next().unwrap();
+        (cmd, parts.next().unwrap_or("").trim())
+    } else {
+        ("", line)
+    };
+    match cmd {
+        "" if arg.is_empty() => {}
+        "" => println!("{}", repl.eval(arg)?),
+        ":let" => {
+            // Parse the binding with its `let` keyword, so that errors point at the right column.
+            let (name, ty) = repl.insert_let(&line[1..])?;
+            println!("{} : {}", name, ty);
+        }
+        ":type" => println!("{}", repl.type_of(arg)?),
+        ":hash" => println!("sha256:{}", hex::encode(repl.hash(arg)?)),
+        ":load" => {
+            for line in std::fs::read_to_string(arg)?.lines() {
+                if let Flow::Quit = command(repl, line)? {
+                    return Ok(Flow::Quit);
+                }
+            }
+        }
+        ":save" => {
+            let mut out = String::new();
+            for binding in repl.bindings() {
+                write!(out, ":let {}", binding.name)?;
+                if let Some(annot) = &binding.annot {
+                    write!(out, " : {}", annot)?;
+                }
+                writeln!(out, " = {}", binding.value)?;
+            }
+            std::fs::write(arg, out)?;
+        }
+        ":help" => println!("{}", HELP),
+        ":quit" => return Ok(Flow::Quit),
+        _ => {
+            return Err(format!("unknown command `{}`, try `:help`", cmd).into())
+        }
+    }
+    Ok(Flow::Continue)
+}
@@ -0,0 +1,63 @@
+//! A Language Server Protocol server for Dhall.
+//!
+//! It talks JSON-RPC over standard input and output. It publishes the parse, import and type
+//! errors of open documents, and provides hover types, go-to-definition for `let` bindings and
+//! local imports, and completion of record fields and union alternatives.
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+mod server;
+
+use server::{parse_error_reply, Server};
+
+/// Read the body of one message, or `None` at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let mut header = line.splitn(2, ':');
+        if header.next() == Some("Content-Length") {
+            length = header.next().and_then(|l| l.trim().parse::<usize>().ok());
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut server = Server::new();
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match serde_json::from_slice::<Value>(&body) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![parse_error_reply(&err)],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            std::process::exit(code);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,520 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use url::Url;
+
+use dhall::error::{Diagnostic, Error, Position};
+use dhall::semantics::inspect::Definition;
+use dhall::semantics::{ImportLocation, Session};
+use dhall::syntax::Span;
+use dhall::{Parsed, Resolved};
+
+/// JSON-RPC error code for messages that are not valid JSON.
+const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for unknown methods.
+const METHOD_NOT_FOUND: i64 = -32601;
+/// LSP `DiagnosticSeverity.Error`.
+const SEVERITY_ERROR: u64 = 1;
+/// LSP `CompletionItemKind.Field`.
+const COMPLETION_FIELD: u64 = 5;
+/// LSP `CompletionItemKind.EnumMember`.
+const COMPLETION_ENUM_MEMBER: u64 = 20;
+
+/// The state of the server: the open documents and whether we were asked to shut down.
+pub struct Server {
+    documents: HashMap<Url, Document>,
+    /// Keeps the imports of all the documents, so that they are only read again when they change.
+    session: Session,
+    shutdown: bool,
+    exit: Option<i32>,
+}
+
+struct Document {
+    text: String,
+    /// The last text of the document whose imports could be resolved, with the result.
+    resolved: Option<(String, Resolved)>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+            session: Session::new(),
+            shutdown: false,
+            exit: None,
+        }
+    }
+
+    /// Set once the client asked the server to exit.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit
+    }
+
+    /// Handle a request or notification, and return the messages to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.handle_notification(method, params),
+        };
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": { "triggerCharacters": ["."] },
+                },
+                "serverInfo": {
+                    "name": "dhall-lsp",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Value::Null)
+            }
+            "textDocument/hover" => Some(self.hover(params)),
+            "textDocument/definition" => Some(self.definition(params)),
+            "textDocument/completion" => Some(self.completion(params)),
+            _ => None,
+        };
+        let reply = match result {
+            Some(result) => {
+                json!({ "jsonrpc": "2.0", "id": id, "result": result })
+            }
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": METHOD_NOT_FOUND,
+                    "message": format!("unknown method: {}", method),
+                },
+            }),
+        };
+        vec![reply]
+    }
+
+    fn handle_notification(
+        &mut self,
+        method: &str,
+        params: &Value,
+    ) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| Url::parse(uri).ok());
+        match (method, uri) {
+            ("exit", _) => {
+                self.exit = Some(if self.shutdown { 0 } else { 1 });
+                Vec::new()
+            }
+            ("textDocument/didOpen", Some(uri)) => {
+                let text =
+                    params["textDocument"]["text"].as_str().unwrap_or("");
+                let document = Document {
+                    text: text.to_owned(),
+                    resolved: None,
+                };
+                self.documents.insert(uri.clone(), document);
+                vec![self.publish_diagnostics(&uri)]
+            }
+            ("textDocument/didChange", Some(uri)) => {
+                // We only ask for full-text synchronization, so the last change has the whole
+                // text.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    if let Some(document) = self.documents.get_mut(&uri) {
+                        document.text = text.to_owned();
+                    }
+                }
+                vec![self.publish_diagnostics(&uri)]
+            }
+            // Imported files may have changed.
+            ("textDocument/didSave", Some(_)) => {
+                let uris: Vec<_> = self.documents.keys().cloned().collect();
+                uris.iter()
+                    .map(|uri| self.publish_diagnostics(uri))
+                    .collect()
+            }
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                vec![diagnostics_notification(&uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// An open document and the byte offset of the position in the request.
+    fn document_at<'a>(
+        &'a self,
+        params: &Value,
+    ) -> Option<(Url, &'a Document, usize)> {
+        let uri = Url::parse(params["textDocument"]["uri"].as_str()?).ok()?;
+        let document = self.documents.get(&uri)?;
+        let position = &params["position"];
+        let offset = offset_at(
+            &document.text,
+            position["line"].as_u64()? as usize,
+            position["character"].as_u64()? as usize,
+        );
+        Some((uri, document, offset))
+    }
+
+    /// Check the document, and keep the result of resolving it for later requests.
+    fn publish_diagnostics(&mut self, uri: &Url) -> Value {
+        let document = match self.documents.get_mut(uri) {
+            Some(document) => document,
+            None => return diagnostics_notification(uri, Vec::new()),
+        };
+        let text = &document.text;
+        let err = match resolve(&self.session, uri, text) {
+            Ok(resolved) => {
+                let err = resolved.typecheck().err().map(Error::from);
+                document.resolved = Some((text.clone(), resolved));
+                err
+            }
+            Err(err) => Some(err),
+        };
+        let diagnostics = match err {
+            None => Vec::new(),
+            Some(err) => vec![to_lsp_diagnostic(uri, text, &err)],
+        };
+        diagnostics_notification(uri, diagnostics)
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let (_, document, offset) = match self.document_at(params) {
+            Some(x) => x,
+            None => return Value::Null,
+        };
+        let text = &document.text;
+        // The document is resolved whenever it changes.
+        let resolved = match &document.resolved {
+            Some((resolved_text, resolved)) if resolved_text == text => {
+                resolved
+            }
+            _ => return Value::Null,
+        };
+        match resolved.type_at(offset) {
+            Some((span, ty)) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("```dhall\n{}\n```", ty),
+                },
+                "range": span_range(text, &span),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let (uri, document, offset) = match self.document_at(params) {
+            Some(x) => x,
+            None => return Value::Null,
+        };
+        let text = &document.text;
+        let parsed = match Parsed::parse_str_at(text, location(&uri)) {
+            Ok(parsed) => parsed,
+            Err(_) => return Value::Null,
+        };
+        match parsed.definition_at(offset) {
+            Some(Definition::Let(span)) => {
+                json!({ "uri": uri.as_str(), "range": span_range(text, &span) })
+            }
+            Some(Definition::Import(ImportLocation::Local(path))) => {
+                let path = match std::env::current_dir() {
+                    Ok(dir) => dir.join(path),
+                    Err(_) => path,
+                };
+                match Url::from_file_path(path) {
+                    Ok(target) => json!({
+                        "uri": target.as_str(),
+                        "range": {
+                            "start": { "line": 0, "character": 0 },
+                            "end": { "line": 0, "character": 0 },
+                        },
+                    }),
+                    Err(()) => Value::Null,
+                }
+            }
+            _ => Value::Null,
+        }
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let (uri, document, offset) = match self.document_at(params) {
+            Some(x) => x,
+            None => return Value::Null,
+        };
+        let text = &document.text;
+        // The text being completed, e.g. `x.fo` with the cursor after `fo`, does not typecheck
+        // and often does not parse. We remove `.fo` and look at what comes before the dot.
+        let before = &text[..offset];
+        let prefix_len = before
+            .chars()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+            .map(char::len_utf8)
+            .sum::<usize>();
+        let dot = offset - prefix_len;
+        if !text[..dot].ends_with('.') {
+            return json!([]);
+        }
+        let dot = dot - 1;
+        let edited = format!("{}{}", &text[..dot], &text[offset..]);
+        // This is usually the text from before the dot was typed, which we already resolved.
+        let resolved_edited;
+        let resolved = match &document.resolved {
+            Some((resolved_text, resolved)) if *resolved_text == edited => {
+                resolved
+            }
+            _ => match resolve(&self.session, &uri, &edited) {
+                Ok(resolved) => {
+                    resolved_edited = resolved;
+                    &resolved_edited
+                }
+                Err(_) => return json!([]),
+            },
+        };
+        let items: Vec<_> = resolved
+            .completions_at(dot)
+            .into_iter()
+            .map(|completion| {
+                let kind = if completion.alternative {
+                    COMPLETION_ENUM_MEMBER
+                } else {
+                    COMPLETION_FIELD
+                };
+                let mut item = json!({
+                    "label": String::from(&completion.label),
+                    "kind": kind,
+                });
+                if let Some(ty) = completion.ty {
+                    item["detail"] = json!(ty.to_string());
+                }
+                item
+            })
+            .collect();
+        json!(items)
+    }
+}
+
+/// The reply to a message that is not valid JSON. Its id cannot be known.
+pub fn parse_error_reply(err: &serde_json::Error) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": {
+            "code": PARSE_ERROR,
+            "message": format!("invalid JSON: {}", err),
+        },
+    })
+}
+
+/// Where the document was read from, so that relative imports work.
+fn location(uri: &Url) -> ImportLocation {
+    match uri.to_file_path() {
+        Ok(path) => ImportLocation::Local(path),
+        Err(()) => ImportLocation::Local(PathBuf::from(uri.path())),
+    }
+}
+
+fn resolve(
+    session: &Session,
+    uri: &Url,
+    text: &str,
+) -> Result<Resolved, Error> {
+    Parsed::parse_str_at(text, location(uri))?.resolve_in(session)
+}
+
+fn diagnostics_notification(uri: &Url, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri.as_str(), "diagnostics": diagnostics },
+    })
+}
+
+/// Convert an error to an LSP diagnostic located in the document. If the error happened inside
+/// an imported file, the diagnostic points at the import.
+fn to_lsp_diagnostic(uri: &Url, text: &str, err: &Error) -> Value {
+    let diagnostic: Diagnostic = err.to_diagnostic();
+    let this_file = location(uri).to_string();
+    let primary = diagnostic.spans.iter().find(|span| span.primary);
+    let range = match primary {
+        Some(span)
+            if span.file.is_none()
+                || span.file.as_ref() == Some(&this_file) =>
+        {
+            json!({
+                "start": lsp_position(text, span.start),
+                "end": lsp_position(text, span.end),
+            })
+        }
+        _ => match err.import_chain().last() {
+            Some(frame) => span_range(text, &frame.span),
+            None => json!({
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 0 },
+            }),
+        },
+    };
+    let message = Some(&diagnostic.message)
+        .into_iter()
+        .chain(&diagnostic.notes)
+        .chain(&diagnostic.help)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    json!({
+        "range": range,
+        "severity": SEVERITY_ERROR,
+        "source": "dhall",
+        "message": message,
+    })
+}
+
+/// The byte offset of an LSP position, which counts UTF-16 code units within a line.
+fn offset_at(text: &str, line: usize, character: usize) -> usize {
+    let line_start = if line == 0 {
+        0
+    } else {
+        match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        }
+    };
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// The LSP position of a `dhall` position, which counts lines and characters from 1.
+fn lsp_position(text: &str, pos: Position) -> Value {
+    let line = text.split('\n').nth(pos.line - 1).unwrap_or("");
+    let character: usize =
+        line.chars().take(pos.column - 1).map(char::len_utf16).sum();
+    json!({ "line": pos.line - 1, "character": character })
+}
+
+fn span_range(text: &str, span: &Span) -> Value {
+    let (start, end) = match span {
+        Span::Parsed(span) => span.line_col(),
+        _ => ((1, 1), (1, 1)),
+    };
+    let position = |(line, column)| Position { line, column };
+    json!({
+        "start": lsp_position(text, position(start)),
+        "end": lsp_position(text, position(end)),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        server.handle(&message).remove(0)
+    }
+
+    fn open(server: &mut Server, uri: &str, text: &str) -> Value {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": text } },
+        });
+        server.handle(&message).remove(0)
+    }
+
+    fn at(uri: &str, line: usize, character: usize) -> Value {
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+        })
+    }
+
+    #[test]
+    fn invalid_json_should_get_a_parse_error() {
+        let err = serde_json::from_str::<Value>("{ not json").unwrap_err();
+        let reply = parse_error_reply(&err);
+        assert_eq!(reply["id"], Value::Null);
+        assert_eq!(reply["error"]["code"], -32700);
+    }
+
+    #[test]
+    fn positions_should_count_utf16_units() {
+        let text = "let x = \"é😀\"\nin x";
+        assert_eq!(offset_at(text, 1, 3), text.rfind('x').unwrap());
+        assert_eq!(offset_at(text, 0, 13), text.find('\n').unwrap());
+        let end = Position {
+            line: 1,
+            column: 13,
+        };
+        assert_eq!(lsp_position(text, end)["character"], 13);
+    }
+
+    #[test]
+    fn server_should_report_errors_and_types() {
+        let mut server = Server::new();
+        let uri = "file:///dir/config.dhall";
+
+        let published = open(&mut server, uri, "let x = 1\nin x + True");
+        let diagnostics = &published["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+        open(&mut server, uri, "let r = { a = 1, b = True }\nin r");
+        let hover = request(&mut server, "textDocument/hover", at(uri, 1, 3));
+        assert_eq!(
+            hover["result"]["contents"]["value"],
+            "```dhall\n{ a : Natural, b : Bool }\n```"
+        );
+        let definition =
+            request(&mut server, "textDocument/definition", at(uri, 1, 3));
+        let range = &definition["result"]["range"];
+        assert_eq!(range["start"], json!({ "line": 0, "character": 4 }));
+        assert_eq!(range["end"], json!({ "line": 0, "character": 5 }));
+
+        // Completion reuses the document from before the dot was typed.
+        let mut complete = |text: &str| {
+            let message = json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didChange",
+                "params": {
+                    "textDocument": { "uri": uri },
+                    "contentChanges": [{ "text": text }],
+                },
+            });
+            server.handle(&message);
+            let completion =
+                request(&mut server, "textDocument/completion", at(uri, 1, 5));
+            completion["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| {
+                    let label = item["label"].as_str().unwrap().to_owned();
+                    (label, item["kind"].as_u64().unwrap())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            complete("let r = { a = 1, b = True }\nin r."),
+            vec![("a".to_owned(), 5), ("b".to_owned(), 5)]
+        );
+        assert_eq!(
+            complete("let C = < A | B : Natural >\nin C."),
+            vec![("A".to_owned(), 20), ("B".to_owned(), 20)]
+        );
+    }
+}
@@ -5,6 +5,7 @@ use std::borrow::Cow;
 
 use dhall::syntax::NumKind;
 
+use crate::error::PathSegment;
 use crate::value::SimpleValue;
 use crate::{Error, ErrorKind, Result, Value};
 
@@ -43,7 +44,47 @@ pub trait FromDhall: Sealed + Sized {
 
 impl<T> Sealed for T where T: serde::de::DeserializeOwned {}
 
-struct Deserializer<'a>(Cow<'a, SimpleValue>);
+struct Deserializer<'a> {
+    value: Cow<'a, SimpleValue>,
+    /// How this value is reached from its parent, to locate errors.
+    segment: Option<Segment<'a>>,
+}
+
+#[derive(Clone, Copy)]
+enum Segment<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+impl<'a> Deserializer<'a> {
+    fn child(value: &'a SimpleValue, segment: Segment<'a>) -> Self {
+        Deserializer {
+            value: Cow::Borrowed(value),
+            segment: Some(segment),
+        }
+    }
+
+    /// Adds the position of this value in its parent to the path of the error.
+    fn locate<T>(segment: Option<Segment<'_>>, result: Result<T>) -> Result<T> {
+        result.map_err(|err| match segment {
+            Some(Segment::Field(name)) => {
+                err.inside(PathSegment::Field(name.to_owned()))
+            }
+            Some(Segment::Index(i)) => err.inside(PathSegment::Index(i)),
+            None => err,
+        })
+    }
+}
+
+/// Numbers are unbounded in Dhall, but the serde data model stops at 64 bits.
+fn out_of_range<'de, V>(x: &dyn std::fmt::Display, visitor: &V) -> Error
+where
+    V: serde::de::Visitor<'de>,
+{
+    use serde::de::{Error as _, Unexpected};
+    let unexpected = format!("integer `{}`", x);
+    Error::invalid_value(Unexpected::Other(&unexpected), visitor)
+}
 
 impl<T> FromDhall for T
 where
@@ -56,7 +97,10 @@ where
                 v
             )))
         })?;
-        T::deserialize(Deserializer(Cow::Owned(sval)))
+        T::deserialize(Deserializer {
+            value: Cow::Owned(sval),
+            segment: None,
+        })
     }
 }
 
@@ -74,44 +118,44 @@ impl<'de: 'a, 'a> serde::Deserializer<'de> for Deserializer<'a> {
     where
         V: serde::de::Visitor<'de>,
     {
-        use std::convert::TryInto;
         use NumKind::*;
         use SimpleValue::*;
 
-        let val = |x| Deserializer(Cow::Borrowed(x));
-        match self.0.as_ref() {
+        let Deserializer { value, segment } = self;
+        let result = match value.as_ref() {
             Num(Bool(x)) => visitor.visit_bool(*x),
-            Num(Natural(x)) => {
-                if let Ok(x64) = (*x).try_into() {
-                    visitor.visit_u64(x64)
-                } else if let Ok(x32) = (*x).try_into() {
-                    visitor.visit_u32(x32)
-                } else {
-                    unimplemented!()
-                }
-            }
-            Num(Integer(x)) => {
-                if let Ok(x64) = (*x).try_into() {
-                    visitor.visit_i64(x64)
-                } else if let Ok(x32) = (*x).try_into() {
-                    visitor.visit_i32(x32)
-                } else {
-                    unimplemented!()
-                }
-            }
+            Num(Natural(x)) => match x.to_u64() {
+                Some(x) => visitor.visit_u64(x),
+                None => Err(out_of_range(x, &visitor)),
+            },
+            Num(Integer(x)) => match x.to_i64() {
+                Some(x) => visitor.visit_i64(x),
+                None => Err(out_of_range(x, &visitor)),
+            },
             Num(Double(x)) => visitor.visit_f64((*x).into()),
             Text(x) => visitor.visit_str(x),
-            List(xs) => {
-                visitor.visit_seq(SeqDeserializer::new(xs.iter().map(val)))
-            }
-            Optional(None) => visitor.visit_none(),
-            Optional(Some(x)) => visitor.visit_some(val(x)),
-            Record(m) => visitor.visit_map(MapDeserializer::new(
-                m.iter().map(|(k, v)| (k.as_ref(), val(v))),
+            List(xs) => visitor.visit_seq(SeqDeserializer::new(
+                xs.iter()
+                    .enumerate()
+                    .map(|(i, x)| Deserializer::child(x, Segment::Index(i))),
             )),
+            Optional(None) => visitor.visit_none(),
+            Optional(Some(x)) => visitor.visit_some(Deserializer {
+                value: Cow::Borrowed(x),
+                segment: None,
+            }),
+            Record(m) => visitor.visit_map(MapDeserializer::new(m.iter().map(
+                |(k, v)| {
+                    (k.as_str(), Deserializer::child(v, Segment::Field(k)))
+                },
+            ))),
             Union(field_name, Some(x)) => visitor.visit_enum(
                 MapAccessDeserializer::new(MapDeserializer::new(
-                    Some((field_name.as_str(), val(x))).into_iter(),
+                    Some((
+                        field_name.as_str(),
+                        Deserializer::child(x, Segment::Field(field_name)),
+                    ))
+                    .into_iter(),
                 )),
             ),
             Union(field_name, None) => visitor.visit_enum(
@@ -119,18 +163,23 @@ impl<'de: 'a, 'a> serde::Deserializer<'de> for Deserializer<'a> {
                     Some((field_name.as_str(), ())).into_iter(),
                 )),
             ),
-        }
+        };
+        Deserializer::locate(segment, result)
     }
 
     fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value>
     where
         V: serde::de::Visitor<'de>,
     {
-        let val = |x| Deserializer(Cow::Borrowed(x));
-        match self.0.as_ref() {
+        match self.value.as_ref() {
             // Blindly takes keys in sorted order.
-            SimpleValue::Record(m) => visitor
-                .visit_seq(SeqDeserializer::new(m.iter().map(|(_, v)| val(v)))),
+            SimpleValue::Record(m) => {
+                let result =
+                    visitor.visit_seq(SeqDeserializer::new(m.iter().map(
+                        |(k, v)| Deserializer::child(v, Segment::Field(k)),
+                    )));
+                Deserializer::locate(self.segment, result)
+            }
             _ => self.deserialize_any(visitor),
         }
     }
@@ -1,9 +1,11 @@
-use dhall::error::Error as DhallError;
+use dhall::error::{Diagnostic, DiagnosticSpan, Error as DhallError};
+use dhall::operations::OpKind;
+use dhall::syntax::{Expr, ExprKind, ParsedSpan, Span};
 
 /// Alias for a `Result` with the error type `serde_dhall::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
 
-/// Errors that can occur when deserializing Dhall data.
+/// Errors that can occur when deserializing or serializing Dhall data.
 #[derive(Debug)]
 pub struct Error(pub(crate) ErrorKind);
 
@@ -11,6 +13,173 @@ pub struct Error(pub(crate) ErrorKind);
 pub(crate) enum ErrorKind {
     Dhall(DhallError),
     Deserialize(String),
+    /// A deserialization error inside the value, with the source of the offending part if it
+    /// could be found.
+    DeserializeAt {
+        message: String,
+        path: Vec<PathSegment>,
+        span: Option<ParsedSpan>,
+    },
+    Serialize(String),
+    /// An error converting a value to or from JSON or YAML.
+    #[cfg(feature = "json")]
+    Convert(String),
+}
+
+/// A step from a value to one of its parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PathSegment {
+    /// A record field or a union alternative.
+    Field(String),
+    /// A list element.
+    Index(usize),
+}
+
+fn fmt_path(path: &[PathSegment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            PathSegment::Field(name) => format!(".{}", name),
+            PathSegment::Index(i) => format!("[{}]", i),
+        })
+        .collect()
+}
+
+/// The part of `expr` at `path`, if it is written as nested literals, possibly under `let`s and
+/// type annotations.
+fn find_in_source<'a>(
+    mut expr: &'a Expr,
+    path: &[PathSegment],
+) -> Option<&'a Expr> {
+    loop {
+        expr = match expr.kind() {
+            ExprKind::Let(_, _, _, body) => body,
+            ExprKind::Annot(e, _) | ExprKind::SomeLit(e) => e,
+            _ => break,
+        };
+    }
+    let (segment, rest) = match path.split_first() {
+        None => return Some(expr),
+        Some(x) => x,
+    };
+    let part = match (segment, expr.kind()) {
+        (PathSegment::Field(name), ExprKind::RecordLit(kvs)) => {
+            kvs.get(name.as_str())?
+        }
+        (PathSegment::Index(i), ExprKind::NEListLit(xs)) => xs.get(*i)?,
+        // A union alternative applied to its payload.
+        (PathSegment::Field(name), ExprKind::Op(OpKind::App(f, x))) => {
+            match f.kind() {
+                ExprKind::Op(OpKind::Field(_, l))
+                    if String::from(l) == *name =>
+                {
+                    x
+                }
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    find_in_source(part, rest)
+}
+
+impl Error {
+    /// For an error that happened while deserializing a part of the value, the path to that
+    /// part, e.g. `.servers[2].port`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Server {
+    ///     port: u16,
+    /// }
+    /// #[derive(Debug, Deserialize)]
+    /// struct Config {
+    ///     servers: Vec<Server>,
+    /// }
+    ///
+    /// let data = "{ servers = [ { port = 80 }, { port = 100000 } ] }";
+    /// let err = serde_dhall::from_str(data).parse::<Config>().unwrap_err();
+    /// assert_eq!(err.path().as_deref(), Some(".servers[1].port"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn path(&self) -> Option<String> {
+        match &self.0 {
+            ErrorKind::DeserializeAt { path, .. } => Some(fmt_path(path)),
+            _ => None,
+        }
+    }
+
+    /// For an error that happened while deserializing a part of the value, the source text of
+    /// that part, if it was written literally in the parsed text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let err = serde_dhall::from_str("{ x = -1 }")
+    ///     .parse::<std::collections::HashMap<String, u64>>()
+    ///     .unwrap_err();
+    /// let span = err.span().unwrap();
+    /// assert_eq!((span.start(), span.end()), (6, 8));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn span(&self) -> Option<&ParsedSpan> {
+        match &self.0 {
+            ErrorKind::DeserializeAt { span, .. } => span.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Records that the error happened inside the part of the value reached with `segment`.
+    pub(crate) fn inside(self, segment: PathSegment) -> Self {
+        match self.0 {
+            ErrorKind::Deserialize(message) => {
+                Error(ErrorKind::DeserializeAt {
+                    message,
+                    path: vec![segment],
+                    span: None,
+                })
+            }
+            ErrorKind::DeserializeAt {
+                message,
+                mut path,
+                span,
+            } => {
+                path.insert(0, segment);
+                Error(ErrorKind::DeserializeAt {
+                    message,
+                    path,
+                    span,
+                })
+            }
+            kind => Error(kind),
+        }
+    }
+
+    /// Looks for the source of the part of the value where the error happened in `expr`, the
+    /// expression the value was parsed from.
+    pub(crate) fn locate_in(self, expr: &Expr) -> Self {
+        match self.0 {
+            ErrorKind::DeserializeAt { message, path, .. } => {
+                let span = match find_in_source(expr, &path).map(Expr::span) {
+                    Some(Span::Parsed(span)) => Some(span),
+                    _ => None,
+                };
+                Error(ErrorKind::DeserializeAt {
+                    message,
+                    path,
+                    span,
+                })
+            }
+            kind => Error(kind),
+        }
+    }
 }
 
 impl From<ErrorKind> for Error {
@@ -24,11 +193,41 @@ impl std::fmt::Display for Error {
         match &self.0 {
             ErrorKind::Dhall(err) => write!(f, "{}", err),
             ErrorKind::Deserialize(err) => write!(f, "{}", err),
+            ErrorKind::DeserializeAt {
+                message,
+                path,
+                span: Some(span),
+            } => {
+                let mut diagnostic = Diagnostic::error(format!(
+                    "{}: {}",
+                    fmt_path(path),
+                    message
+                ));
+                diagnostic.spans.push(DiagnosticSpan::new(
+                    span.clone(),
+                    "in this value",
+                    true,
+                ));
+                write!(f, "{}", diagnostic)
+            }
+            ErrorKind::DeserializeAt { message, path, .. } => {
+                write!(f, "{}: {}", fmt_path(path), message)
+            }
+            ErrorKind::Serialize(err) => write!(f, "{}", err),
+            #[cfg(feature = "json")]
+            ErrorKind::Convert(err) => write!(f, "{}", err),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.0 {
+            ErrorKind::Dhall(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl serde::de::Error for Error {
     fn custom<T>(msg: T) -> Self
@@ -38,3 +237,12 @@ impl serde::de::Error for Error {
         ErrorKind::Deserialize(msg.to_string()).into()
     }
 }
+
+impl serde::ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        ErrorKind::Serialize(msg.to_string()).into()
+    }
+}
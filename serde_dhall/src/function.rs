@@ -0,0 +1,126 @@
+use std::marker::PhantomData;
+
+use crate::{Error, ErrorKind, FromDhall, Result, Sealed, ToDhall, Value};
+
+/// A Dhall function from `A` to `B`, that can be called from Rust.
+///
+/// Deserializing into a `DhallFn` only checks that the value is a function; the argument and the
+/// result are checked on each call. Functions of several arguments can take a tuple or a struct,
+/// which Dhall sees as a record.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> serde_dhall::Result<()> {
+/// use serde::{Deserialize, Serialize};
+/// use serde_dhall::DhallFn;
+///
+/// #[derive(Serialize)]
+/// struct Env {
+///     name: String,
+///     replicas: u64,
+/// }
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Deployment {
+///     host: String,
+///     workers: u64,
+/// }
+///
+/// let config = r#"
+///     λ(env : { name : Text, replicas : Natural }) →
+///         { host = "${env.name}.example.com", workers = env.replicas * 4 }
+/// "#;
+/// let deployment: DhallFn<Env, Deployment> = serde_dhall::from_str(config).parse()?;
+/// let prod = Env {
+///     name: "prod".to_owned(),
+///     replicas: 3,
+/// };
+/// assert_eq!(
+///     deployment.call(&prod)?,
+///     Deployment {
+///         host: "prod.example.com".to_owned(),
+///         workers: 12,
+///     }
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct DhallFn<A, B> {
+    function: Value,
+    types: PhantomData<fn(&A) -> B>,
+}
+
+impl<A, B> DhallFn<A, B>
+where
+    A: ToDhall,
+    B: FromDhall,
+{
+    /// Applies the function to `arg` and converts the result.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde_dhall::DhallFn;
+    ///
+    /// let double: DhallFn<u64, u64> = serde_dhall::from_str("λ(n : Natural) → n * 2").parse()?;
+    /// assert_eq!(double.call(&21)?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call(&self, arg: &A) -> Result<B> {
+        B::from_dhall(&self.function.apply(arg)?)
+    }
+}
+
+impl<A, B> DhallFn<A, B> {
+    /// The function, as a Dhall value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde_dhall::DhallFn;
+    ///
+    /// let id: DhallFn<bool, bool> = serde_dhall::from_str("λ(b : Bool) → b").parse()?;
+    /// assert_eq!(id.as_value().to_string(), "λ(b : Bool) → b");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_value(&self) -> &Value {
+        &self.function
+    }
+}
+
+impl<A, B> Clone for DhallFn<A, B> {
+    fn clone(&self) -> Self {
+        DhallFn {
+            function: self.function.clone(),
+            types: PhantomData,
+        }
+    }
+}
+
+impl<A, B> std::fmt::Debug for DhallFn<A, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("DhallFn").field(&self.function).finish()
+    }
+}
+
+impl<A, B> Sealed for DhallFn<A, B> {}
+
+impl<A, B> FromDhall for DhallFn<A, B> {
+    fn from_dhall(v: &Value) -> Result<Self> {
+        v.function_input_type().map_err(|_| {
+            Error(ErrorKind::Deserialize(format!(
+                "this is not a function: {}",
+                v
+            )))
+        })?;
+        Ok(DhallFn {
+            function: v.clone(),
+            types: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Map, Number};
+
+use dhall::syntax::NumKind;
+
+use crate::value::SimpleValue;
+use crate::{Error, ErrorKind, Result, SimpleType, Value};
+
+/// Controls how Dhall values are converted to JSON or YAML.
+///
+/// The options mirror the flags of the official `dhall-to-json` tool.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonOptions {
+    /// Omit record fields that are `null` or empty records. Takes precedence over
+    /// `preserve_null`.
+    pub omit_empty: bool,
+    /// Keep record fields that are `None` as `null` instead of omitting them.
+    pub preserve_null: bool,
+    /// When the value is a list, render each element as a separate YAML document. Has no effect
+    /// on JSON output.
+    pub documents: bool,
+}
+
+fn json_error(msg: impl ToString) -> Error {
+    Error(ErrorKind::Convert(msg.to_string()))
+}
+
+impl Value {
+    /// Converts a JSON value to a Dhall value of type `ty`.
+    ///
+    /// Objects become records, or `Prelude.Map`s if `ty` asks for a `List { mapKey : Text,
+    /// mapValue : T }`. Missing keys and `null`s become `None` for optional fields. A string
+    /// matches the alternative of that name if it has no contents; otherwise the alternatives of a
+    /// union are tried in alphabetical order. Keys that are not in the type are an error. The
+    /// result is typechecked against `ty`, and can be printed as Dhall text with `Display`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde_dhall::{SimpleType, Value};
+    ///
+    /// let ty: SimpleType =
+    ///     serde_dhall::from_str("{ x : List Natural, y : Optional Text }").parse()?;
+    /// let json = serde_json::json!({ "x": [] });
+    /// let value = Value::from_json(&json, &ty)?;
+    /// assert_eq!(value.to_string(), "{ x = [] : List Natural, y = None Text }");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_json(
+        json: &serde_json::Value,
+        ty: &SimpleType,
+    ) -> Result<Value> {
+        json_to_simple(json, ty)?.into_value(Some(ty))
+    }
+
+    /// Parses a JSON document and converts it to a Dhall value of type `ty`. See [`from_json`]
+    /// for details.
+    ///
+    /// [`from_json`]: struct.Value.html#method.from_json
+    pub fn from_json_str(json: &str, ty: &SimpleType) -> Result<Value> {
+        let json = serde_json::from_str(json).map_err(json_error)?;
+        Value::from_json(&json, ty)
+    }
+
+    /// Parses a YAML document and converts it to a Dhall value of type `ty`. See [`from_json`]
+    /// for details.
+    ///
+    /// [`from_json`]: struct.Value.html#method.from_json
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(yaml: &str, ty: &SimpleType) -> Result<Value> {
+        let json = serde_yaml::from_str(yaml).map_err(json_error)?;
+        Value::from_json(&json, ty)
+    }
+
+    /// Converts this value to JSON.
+    ///
+    /// Optional values become their contents or `null`, `Prelude.Map`s become objects and unions
+    /// become their contents, or the name of the alternative if it has no contents. Fails if the
+    /// value contains a function or a type, or a `Double` that JSON can't represent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde_dhall::{JsonOptions, Value};
+    ///
+    /// let value: Value = serde_dhall::from_str("{ x = 1, y = None Bool }").parse()?;
+    /// let json = value.to_json(JsonOptions::default())?;
+    /// assert_eq!(json.to_string(), r#"{"x":1}"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_json(&self, options: JsonOptions) -> Result<serde_json::Value> {
+        match self.to_simple_value() {
+            Some(val) => simple_to_json(&val, options),
+            None => Err(json_error(format!(
+                "cannot convert to JSON: `{}` is not a plain data value",
+                self
+            ))),
+        }
+    }
+
+    /// Converts this value to a pretty-printed JSON string. See [`to_json`] for details.
+    ///
+    /// [`to_json`]: struct.Value.html#method.to_json
+    pub fn to_json_string(&self, options: JsonOptions) -> Result<String> {
+        let json = self.to_json(options)?;
+        serde_json::to_string_pretty(&json).map_err(json_error)
+    }
+
+    /// Converts this value to a YAML string. See [`to_json`] for details.
+    ///
+    /// [`to_json`]: struct.Value.html#method.to_json
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml_string(&self, options: JsonOptions) -> Result<String> {
+        let to_yaml = |json: &serde_json::Value| {
+            serde_yaml::to_string(json).map_err(json_error)
+        };
+        match self.to_json(options)? {
+            serde_json::Value::Array(docs) if options.documents => Ok(docs
+                .iter()
+                .map(|doc| Ok(to_yaml(doc)?.trim_end().to_owned() + "\n"))
+                .collect::<Result<String>>()?),
+            json => {
+                let yaml = to_yaml(&json)?;
+                let yaml = yaml.trim_start_matches("---\n").trim_end();
+                Ok(yaml.to_owned() + "\n")
+            }
+        }
+    }
+}
+
+fn simple_to_json(
+    val: &SimpleValue,
+    options: JsonOptions,
+) -> Result<serde_json::Value> {
+    use serde_json::Value as Json;
+    Ok(match val {
+        SimpleValue::Num(NumKind::Bool(b)) => Json::Bool(*b),
+        SimpleValue::Num(NumKind::Natural(n)) => match n.to_u64() {
+            Some(n) => Json::Number(n.into()),
+            None => {
+                return Err(json_error(format!("cannot convert {} to JSON", n)))
+            }
+        },
+        SimpleValue::Num(NumKind::Integer(n)) => match n.to_i64() {
+            Some(n) => Json::Number(n.into()),
+            None => {
+                return Err(json_error(format!("cannot convert {} to JSON", n)))
+            }
+        },
+        SimpleValue::Num(NumKind::Double(d)) => {
+            let d = f64::from(*d);
+            match Number::from_f64(d) {
+                Some(n) => Json::Number(n),
+                None => {
+                    return Err(json_error(format!(
+                        "cannot convert {} to JSON",
+                        d
+                    )))
+                }
+            }
+        }
+        SimpleValue::Text(s) => Json::String(s.clone()),
+        SimpleValue::Optional(None) => Json::Null,
+        SimpleValue::Optional(Some(x)) => simple_to_json(x, options)?,
+        SimpleValue::List(xs) => Json::Array(
+            xs.iter()
+                .map(|x| simple_to_json(x, options))
+                .collect::<Result<_>>()?,
+        ),
+        SimpleValue::Record(kvs) => {
+            let mut map = Map::new();
+            for (k, v) in kvs {
+                let v = simple_to_json(v, options)?;
+                let omit = match &v {
+                    Json::Null => options.omit_empty || !options.preserve_null,
+                    Json::Object(m) => options.omit_empty && m.is_empty(),
+                    _ => false,
+                };
+                if !omit {
+                    map.insert(k.clone(), v);
+                }
+            }
+            Json::Object(map)
+        }
+        SimpleValue::Union(field, None) => Json::String(field.clone()),
+        SimpleValue::Union(_, Some(x)) => simple_to_json(x, options)?,
+    })
+}
+
+fn json_to_simple(
+    json: &serde_json::Value,
+    ty: &SimpleType,
+) -> Result<SimpleValue> {
+    use serde_json::Value as Json;
+    let mismatch = || {
+        json_error(format!(
+            "cannot convert `{}` to type `{}`",
+            json,
+            ty.to_value()
+        ))
+    };
+    let num = |n: Option<NumKind>| n.map(SimpleValue::Num).ok_or_else(mismatch);
+    Ok(match (json, ty) {
+        (Json::Bool(b), SimpleType::Bool) => {
+            SimpleValue::Num(NumKind::Bool(*b))
+        }
+        (Json::Number(n), SimpleType::Natural) => {
+            num(n.as_u64().map(|n| NumKind::Natural(n.into())))?
+        }
+        (Json::Number(n), SimpleType::Integer) => {
+            num(n.as_i64().map(|n| NumKind::Integer(n.into())))?
+        }
+        (Json::Number(n), SimpleType::Double) => {
+            num(n.as_f64().map(|n| NumKind::Double(n.into())))?
+        }
+        (Json::String(s), SimpleType::Text) => SimpleValue::Text(s.clone()),
+        (Json::Null, SimpleType::Optional(_)) => SimpleValue::Optional(None),
+        (_, SimpleType::Optional(t)) => {
+            SimpleValue::Optional(Some(Box::new(json_to_simple(json, t)?)))
+        }
+        (Json::Array(xs), SimpleType::List(t)) => SimpleValue::List(
+            xs.iter()
+                .map(|x| json_to_simple(x, t))
+                .collect::<Result<_>>()?,
+        ),
+        // `SimpleValue::into_value` turns this into a `Prelude.Map`.
+        (Json::Object(kvs), SimpleType::List(t)) => {
+            let val_ty = match t.as_ref() {
+                SimpleType::Record(kts)
+                    if kts.len() == 2
+                        && kts.get("mapKey") == Some(&SimpleType::Text) =>
+                {
+                    kts.get("mapValue").ok_or_else(mismatch)?
+                }
+                _ => return Err(mismatch()),
+            };
+            SimpleValue::Record(
+                kvs.iter()
+                    .map(|(k, v)| Ok((k.clone(), json_to_simple(v, val_ty)?)))
+                    .collect::<Result<_>>()?,
+            )
+        }
+        (Json::Object(kvs), SimpleType::Record(kts)) => {
+            if let Some(k) = kvs.keys().find(|k| !kts.contains_key(*k)) {
+                return Err(json_error(format!(
+                    "unexpected field `{}` for type `{}`",
+                    k,
+                    ty.to_value()
+                )));
+            }
+            let mut fields = BTreeMap::new();
+            for (k, t) in kts {
+                match (kvs.get(k), t) {
+                    (Some(v), _) => {
+                        fields.insert(k.clone(), json_to_simple(v, t)?);
+                    }
+                    // `SimpleValue::into_value` fills in omitted optional fields.
+                    (None, SimpleType::Optional(_)) => {}
+                    (None, _) => {
+                        return Err(json_error(format!(
+                            "missing field `{}` for type `{}`",
+                            k,
+                            ty.to_value()
+                        )))
+                    }
+                }
+            }
+            SimpleValue::Record(fields)
+        }
+        (_, SimpleType::Union(kts)) => {
+            if let Json::String(s) = json {
+                if let Some(None) = kts.get(s) {
+                    return Ok(SimpleValue::Union(s.clone(), None));
+                }
+            }
+            let mut alternatives: Vec<_> = kts
+                .iter()
+                .filter_map(|(k, t)| Some((k, t.as_ref()?)))
+                .collect();
+            alternatives.sort_by_key(|(k, _)| *k);
+            alternatives
+                .into_iter()
+                .find_map(|(k, t)| {
+                    let x = json_to_simple(json, t).ok()?;
+                    Some(SimpleValue::Union(k.clone(), Some(Box::new(x))))
+                })
+                .ok_or_else(mismatch)?
+        }
+        _ => return Err(mismatch()),
+    })
+}
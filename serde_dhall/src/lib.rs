@@ -12,8 +12,8 @@
 //! YAML. It uses the [Serde][serde] serialization library to provide drop-in support for Dhall
 //! for any datatype that supports serde (and that's a lot of them !).
 //!
-//! This library is limited to deserializing (reading) Dhall values; serializing (writing)
-//! values to Dhall is not supported.
+//! It can also go the other way and write any serde-compatible value as Dhall text, for example to
+//! generate a starter configuration file from Rust defaults.
 //!
 //! # Basic usage
 //!
@@ -151,6 +151,33 @@
 //! # }
 //! ```
 //!
+//! # Serialization
+//!
+//! The [`serialize`](fn.serialize.html) function does the converse of `from_str`: it turns a
+//! serde-compatible value into Dhall text. Dhall needs to know the type of empty lists, `None`
+//! and union alternatives, so you will usually want to provide a type annotation, in the same way
+//! as for deserialization.
+//!
+//! ```rust
+//! # fn main() -> serde_dhall::Result<()> {
+//! use serde::Serialize;
+//! use serde_dhall::StaticType;
+//!
+//! #[derive(Serialize, StaticType)]
+//! struct Point {
+//!     x: u64,
+//!     y: Option<u64>,
+//! }
+//!
+//! let point = Point { x: 1, y: None };
+//! let string = serde_dhall::serialize(&point)
+//!     .static_type_annotation()
+//!     .to_string()?;
+//! assert_eq!(string, "{ x = 1, y = None Natural }".to_string());
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! # Controlling deserialization
 //!
 //! If you need more control over the process of reading Dhall values, e.g. disabling
@@ -168,7 +195,11 @@ mod test_readme {
 
 mod deserialize;
 mod error;
+mod function;
+#[cfg(feature = "json")]
+mod json;
 mod options;
+mod serialize;
 mod static_type;
 /// Dhall values
 mod value;
@@ -178,8 +209,13 @@ pub use dhall_proc_macros::StaticType;
 
 pub use deserialize::FromDhall;
 pub(crate) use deserialize::Sealed;
+pub use dhall::semantics::{Fetcher, ImportPolicy, Session};
 pub(crate) use error::ErrorKind;
 pub use error::{Error, Result};
-pub use options::{from_file, from_str, Deserializer};
+pub use function::DhallFn;
+#[cfg(feature = "json")]
+pub use json::JsonOptions;
+pub use options::{from_file, from_str, serialize, Deserializer, Serializer};
+pub use serialize::ToDhall;
 pub use static_type::StaticType;
 pub use value::{SimpleType, Value};
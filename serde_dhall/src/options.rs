@@ -1,9 +1,14 @@
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 
+use dhall::semantics::{
+    Cache, DefaultFetcher, Fetcher, ImportLocation, ImportPolicy, Session,
+};
+use dhall::syntax::Expr;
 use dhall::Parsed;
 
 use crate::SimpleType;
-use crate::{Error, ErrorKind, FromDhall, Result, StaticType, Value};
+use crate::{Error, ErrorKind, FromDhall, Result, StaticType, ToDhall, Value};
 
 #[derive(Debug, Clone)]
 enum Source<'a> {
@@ -85,8 +90,22 @@ pub struct Deserializer<'a, A> {
     source: Source<'a>,
     annot: A,
     allow_imports: bool,
-    // allow_remote_imports: bool,
-    // use_cache: bool,
+    /// `None` for `DefaultFetcher`.
+    fetcher: Option<&'a dyn Fetcher>,
+    allow_remote_imports: bool,
+    import_policy: ImportPolicy,
+    session: Option<&'a Session>,
+    bindings: Vec<Binding<'a>>,
+    /// `None` to use the cache only with `DefaultFetcher`.
+    use_cache: Option<bool>,
+}
+
+/// A variable added with `Deserializer::with_binding`.
+#[derive(Debug, Clone)]
+struct Binding<'a> {
+    name: String,
+    value: &'a dyn ToDhall,
+    ty: SimpleType,
 }
 
 impl<'a> Deserializer<'a, NoAnnot> {
@@ -95,8 +114,12 @@ impl<'a> Deserializer<'a, NoAnnot> {
             source,
             annot: NoAnnot,
             allow_imports: true,
-            // allow_remote_imports: true,
-            // use_cache: true,
+            fetcher: None,
+            allow_remote_imports: true,
+            import_policy: ImportPolicy::new(),
+            session: None,
+            bindings: Vec::new(),
+            use_cache: None,
         }
     }
     fn from_str(s: &'a str) -> Self {
@@ -155,6 +178,12 @@ impl<'a> Deserializer<'a, NoAnnot> {
             annot: ManualAnnot(ty),
             source: self.source,
             allow_imports: self.allow_imports,
+            fetcher: self.fetcher,
+            allow_remote_imports: self.allow_remote_imports,
+            import_policy: self.import_policy,
+            session: self.session,
+            bindings: self.bindings,
+            use_cache: self.use_cache,
         }
     }
 
@@ -205,6 +234,12 @@ impl<'a> Deserializer<'a, NoAnnot> {
             annot: StaticAnnot,
             source: self.source,
             allow_imports: self.allow_imports,
+            fetcher: self.fetcher,
+            allow_remote_imports: self.allow_remote_imports,
+            import_policy: self.import_policy,
+            session: self.session,
+            bindings: self.bindings,
+            use_cache: self.use_cache,
         }
     }
 }
@@ -241,25 +276,260 @@ impl<'a, A> Deserializer<'a, A> {
         }
     }
 
-    // /// TODO
-    // pub fn remote_imports(&mut self, imports: bool) -> &mut Self {
-    //     self.allow_remote_imports = imports;
-    //     if imports {
-    //         self.allow_imports = true;
-    //     }
-    //     self
-    // }
+    /// Sets how the contents of imports are read.
+    ///
+    /// By default, files are read from disk, urls are downloaded and environment variables are
+    /// read from the process environment. A custom [`Fetcher`] can for example serve imports from
+    /// memory. It is also used to read the file given to [`from_file`].
+    ///
+    /// Setting a fetcher disables the on-disk cache of frozen imports, unless
+    /// [`use_cache`] says otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use std::io;
+    /// use std::path::Path;
+    /// use serde_dhall::Fetcher;
+    /// use url::Url;
+    ///
+    /// struct InMemory;
+    ///
+    /// impl Fetcher for InMemory {
+    ///     fn read_file(&self, path: &Path) -> io::Result<String> {
+    ///         match path.file_name().and_then(|name| name.to_str()) {
+    ///             Some("other_file.dhall") => Ok("30".to_string()),
+    ///             _ => Err(io::Error::new(io::ErrorKind::NotFound, "no such file")),
+    ///         }
+    ///     }
+    ///     fn fetch_url(&self, _: &Url, _: &[(String, String)]) -> io::Result<String> {
+    ///         Err(io::Error::new(io::ErrorKind::Other, "no network"))
+    ///     }
+    ///     fn read_env(&self, _: &str) -> Option<String> {
+    ///         None
+    ///     }
+    /// }
+    ///
+    /// let data = "12 + ./other_file.dhall : Natural";
+    /// let n: u64 = serde_dhall::from_str(data).fetcher(&InMemory).parse()?;
+    /// assert_eq!(n, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Fetcher`]: trait.Fetcher.html
+    /// [`from_file`]: fn.from_file.html
+    /// [`use_cache`]: struct.Deserializer.html#method.use_cache
+    pub fn fetcher(self, fetcher: &'a dyn Fetcher) -> Self {
+        Deserializer {
+            fetcher: Some(fetcher),
+            ..self
+        }
+    }
+
+    /// Sets whether to read and write frozen imports in the on-disk cache, found in
+    /// `$XDG_CACHE_HOME/dhall` or `~/.cache/dhall`.
+    ///
+    /// By default, the cache is used unless a custom [`Fetcher`] is set. With a [`Session`], the
+    /// cache of the session is used instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let n: u64 = serde_dhall::from_str("1 + 1").use_cache(false).parse()?;
+    /// assert_eq!(n, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Fetcher`]: trait.Fetcher.html
+    /// [`Session`]: struct.Session.html
+    pub fn use_cache(self, use_cache: bool) -> Self {
+        Deserializer {
+            use_cache: Some(use_cache),
+            ..self
+        }
+    }
+
+    fn get_fetcher(&self) -> &'a dyn Fetcher {
+        self.fetcher.unwrap_or(&DefaultFetcher)
+    }
+
+    /// Sets whether to enable remote imports.
+    ///
+    /// By default, remote imports are enabled. Enabling them also enables imports.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let data = "12 + https://example.com/other_file.dhall : Natural";
+    /// assert!(
+    ///     serde_dhall::from_str(data)
+    ///         .remote_imports(false)
+    ///         .parse::<u64>()
+    ///         .is_err()
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remote_imports(self, imports: bool) -> Self {
+        Deserializer {
+            allow_remote_imports: imports,
+            allow_imports: self.allow_imports || imports,
+            ..self
+        }
+    }
+
+    /// Restricts which files, urls and environment variables imports may read.
+    ///
+    /// By default, all imports are allowed. See [`ImportPolicy`] for the available restrictions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde_dhall::ImportPolicy;
+    ///
+    /// let policy = ImportPolicy::new()
+    ///     .allow_local_root("/etc/myapp")
+    ///     .deny_env_vars();
+    /// assert!(
+    ///     serde_dhall::from_str("env:HOME as Text")
+    ///         .import_policy(policy)
+    ///         .parse::<String>()
+    ///         .is_err()
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ImportPolicy`]: struct.ImportPolicy.html
+    pub fn import_policy(self, policy: ImportPolicy) -> Self {
+        Deserializer {
+            import_policy: policy,
+            ..self
+        }
+    }
+
+    /// Reuses the imports resolved by earlier calls with the same [`Session`], and stores the new
+    /// ones in it.
+    ///
+    /// This avoids reading, parsing and typechecking shared imports again when loading many
+    /// values. Imports that changed since they were stored are resolved again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde_dhall::Session;
+    ///
+    /// let session = Session::new();
+    /// let sum = |data| serde_dhall::from_str(data).session(&session).parse::<u64>();
+    /// assert_eq!(sum("env:NOT_SET ? 1")?, 1);
+    /// assert_eq!(sum("(env:NOT_SET ? 1) + 1")?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Session`]: struct.Session.html
+    pub fn session(self, session: &'a Session) -> Self {
+        Deserializer {
+            session: Some(session),
+            ..self
+        }
+    }
+
+    /// Makes `value` available to the Dhall code as the variable `name`.
+    ///
+    /// The value is converted like with [`serialize`], with the type given by its [`StaticType`],
+    /// so that e.g. enums can be passed. If the same name is bound several times, the last
+    /// binding is the one named `name`, and the previous ones can be reached with `name@1` etc.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde::Serialize;
+    /// use serde_dhall::StaticType;
+    ///
+    /// #[derive(Serialize, StaticType)]
+    /// enum Region {
+    ///     EuWest,
+    ///     UsEast,
+    /// }
+    ///
+    /// let data = r#"
+    ///     merge { EuWest = "eu.example.com", UsEast = "us.example.com" } region
+    /// "#;
+    /// let host: String = serde_dhall::from_str(data)
+    ///     .with_binding("region", &Region::EuWest)
+    ///     .parse()?;
+    /// assert_eq!(host, "eu.example.com");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`serialize`]: fn.serialize.html
+    /// [`StaticType`]: trait.StaticType.html
+    pub fn with_binding<T>(mut self, name: &str, value: &'a T) -> Self
+    where
+        T: ToDhall + StaticType,
+    {
+        self.bindings.push(Binding {
+            name: name.to_owned(),
+            value,
+            ty: T::static_type(),
+        });
+        self
+    }
+
+    fn parse_source(&self) -> dhall::error::Result<Parsed> {
+        match &self.source {
+            Source::Str(s) => Parsed::parse_str(s),
+            Source::File(p) => {
+                let text = self.get_fetcher().read_file(p)?;
+                Parsed::parse_str_at(&text, ImportLocation::local(p))
+            }
+        }
+    }
 
-    fn _parse<T>(&self) -> dhall::error::Result<Value>
+    /// Returns the value along with the source expression, to locate deserialization errors in.
+    fn _parse<T>(
+        &self,
+        bindings: &[(&str, Value)],
+    ) -> dhall::error::Result<(Value, Expr)>
     where
         T: HasAnnot<A>,
     {
-        let parsed = match &self.source {
-            Source::Str(s) => Parsed::parse_str(s)?,
-            Source::File(p) => Parsed::parse_file(p.as_ref())?,
-        };
+        let mut parsed = self.parse_source()?;
+        let source = parsed.to_expr();
+        // The last binding must be the innermost `let`.
+        for (name, value) in bindings.iter().rev() {
+            parsed = parsed.with_binding(name, value.as_hir())?;
+        }
         let resolved = if self.allow_imports {
-            parsed.resolve()?
+            let policy = if self.allow_remote_imports {
+                Cow::Borrowed(&self.import_policy)
+            } else {
+                Cow::Owned(self.import_policy.clone().deny_remote())
+            };
+            let fetcher = self.get_fetcher();
+            match self.session {
+                Some(session) => {
+                    parsed.resolve_in_with(session, fetcher, &policy)?
+                }
+                None if self.use_cache.unwrap_or(self.fetcher.is_none()) => {
+                    parsed.resolve_with(fetcher, &policy)?
+                }
+                None => parsed.resolve_with_cache(
+                    fetcher,
+                    &policy,
+                    &Cache::disabled(),
+                )?,
+            }
         } else {
             parsed.skip_resolve()?
         };
@@ -267,7 +537,7 @@ impl<'a, A> Deserializer<'a, A> {
             None => resolved.typecheck()?,
             Some(ty) => resolved.typecheck_with(ty.to_value().as_hir())?,
         };
-        Ok(Value::from_nir(typed.normalize().as_nir()))
+        Ok((Value::from_nir(typed.normalize().as_nir()), source))
     }
 
     /// Parses the chosen dhall value with the options provided.
@@ -289,11 +559,17 @@ impl<'a, A> Deserializer<'a, A> {
     where
         T: FromDhall + HasAnnot<A>,
     {
-        let val = self
-            ._parse::<T>()
+        let bindings = self
+            .bindings
+            .iter()
+            .map(|b| Ok((b.name.as_str(), b.value.to_dhall(Some(&b.ty))?)))
+            .collect::<Result<Vec<_>>>()?;
+        let (val, source) = self
+            ._parse::<T>(&bindings)
             .map_err(ErrorKind::Dhall)
             .map_err(Error)?;
-        T::from_dhall(&val)
+        // Point at where the offending part of the value was written, if it was.
+        T::from_dhall(&val).map_err(|err| err.locate_in(&source))
     }
 }
 
@@ -370,3 +646,172 @@ pub fn from_file<'a, P: AsRef<Path>>(path: P) -> Deserializer<'a, NoAnnot> {
 // pub fn from_url(url: &str) -> Deserializer<'_, NoAnnot> {
 //     Deserializer::from_url(url)
 // }
+
+/// Controls how a value is written to Dhall.
+///
+/// Dhall requires type annotations on empty lists, `None` and union alternatives. Since those
+/// cannot be inferred from a Rust value alone, serializing such values fails unless a type is
+/// provided with [`type_annotation`] or [`static_type_annotation`].
+///
+/// [`type_annotation`]: struct.Serializer.html#method.type_annotation
+/// [`static_type_annotation`]: struct.Serializer.html#method.static_type_annotation
+///
+/// # Examples
+///
+/// ```rust
+/// # fn main() -> serde_dhall::Result<()> {
+/// use serde::Serialize;
+/// use serde_dhall::{serialize, StaticType};
+///
+/// #[derive(Serialize, StaticType)]
+/// struct Config {
+///     hosts: Vec<String>,
+///     port: Option<u64>,
+/// }
+///
+/// let config = Config { hosts: vec![], port: None };
+///
+/// // Without a type, Dhall wouldn't know the type of `[]` or `None`.
+/// assert!(serialize(&config).to_string().is_err());
+///
+/// let string = serialize(&config).static_type_annotation().to_string()?;
+/// assert_eq!(
+///     string,
+///     "{ hosts = [] : List Text, port = None Natural }".to_string()
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Serializer<'a, T, A> {
+    data: &'a T,
+    annot: A,
+}
+
+impl<'a, T> Serializer<'a, T, NoAnnot> {
+    /// Ensures that the serialized value has the provided type.
+    ///
+    /// This is also used to annotate empty lists, `None` and union alternatives.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use std::collections::HashMap;
+    /// use serde_dhall::{from_str, serialize, SimpleType};
+    ///
+    /// let ty = from_str("{ x: List Natural }").parse::<SimpleType>()?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("x".to_string(), Vec::<u64>::new());
+    /// let string = serialize(&data).type_annotation(&ty).to_string()?;
+    /// assert_eq!(string, "{ x = [] : List Natural }".to_string());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn type_annotation<'ty>(
+        self,
+        ty: &'ty SimpleType,
+    ) -> Serializer<'a, T, ManualAnnot<'ty>> {
+        Serializer {
+            annot: ManualAnnot(ty),
+            data: self.data,
+        }
+    }
+
+    /// Ensures that the serialized value has the type of `T`.
+    ///
+    /// `T` must implement the [`StaticType`] trait. If it doesn't, you can use [`type_annotation`]
+    /// to provide a type manually.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde::Serialize;
+    /// use serde_dhall::{serialize, StaticType};
+    ///
+    /// #[derive(Serialize, StaticType)]
+    /// enum Choice {
+    ///     A,
+    ///     B(u64),
+    /// }
+    ///
+    /// let string = serialize(&Choice::B(1)).static_type_annotation().to_string()?;
+    /// assert_eq!(string, "< A | B: Natural >.B 1".to_string());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`type_annotation`]: struct.Serializer.html#method.type_annotation
+    /// [`StaticType`]: trait.StaticType.html
+    pub fn static_type_annotation(self) -> Serializer<'a, T, StaticAnnot> {
+        Serializer {
+            annot: StaticAnnot,
+            data: self.data,
+        }
+    }
+}
+
+impl<'a, T, A> Serializer<'a, T, A> {
+    /// Prints the chosen value with the options provided.
+    ///
+    /// If you enabled static annotations, `T` is required to implement [`StaticType`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let string = serde_dhall::serialize(&(1u64, true)).to_string()?;
+    /// assert_eq!(string, "{ `_1` = 1, `_2` = True }".to_string());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`StaticType`]: trait.StaticType.html
+    pub fn to_string(&self) -> Result<String>
+    where
+        T: ToDhall + HasAnnot<A>,
+    {
+        let val = self.data.to_dhall(T::get_annot(&self.annot).as_ref())?;
+        Ok(val.to_string())
+    }
+}
+
+/// Serialize a value to a string of Dhall text.
+///
+/// This returns a [`Serializer`] object. Call the [`to_string`] method to get the serialized
+/// value, or use other [`Serializer`] methods to control the serialization process.
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> serde_dhall::Result<()> {
+/// use serde::Serialize;
+///
+/// // We use serde's derive feature
+/// #[derive(Serialize)]
+/// struct Point {
+///     x: u64,
+///     y: u64,
+/// }
+///
+/// let point = Point { x: 1, y: 2 };
+/// let string = serde_dhall::serialize(&point).to_string()?;
+///
+/// assert_eq!(string, "{ x = 1, y = 2 }".to_string());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Serializer`]: struct.Serializer.html
+/// [`to_string`]: struct.Serializer.html#method.to_string
+pub fn serialize<T>(data: &T) -> Serializer<'_, T, NoAnnot>
+where
+    T: ToDhall,
+{
+    Serializer {
+        data,
+        annot: NoAnnot,
+    }
+}
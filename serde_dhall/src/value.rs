@@ -1,14 +1,42 @@
+use std::borrow::Borrow;
 use std::collections::{BTreeMap, HashMap};
 
 use dhall::builtins::Builtin;
 use dhall::operations::OpKind;
 use dhall::semantics::{Hir, HirKind, Nir, NirKind};
-use dhall::syntax::{Expr, ExprKind, NumKind, Span};
+use dhall::syntax::{Expr, ExprKind, Label, NumKind, Span};
 
-use crate::{Error, ErrorKind, FromDhall, Result, Sealed};
+use crate::{Error, ErrorKind, FromDhall, Result, Sealed, ToDhall};
 
-#[doc(hidden)]
 /// An arbitrary Dhall value.
+///
+/// A `Value` can be deserialized from any Dhall expression, including functions and types. Its
+/// parts can be inspected with the `as_*` methods or with a path query, without defining Rust
+/// types for all of it. It prints as Dhall source with `Display`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> serde_dhall::Result<()> {
+/// use serde_dhall::Value;
+///
+/// let data = r#"
+///     { servers = [ { host = "a.example.com", port = 8080 } ]
+///     , mode = < Dev | Prod >.Prod
+///     }
+/// "#;
+/// let config: Value = serde_dhall::from_str(data).parse()?;
+///
+/// let port = config.get("servers[0].port").unwrap();
+/// assert_eq!(port.as_natural(), Some(8080));
+/// assert_eq!(config.get("mode").unwrap().as_union().unwrap().0, "Prod");
+/// assert_eq!(
+///     config.get("servers").unwrap().get_type()?.to_string(),
+///     "List { host : Text, port : Natural }"
+/// );
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Debug, Clone)]
 pub struct Value {
     /// Invariant: in normal form
@@ -130,6 +158,363 @@ impl Value {
     pub(crate) fn to_expr(&self) -> Expr {
         self.hir.to_expr(Default::default())
     }
+
+    fn from_hir(hir: &Hir) -> Self {
+        Value::from_nir(&hir.eval_closed_expr())
+    }
+
+    fn kind(&self) -> Option<&ExprKind<Hir>> {
+        match self.hir.kind() {
+            HirKind::Expr(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The value of a `Bool`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("Natural/even 4").parse()?;
+    /// assert_eq!(value.as_bool(), Some(true));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind()? {
+            ExprKind::Num(NumKind::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// The value of a `Natural`, if it fits in a `u64`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("6 * 7").parse()?;
+    /// assert_eq!(value.as_natural(), Some(42));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_natural(&self) -> Option<u64> {
+        match self.kind()? {
+            ExprKind::Num(NumKind::Natural(n)) => n.to_u64(),
+            _ => None,
+        }
+    }
+
+    /// The value of an `Integer`, if it fits in an `i64`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("-3").parse()?;
+    /// assert_eq!(value.as_integer(), Some(-3));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_integer(&self) -> Option<i64> {
+        match self.kind()? {
+            ExprKind::Num(NumKind::Integer(n)) => n.to_i64(),
+            _ => None,
+        }
+    }
+
+    /// The value of a `Double`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("1.5").parse()?;
+    /// assert_eq!(value.as_double(), Some(1.5));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_double(&self) -> Option<f64> {
+        match self.kind()? {
+            ExprKind::Num(NumKind::Double(d)) => Some((*d).into()),
+            _ => None,
+        }
+    }
+
+    /// The value of a `Text` literal. Text with interpolated variables, as found inside
+    /// functions, has no value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str(r#""a" ++ "b""#).parse()?;
+    /// assert_eq!(value.as_text(), Some("ab"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_text(&self) -> Option<&str> {
+        match self.kind()? {
+            ExprKind::TextLit(t) if t.tail().is_empty() => Some(t.head()),
+            _ => None,
+        }
+    }
+
+    /// The elements of a `List`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("[1, 2]").parse()?;
+    /// let naturals: Vec<_> = value.as_list().unwrap().iter().map(|x| x.as_natural()).collect();
+    /// assert_eq!(naturals, vec![Some(1), Some(2)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_list(&self) -> Option<Vec<Value>> {
+        match self.kind()? {
+            ExprKind::EmptyListLit(_) => Some(Vec::new()),
+            ExprKind::NEListLit(xs) => {
+                Some(xs.iter().map(Value::from_hir).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// The contents of an `Optional`: `Some(None)` for `None T`, `Some(Some(x))` for `Some x`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("None Natural").parse()?;
+    /// assert_eq!(value.as_optional(), Some(None));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_optional(&self) -> Option<Option<Value>> {
+        match self.kind()? {
+            ExprKind::SomeLit(x) => Some(Some(Value::from_hir(x))),
+            ExprKind::Op(OpKind::App(f, _)) => match f.kind() {
+                HirKind::Expr(ExprKind::Builtin(Builtin::OptionalNone)) => {
+                    Some(None)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The fields of a record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("{ x = 1, y = True }").parse()?;
+    /// let fields = value.as_record().unwrap();
+    /// assert_eq!(fields.keys().collect::<Vec<_>>(), vec!["x", "y"]);
+    /// assert_eq!(fields["y"].as_bool(), Some(true));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_record(&self) -> Option<BTreeMap<String, Value>> {
+        match self.kind()? {
+            ExprKind::RecordLit(kvs) => Some(
+                kvs.iter()
+                    .map(|(k, v)| (k.into(), Value::from_hir(v)))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// The alternative of a union value, and its payload if it has one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value =
+    ///     serde_dhall::from_str("< Port : Natural | Socket : Text >.Port 80").parse()?;
+    /// let (name, payload) = value.as_union().unwrap();
+    /// assert_eq!(name, "Port");
+    /// assert_eq!(payload.unwrap().as_natural(), Some(80));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_union(&self) -> Option<(&str, Option<Value>)> {
+        /// The label of a union constructor.
+        fn alternative(hir: &Hir) -> Option<&Label> {
+            match hir.kind() {
+                HirKind::Expr(ExprKind::Op(OpKind::Field(ut, l))) => {
+                    match ut.kind() {
+                        HirKind::Expr(ExprKind::UnionType(_)) => Some(l),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+        match self.kind()? {
+            ExprKind::Op(OpKind::App(f, x)) => {
+                let l = alternative(f)?;
+                Some((l.borrow(), Some(Value::from_hir(x))))
+            }
+            ExprKind::Op(OpKind::Field(..)) => {
+                Some((alternative(&self.hir)?.borrow(), None))
+            }
+            _ => None,
+        }
+    }
+
+    /// The part of the value found by following `path`, made of record fields separated by `.`
+    /// and list indices in brackets, e.g. `servers[0].port`. `Some` values are looked through,
+    /// so that optional fields can be navigated. Returns `None` if there is nothing at `path`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value =
+    ///     serde_dhall::from_str("{ a = Some [ { b = 1 }, { b = 2 } ] }").parse()?;
+    /// assert_eq!(value.get("a[1].b").unwrap().as_natural(), Some(2));
+    /// assert!(value.get("a[2].b").is_none());
+    /// assert_eq!(value.get("").unwrap(), value);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get(&self, path: &str) -> Option<Value> {
+        let mut value = self.clone();
+        let mut rest = path;
+        while !rest.is_empty() {
+            if let Some(Some(x)) = value.as_optional() {
+                value = x;
+            }
+            let mut chars = rest.chars();
+            let field = match chars.next() {
+                Some('[') => {
+                    let tail = chars.as_str();
+                    let end = tail.find(']')?;
+                    let index: usize = tail[..end].parse().ok()?;
+                    value = match value.kind()? {
+                        ExprKind::NEListLit(xs) => {
+                            Value::from_hir(xs.get(index)?)
+                        }
+                        _ => return None,
+                    };
+                    rest = &tail[end + 1..];
+                    continue;
+                }
+                _ if rest.len() == path.len() => rest,
+                Some('.') => chars.as_str(),
+                _ => return None,
+            };
+            let end = field.find(&['.', '['][..]).unwrap_or(field.len());
+            value = match value.kind()? {
+                ExprKind::RecordLit(kvs) => {
+                    Value::from_hir(kvs.get(&field[..end])?)
+                }
+                _ => return None,
+            };
+            rest = &field[end..];
+        }
+        Some(value)
+    }
+
+    /// The Dhall type of the value. This fails only for `Sort`, which has no type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// let value: serde_dhall::Value = serde_dhall::from_str("{ x = [1] }").parse()?;
+    /// assert_eq!(value.get_type()?.to_string(), "{ x : List Natural }");
+    ///
+    /// let kind: serde_dhall::Value = serde_dhall::from_str("Kind").parse()?;
+    /// assert_eq!(kind.get_type()?.to_string(), "Sort");
+    /// assert!(kind.get_type()?.get_type().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_type(&self) -> Result<Value> {
+        let tir = self
+            .hir
+            .typecheck_noenv()
+            .map_err(|e| Error(ErrorKind::Dhall(e.into())))?;
+        Ok(Value::from_nir(tir.ty().as_nir()))
+    }
+
+    /// If the value is a function, the type of its argument, when it is a `SimpleType`. Otherwise
+    /// this is the type error of applying the value.
+    pub(crate) fn function_input_type(&self) -> Result<Option<SimpleType>> {
+        let tir = self
+            .hir
+            .typecheck_noenv()
+            .map_err(|e| Error(ErrorKind::Dhall(e.into())))?;
+        match tir.ty().kind() {
+            NirKind::PiClosure { annot, .. } => Ok(SimpleType::from_nir(annot)),
+            // Let the typechecker explain that this is not a function.
+            _ => {
+                let arg = Hir::new(
+                    HirKind::Expr(ExprKind::Builtin(Builtin::Bool)),
+                    Span::Artificial,
+                );
+                self.typecheck_app(&arg)?;
+                unreachable!("only functions can be applied")
+            }
+        }
+    }
+
+    fn typecheck_app(&self, arg: &Hir) -> Result<()> {
+        let app = Hir::new(
+            HirKind::Expr(ExprKind::Op(OpKind::App(
+                self.hir.clone(),
+                arg.clone(),
+            ))),
+            Span::Artificial,
+        );
+        match app.typecheck_noenv() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error(ErrorKind::Dhall(e.into()))),
+        }
+    }
+
+    /// Applies the function to `arg`, and returns the normalized result.
+    ///
+    /// The argument is converted like with [`serialize`], annotated with the argument type of
+    /// the function if it is a [`SimpleType`]. The application is typechecked, so this fails if
+    /// the value is not a function or if the argument has the wrong type. Use [`DhallFn`] to get
+    /// a Rust value back.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> serde_dhall::Result<()> {
+    /// use serde_dhall::Value;
+    ///
+    /// let count: Value =
+    ///     serde_dhall::from_str("λ(names : List Text) → List/length Text names").parse()?;
+    /// let names: Vec<String> = vec![];
+    /// assert_eq!(count.apply(&names)?.to_string(), "0");
+    /// assert!(count.apply(&1u64).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`serialize`]: fn.serialize.html
+    /// [`SimpleType`]: enum.SimpleType.html
+    /// [`DhallFn`]: struct.DhallFn.html
+    pub fn apply<A: ToDhall>(&self, arg: &A) -> Result<Value> {
+        let arg_ty = self.function_input_type()?;
+        let arg = arg.to_dhall(arg_ty.as_ref())?;
+        self.typecheck_app(&arg.hir)?;
+        let result =
+            self.hir.eval_closed_expr().app(arg.hir.eval_closed_expr());
+        Ok(Value::from_nir(&result))
+    }
 }
 
 impl SimpleValue {
@@ -213,6 +598,162 @@ impl SimpleValue {
             _ => return None,
         })
     }
+
+    /// Converts a SimpleValue into a Value, checking that it has type `ty` if provided.
+    ///
+    /// Dhall requires annotations on empty lists, `None` and union alternatives; these are taken
+    /// from `ty`, so this fails if the value contains any of those and `ty` is not provided.
+    pub(crate) fn into_value(self, ty: Option<&SimpleType>) -> Result<Value> {
+        let hir = self.to_hir(ty)?;
+        let annotated = match ty {
+            Some(ty) => Hir::new(
+                HirKind::Expr(ExprKind::Annot(hir.clone(), ty.to_hir())),
+                Span::Artificial,
+            ),
+            None => hir.clone(),
+        };
+        if let Err(e) = annotated.typecheck_noenv() {
+            return Err(Error(ErrorKind::Dhall(e.into())));
+        }
+        // `to_hir` may have converted numbers, filled in missing optional fields or built a
+        // `Prelude.Map`, so `self` no longer describes the value.
+        Ok(Value::from_nir(&hir.eval_closed_expr()))
+    }
+
+    /// Builds the Hir for this value, using `ty` to annotate the parts that need it.
+    fn to_hir(&self, ty: Option<&SimpleType>) -> Result<Hir> {
+        let hir = |k| Hir::new(HirKind::Expr(k), Span::Artificial);
+        let missing_annot = |what: &str| {
+            Error(ErrorKind::Serialize(format!(
+                "cannot serialize {} without a type annotation",
+                what
+            )))
+        };
+        Ok(hir(match (self, ty) {
+            // Allow e.g. an `i64` to be written as a `Natural` if the annotation asks for it.
+            (
+                SimpleValue::Num(NumKind::Integer(n)),
+                Some(SimpleType::Natural),
+            ) if !n.is_negative() => ExprKind::Num(NumKind::Natural(n.abs())),
+            (
+                SimpleValue::Num(NumKind::Natural(n)),
+                Some(SimpleType::Integer),
+            ) => ExprKind::Num(NumKind::Integer(n.clone().into())),
+            (SimpleValue::Num(n), _) => ExprKind::Num(n.clone()),
+            (SimpleValue::Text(t), _) => ExprKind::TextLit(t.clone().into()),
+            (SimpleValue::Optional(None), Some(SimpleType::Optional(t))) => {
+                ExprKind::Op(OpKind::App(
+                    hir(ExprKind::Builtin(Builtin::OptionalNone)),
+                    t.to_hir(),
+                ))
+            }
+            (SimpleValue::Optional(None), _) => {
+                return Err(missing_annot("`None`"))
+            }
+            (SimpleValue::Optional(Some(x)), _) => {
+                let t = match ty {
+                    Some(SimpleType::Optional(t)) => Some(t.as_ref()),
+                    _ => None,
+                };
+                ExprKind::SomeLit(x.to_hir(t)?)
+            }
+            (SimpleValue::List(xs), Some(SimpleType::List(t)))
+                if xs.is_empty() =>
+            {
+                ExprKind::EmptyListLit(SimpleType::List(t.clone()).to_hir())
+            }
+            (SimpleValue::List(xs), _) if xs.is_empty() => {
+                return Err(missing_annot("an empty list"))
+            }
+            (SimpleValue::List(xs), _) => {
+                let t = match ty {
+                    Some(SimpleType::List(t)) => Some(t.as_ref()),
+                    _ => None,
+                };
+                ExprKind::NEListLit(
+                    xs.iter().map(|x| x.to_hir(t)).collect::<Result<_>>()?,
+                )
+            }
+            // Records can be written as a `Prelude.Map` if the annotation asks for it.
+            (SimpleValue::Record(kvs), Some(SimpleType::List(t))) => {
+                let val_ty = match t.as_ref() {
+                    SimpleType::Record(kts)
+                        if kts.len() == 2
+                            && kts.get("mapKey") == Some(&SimpleType::Text) =>
+                    {
+                        kts.get("mapValue")
+                    }
+                    _ => None,
+                };
+                let val_ty = match val_ty {
+                    Some(val_ty) => val_ty,
+                    None => return Err(missing_annot("a map")),
+                };
+                if kvs.is_empty() {
+                    ExprKind::EmptyListLit(SimpleType::List(t.clone()).to_hir())
+                } else {
+                    ExprKind::NEListLit(
+                        kvs.iter()
+                            .map(|(k, v)| {
+                                let mut entry = BTreeMap::new();
+                                entry.insert(
+                                    Label::from("mapKey"),
+                                    hir(ExprKind::TextLit(k.clone().into())),
+                                );
+                                entry.insert(
+                                    Label::from("mapValue"),
+                                    v.to_hir(Some(val_ty))?,
+                                );
+                                Ok(hir(ExprKind::RecordLit(entry)))
+                            })
+                            .collect::<Result<_>>()?,
+                    )
+                }
+            }
+            (SimpleValue::Record(kvs), _) => {
+                let kts = match ty {
+                    Some(SimpleType::Record(kts)) => Some(kts),
+                    _ => None,
+                };
+                let mut fields = kvs
+                    .iter()
+                    .map(|(k, v)| {
+                        let t = kts.and_then(|kts| kts.get(k));
+                        Ok((Label::from(k.as_str()), v.to_hir(t)?))
+                    })
+                    .collect::<Result<BTreeMap<_, _>>>()?;
+                // Fill in optional fields that were omitted, like the deserializer allows.
+                for (k, t) in kts.into_iter().flatten() {
+                    if let SimpleType::Optional(_) = t {
+                        if !kvs.contains_key(k) {
+                            let none = SimpleValue::Optional(None);
+                            fields.insert(
+                                Label::from(k.as_str()),
+                                none.to_hir(Some(t))?,
+                            );
+                        }
+                    }
+                }
+                ExprKind::RecordLit(fields)
+            }
+            (SimpleValue::Union(variant, x), Some(SimpleType::Union(kts))) => {
+                let alternative = hir(ExprKind::Op(OpKind::Field(
+                    ty.unwrap().to_hir(),
+                    Label::from(variant.as_str()),
+                )));
+                match x {
+                    None => return Ok(alternative),
+                    Some(x) => {
+                        let t = kts.get(variant).and_then(Option::as_ref);
+                        ExprKind::Op(OpKind::App(alternative, x.to_hir(t)?))
+                    }
+                }
+            }
+            (SimpleValue::Union(..), _) => {
+                return Err(missing_annot("an enum"))
+            }
+        }))
+    }
 }
 
 impl SimpleType {
@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_dhall::{from_str, FromDhall, StaticType};
 
 #[test]
@@ -116,5 +116,303 @@ fn test_de_untyped() {
     assert!(from_str("List/length [True, 42]").parse::<bool>().is_err());
 }
 
+#[test]
+fn test_de_fetcher() {
+    let fetcher = dhall::semantics::InMemoryFetcher::new()
+        .file("/config/base.dhall", "{ x = 1 }")
+        .file("/config/main.dhall", "(./base.dhall).x")
+        .url("https://example.com/y.dhall", "./z.dhall")
+        .url("https://example.com/z.dhall", "2")
+        .env("GREETING", "hello");
+
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    struct Foo {
+        x: u64,
+        y: u64,
+        z: String,
+    }
+    let data = "/config/base.dhall /\\ { y = https://example.com/y.dhall, \
+                z = env:GREETING as Text }";
+    assert_eq!(
+        from_str(data).fetcher(&fetcher).parse::<Foo>().unwrap(),
+        Foo {
+            x: 1,
+            y: 2,
+            z: "hello".to_owned(),
+        }
+    );
+
+    // The file given to `from_file` is read through the fetcher too.
+    assert_eq!(
+        serde_dhall::from_file("/config/main.dhall")
+            .fetcher(&fetcher)
+            .parse::<u64>()
+            .unwrap(),
+        1
+    );
+
+    // Missing entries are import errors, and can be recovered from with `?`.
+    assert!(from_str("./missing.dhall")
+        .fetcher(&fetcher)
+        .parse::<u64>()
+        .is_err());
+    assert_eq!(
+        from_str("env:MISSING ? 3")
+            .fetcher(&fetcher)
+            .parse::<u64>()
+            .unwrap(),
+        3
+    );
+
+    // Disabling remote imports also forbids the hosts allowed by the policy.
+    let policy = serde_dhall::ImportPolicy::new().allow_host("example.com");
+    let parse = |remote_imports: bool| {
+        from_str("https://example.com/z.dhall")
+            .fetcher(&fetcher)
+            .import_policy(policy.clone())
+            .remote_imports(remote_imports)
+            .parse::<u64>()
+    };
+    assert_eq!(parse(true).unwrap(), 2);
+    assert!(parse(false).is_err());
+}
+
+#[test]
+fn test_de_across_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<serde_dhall::Value>();
+    assert_send_sync::<serde_dhall::SimpleType>();
+    assert_send_sync::<serde_dhall::Error>();
+
+    // The function body is only normalized when printed, concurrently.
+    let value: serde_dhall::Value =
+        from_str("{ n = 1, f = λ(x : Natural) → x + (2 + 3) }")
+            .parse()
+            .unwrap();
+    let value = std::sync::Arc::new(value);
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let value = value.clone();
+            std::thread::spawn(move || value.to_string())
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(
+            thread.join().unwrap(),
+            "{ f = λ(x : Natural) → x + 5, n = 1 }"
+        );
+    }
+}
+
+#[test]
+fn test_de_function() {
+    use serde_dhall::{DhallFn, Value};
+
+    let f: Value = from_str(
+        "λ(x : Natural) → λ(y : Optional Natural) → \
+         x + merge { None = 0, Some = λ(n : Natural) → n } y",
+    )
+    .parse()
+    .unwrap();
+    let g = f.apply(&2u64).unwrap();
+    assert_eq!(g.apply(&Some(3u64)).unwrap().to_string(), "5");
+    assert_eq!(g.apply(&None::<u64>).unwrap().to_string(), "2");
+    assert!(g.apply(&"x").is_err());
+    let err = g.apply(&None::<u64>).unwrap().apply(&1u64).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected function, found `Natural`"));
+
+    let add: DhallFn<(u64, u64), u64> =
+        from_str("λ(p : { _1 : Natural, _2 : Natural }) → p._1 + p._2")
+            .parse()
+            .unwrap();
+    assert_eq!(add.call(&(1, 2)).unwrap(), 3);
+    let wrong: DhallFn<u64, bool> =
+        from_str("λ(n : Natural) → n").parse().unwrap();
+    assert!(wrong.call(&1).is_err());
+    assert!(from_str("1").parse::<DhallFn<u64, u64>>().is_err());
+}
+
+#[test]
+fn test_de_with_binding() {
+    #[derive(Serialize, StaticType)]
+    struct Host {
+        name: String,
+        tags: Vec<String>,
+    }
+    let host = Host {
+        name: "web1".to_owned(),
+        tags: vec![],
+    };
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Out {
+        name: String,
+        tags: u64,
+        n: u64,
+        m: u64,
+    }
+    let data = "{ name = host.name, tags = List/length Text host.tags, n = n, m = n@1 }";
+    assert_eq!(
+        from_str(data)
+            .with_binding("n", &1u64)
+            .with_binding("host", &host)
+            .with_binding("n", &2u64)
+            .parse::<Out>()
+            .unwrap(),
+        Out {
+            name: "web1".to_owned(),
+            tags: 0,
+            n: 2,
+            m: 1,
+        }
+    );
+
+    // The binding has the static type of the Rust value.
+    assert!(from_str("n + 1")
+        .with_binding("n", &1i64)
+        .parse::<u64>()
+        .is_err());
+    assert!(from_str("n").parse::<u64>().is_err());
+}
+
+#[test]
+fn test_de_value_navigation() {
+    use serde_dhall::Value;
+
+    let data = r#"
+        { servers =
+            [ { host = "a", port = Some 80, tags = [] : List Text }
+            , { host = "b", port = None Natural, tags = [ "x" ] }
+            ]
+        , mode = < Dev | Prod : Natural >.Dev
+        , f = λ(x : Natural) → x
+        , `weird name` = -1
+        }
+    "#;
+    let value: Value = from_str(data).parse().unwrap();
+    let get = |path| value.get(path);
+
+    assert_eq!(
+        get("servers[0].port")
+            .unwrap()
+            .as_optional()
+            .unwrap()
+            .unwrap()
+            .as_natural(),
+        Some(80)
+    );
+    assert_eq!(get("servers[1].port").unwrap().as_optional(), Some(None));
+    assert_eq!(get("servers[0].host").unwrap().as_text(), Some("a"));
+    assert_eq!(get("servers[0].tags").unwrap().as_list().unwrap().len(), 0);
+    assert_eq!(get("servers[1].tags[0]").unwrap().as_text(), Some("x"));
+    assert_eq!(
+        get("mode")
+            .unwrap()
+            .as_union()
+            .map(|(l, x)| (l, x.is_none())),
+        Some(("Dev", true))
+    );
+    assert_eq!(get("weird name").unwrap().as_integer(), Some(-1));
+    assert_eq!(
+        get("f").unwrap().get_type().unwrap().to_string(),
+        "∀(x : Natural) → Natural"
+    );
+    assert_eq!(get("f").unwrap().as_record(), None);
+
+    for path in &[
+        "servers[2]",
+        "servers[a]",
+        "servers[0",
+        "servers.host",
+        "mode.Dev",
+        ".servers",
+        "servers[0]host",
+    ] {
+        assert_eq!(get(path), None, "{}", path);
+    }
+}
+
+#[test]
+fn test_de_error_path() {
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    enum Tls {
+        Off,
+        On { port: u16 },
+    }
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Server {
+        host: String,
+        tls: Tls,
+    }
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Config {
+        servers: Vec<Server>,
+    }
+    let parse = |data: &str| from_str(data).parse::<Config>().unwrap_err();
+
+    let data = r#"
+        let Tls = < Off | On : { port : Natural } >
+        in  { servers =
+              [ { host = "a", tls = Tls.Off }
+              , { host = "b", tls = Tls.On { port = 70000 } }
+              ]
+            }
+    "#;
+    let err = parse(data);
+    assert_eq!(err.path().as_deref(), Some(".servers[1].tls.On.port"));
+    let span = err.span().unwrap();
+    assert_eq!(&data[span.start()..span.end()], "70000");
+    assert!(err
+        .to_string()
+        .contains(".servers[1].tls.On.port: invalid value"));
+
+    // Missing fields are reported on the record.
+    let err = parse(r#"{ servers = [ { name = "a", tls = < Off >.Off } ] }"#);
+    assert_eq!(err.path().as_deref(), Some(".servers[0]"));
+    assert!(err.span().is_some());
+
+    // The path is known even when the value was computed.
+    let err = parse(
+        r#"{ servers = [ { host = 1, tls = < Off >.Off } ] # ([] : List { host : Natural, tls : < Off > }) }"#,
+    );
+    assert_eq!(err.path().as_deref(), Some(".servers[0].host"));
+    assert!(err.span().is_none());
+    assert!(parse("{ servers = 1 }").path().is_some());
+    assert!(from_str("1")
+        .parse::<String>()
+        .unwrap_err()
+        .path()
+        .is_none());
+}
+
+#[test]
+fn test_de_big_numbers() {
+    let value: serde_dhall::Value =
+        from_str("18446744073709551615 * 2").parse().unwrap();
+    assert_eq!(value.to_string(), "36893488147419103230");
+    assert_eq!(value.as_natural(), None);
+    assert_eq!(
+        from_str("Integer/negate +9223372036854775808")
+            .parse::<i64>()
+            .unwrap(),
+        i64::MIN
+    );
+
+    let err = from_str("18446744073709551615 + 1")
+        .parse::<u64>()
+        .unwrap_err();
+    assert!(err.to_string().contains(
+        "invalid value: integer `18446744073709551616`, expected u64"
+    ));
+    let err = from_str("{ x = -9223372036854775809 }")
+        .parse::<std::collections::HashMap<String, i64>>()
+        .unwrap_err();
+    assert_eq!(err.path().as_deref(), Some(".x"));
+}
+
 // TODO: test various builder configurations
 // In particular test cloning and reusing builder
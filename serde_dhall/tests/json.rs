@@ -0,0 +1,122 @@
+#![cfg(feature = "json")]
+use serde_dhall::{from_str, JsonOptions, SimpleType, Value};
+
+fn to_json(s: &str, options: JsonOptions) -> String {
+    let value: Value = from_str(s).parse().unwrap();
+    value.to_json(options).unwrap().to_string()
+}
+
+#[test]
+fn test_to_json() {
+    let default = JsonOptions::default();
+    assert_eq!(to_json("[1, 2]", default), "[1,2]");
+    assert_eq!(
+        to_json("{ x = +1, y = -1.5, z = True }", default),
+        r#"{"x":1,"y":-1.5,"z":true}"#
+    );
+    assert_eq!(to_json(r#"Some "foo""#, default), r#""foo""#);
+    assert_eq!(to_json("None Natural", default), "null");
+    assert_eq!(to_json(r#"toMap { a = "x" }"#, default), r#"{"a":"x"}"#);
+    assert_eq!(
+        to_json(
+            "[ < A | B : Natural >.A, < A | B : Natural >.B 1 ]",
+            default
+        ),
+        r#"["A",1]"#
+    );
+
+    let fields = "{ x = None Natural, y = {=}, z = [] : List Natural }";
+    assert_eq!(to_json(fields, default), r#"{"y":{},"z":[]}"#);
+    let preserve_null = JsonOptions {
+        preserve_null: true,
+        ..default
+    };
+    assert_eq!(
+        to_json(fields, preserve_null),
+        r#"{"x":null,"y":{},"z":[]}"#
+    );
+    let omit_empty = JsonOptions {
+        omit_empty: true,
+        ..preserve_null
+    };
+    assert_eq!(to_json(fields, omit_empty), r#"{"z":[]}"#);
+
+    let value: Value = from_str("λ(x : Natural) → x").parse().unwrap();
+    assert!(value.to_json(default).is_err());
+    let value: Value = from_str("{ x = Infinity }").parse().unwrap();
+    assert!(value.to_json(default).is_err());
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_to_yaml() {
+    let value: Value = from_str("[{ x = 1 }, { x = 2 }]").parse().unwrap();
+    assert_eq!(
+        value.to_yaml_string(JsonOptions::default()).unwrap(),
+        "- x: 1\n- x: 2\n"
+    );
+    let documents = JsonOptions {
+        documents: true,
+        ..JsonOptions::default()
+    };
+    assert_eq!(
+        value.to_yaml_string(documents).unwrap(),
+        "---\nx: 1\n---\nx: 2\n"
+    );
+}
+
+#[test]
+fn test_from_json() {
+    fn from_json(json: &str, ty: &str) -> String {
+        let ty: SimpleType = from_str(ty).parse().unwrap();
+        Value::from_json_str(json, &ty).unwrap().to_string()
+    }
+
+    assert_eq!(from_json("[]", "List Natural"), "[] : List Natural");
+    assert_eq!(from_json("[1, -2]", "List Integer"), "[+1, -2]");
+    assert_eq!(
+        from_json(
+            r#"{ "x": 1.5, "z": null }"#,
+            "{ x : Double, y : Optional Bool, z : Optional Text }"
+        ),
+        "{ x = 1.5, y = None Bool, z = None Text }"
+    );
+    assert_eq!(
+        from_json(
+            r#"{ "a": true }"#,
+            "List { mapKey : Text, mapValue : Bool }"
+        ),
+        r#"[{ mapKey = "a", mapValue = True }]"#
+    );
+    assert_eq!(
+        from_json(r#"["A", 1, "x"]"#, "List < A | B : Natural | C : Text >"),
+        r#"[< A | B: Natural | C: Text >.A, < A | B: Natural | C: Text >.B 1, < A | B: Natural | C: Text >.C "x"]"#
+    );
+
+    // The converted value is what gets converted back.
+    let ty: SimpleType = from_str("{ x : Integer, y : Optional Bool }")
+        .parse()
+        .unwrap();
+    let value = Value::from_json_str(r#"{ "x": 1 }"#, &ty).unwrap();
+    let options = JsonOptions {
+        preserve_null: true,
+        ..JsonOptions::default()
+    };
+    assert_eq!(
+        value.to_json(options).unwrap().to_string(),
+        r#"{"x":1,"y":null}"#
+    );
+
+    let ty: SimpleType = from_str("{ x : Natural }").parse().unwrap();
+    assert!(Value::from_json_str("{}", &ty).is_err());
+    assert!(Value::from_json_str(r#"{ "x": -1 }"#, &ty).is_err());
+    assert!(Value::from_json_str(r#"{ "x": 1, "y": 2 }"#, &ty).is_err());
+}
+
+#[test]
+#[cfg(feature = "yaml")]
+fn test_from_yaml() {
+    let ty: SimpleType = from_str("{ x : List Text }").parse().unwrap();
+    let value = Value::from_yaml_str("x:\n  - foo\n  - bar\n", &ty).unwrap();
+    assert_eq!(value.to_string(), r#"{ x = ["foo", "bar"] }"#);
+}
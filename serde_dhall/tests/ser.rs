@@ -0,0 +1,144 @@
+use serde::Serialize;
+use serde_dhall::{from_str, serialize, SimpleType, StaticType, ToDhall};
+
+#[test]
+fn test_serialize_typed() {
+    fn assert_serialize<T: ToDhall + StaticType>(x: &T, s: &str) {
+        assert_eq!(
+            serialize(x).static_type_annotation().to_string().unwrap(),
+            s
+        );
+    }
+
+    assert_serialize(&true, "True");
+
+    assert_serialize(&1u64, "1");
+    assert_serialize(&1u32, "1");
+    assert_serialize(&1usize, "1");
+
+    assert_serialize(&1i64, "+1");
+    assert_serialize(&-1i32, "-1");
+
+    assert_serialize(&1.0f64, "1.0");
+    assert_serialize(&1.5f32, "1.5");
+
+    assert_serialize(&"foo".to_owned(), r#""foo""#);
+    assert_serialize(&<Vec<u64>>::new(), "[] : List Natural");
+    assert_serialize(&vec![1u64, 2], "[1, 2]");
+    assert_serialize(&<Option<u64>>::None, "None Natural");
+    assert_serialize(&Some(1u64), "Some 1");
+
+    assert_serialize(
+        &(1u64, "foo".to_owned()),
+        r#"{ `_1` = 1, `_2` = "foo" }"#,
+    );
+
+    #[derive(Serialize, StaticType)]
+    struct Foo {
+        x: u64,
+        y: Vec<Option<i64>>,
+    }
+    assert_serialize(
+        &Foo {
+            x: 1,
+            y: vec![None, Some(-2)],
+        },
+        "{ x = 1, y = [None Integer, Some -2] }",
+    );
+
+    #[derive(Serialize, StaticType)]
+    #[allow(dead_code)]
+    enum Bar {
+        X(u64),
+        Y(i64),
+    }
+    assert_serialize(&Bar::X(1), "< X: Natural | Y: Integer >.X 1");
+
+    #[derive(Serialize, StaticType)]
+    #[allow(dead_code)]
+    enum Baz {
+        X,
+        Y(i64),
+    }
+    assert_serialize(&Baz::X, "< X | Y: Integer >.X");
+}
+
+#[test]
+fn test_serialize_untyped() {
+    use std::collections::BTreeMap;
+
+    fn assert_serialize<T: ToDhall>(x: &T, s: &str) {
+        assert_eq!(serialize(x).to_string().unwrap(), s);
+    }
+
+    assert_serialize(&vec![true, false], "[True, False]");
+    assert_serialize(&Some(vec![1u64]), "Some [1]");
+    assert_serialize(&(), "{=}");
+
+    let mut map = BTreeMap::new();
+    map.insert("x".to_string(), 1u64);
+    map.insert("if".to_string(), 2u64);
+    assert_serialize(&map, "{ `if` = 2, x = 1 }");
+
+    #[derive(Serialize)]
+    struct Foo {
+        x: u64,
+        y: (bool, String),
+    }
+    assert_serialize(
+        &Foo {
+            x: 1,
+            y: (true, "bar".to_owned()),
+        },
+        r#"{ x = 1, y = { `_1` = True, `_2` = "bar" } }"#,
+    );
+
+    // Types can't be inferred for these.
+    assert!(serialize(&<Vec<u64>>::new()).to_string().is_err());
+    assert!(serialize(&<Option<u64>>::None).to_string().is_err());
+    #[derive(Serialize)]
+    enum Bar {
+        X,
+    }
+    assert!(serialize(&Bar::X).to_string().is_err());
+}
+
+#[test]
+fn test_serialize_annot() {
+    use std::collections::HashMap;
+
+    fn parse(s: &str) -> SimpleType {
+        from_str(s).parse().unwrap()
+    }
+
+    let mut map = HashMap::new();
+    map.insert("foo".to_string(), Vec::<u64>::new());
+    assert_eq!(
+        serialize(&map)
+            .type_annotation(&parse(
+                "List { mapKey: Text, mapValue: List Natural }"
+            ))
+            .to_string()
+            .unwrap(),
+        r#"[{ mapKey = "foo", mapValue = [] : List Natural }]"#
+    );
+
+    // Omitted optional fields are filled in.
+    #[derive(Serialize)]
+    struct Foo {
+        x: u64,
+    }
+    assert_eq!(
+        serialize(&Foo { x: 1 })
+            .type_annotation(&parse("{ x: Natural, y: Optional Text }"))
+            .to_string()
+            .unwrap(),
+        "{ x = 1, y = None Text }"
+    );
+
+    // The value is checked against the annotation.
+    assert!(serialize(&Foo { x: 1 })
+        .type_annotation(&parse("{ x: Text }"))
+        .to_string()
+        .is_err());
+}